@@ -102,7 +102,9 @@ fn expr(x: &AstExpr, res: &mut Vec<Bind>) {
             res.push(Bind::Scope(Scope::new(inner)));
         }
 
-        Expr::ListComprehension(x, clauses) => comprehension(clauses, res, |res| expr(x, res)),
+        Expr::ListComprehension(x, clauses) | Expr::SetComprehension(x, clauses) => {
+            comprehension(clauses, res, |res| expr(x, res))
+        }
         Expr::DictComprehension(x, clauses) => comprehension(clauses, res, |res| {
             expr(&x.0, res);
             expr(&x.1, res)
@@ -188,6 +190,12 @@ fn stmt(x: &AstStmt, res: &mut Vec<Bind>) {
             stmt(body, res);
             flow(res)
         }
+        Stmt::While(cond, box body) => {
+            expr(cond, res);
+            flow(res);
+            stmt(body, res);
+            flow(res)
+        }
         Stmt::Load(_, args, _) => {
             for x in args {
                 res.push(Bind::Set(Assigner::Load, x.0.clone()))
@@ -201,3 +209,30 @@ pub fn scope(module: &AstModule) -> Scope {
     stmt(&module.statement, &mut res);
     Scope::new(res)
 }
+
+/// Every identifier, in the order they are bound or referenced, along with
+/// where it is bound, if that is within this module.
+pub fn resolve(scope: &Scope) -> Vec<(&AstString, Option<(Assigner, Span)>)> {
+    fn f<'a>(
+        scope: &'a Scope,
+        parents: &mut Vec<&'a Scope>,
+        res: &mut Vec<(&'a AstString, Option<(Assigner, Span)>)>,
+    ) {
+        parents.push(scope);
+        for x in &scope.inner {
+            match x {
+                Bind::Set(_, x) | Bind::Get(x) => {
+                    let binding = parents.iter().rev().find_map(|s| s.bound.get(&x.node));
+                    res.push((x, binding.copied()))
+                }
+                Bind::Scope(inner) => f(inner, parents, res),
+                Bind::Flow => {}
+            }
+        }
+        parents.pop();
+    }
+
+    let mut res = Vec::new();
+    f(scope, &mut Vec::new(), &mut res);
+    res
+}
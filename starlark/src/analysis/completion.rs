@@ -0,0 +1,331 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Completions while editing. The code being edited usually doesn't parse, so
+//! we insert a marker identifier at the cursor, possibly followed by something
+//! to close an open bracket or string, and look for where the marker ended up.
+
+use crate::{
+    analysis::{
+        bind::{self, Assigner},
+        definition::find_identifier,
+        hover::find_def_stmt,
+    },
+    environment::{Globals, GlobalsBuilder},
+    syntax::{
+        ast::{Argument, AstExpr, AstLiteral, AstParameter, AstStmt, Expr, Parameter, Stmt},
+        parse, AstModule, Dialect,
+    },
+    values::{Value, ValueLike},
+};
+use gazebo::prelude::*;
+use std::collections::HashSet;
+
+const MARKER: &str = "__starlark_completion__";
+
+// What to try writing after the marker, to close any open brackets or strings
+const CLOSERS: &[&str] = &["", ")", "]", "}", "\")", "\""];
+
+/// The kind of a [`Completion`].
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// A variable in scope, or a global that isn't a function.
+    Variable,
+    /// A global function.
+    Function,
+    /// A named argument of the function being called.
+    NamedArgument,
+    /// An attribute or method, after a `.`.
+    Member,
+}
+
+/// Something that could be written at the cursor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub name: String,
+    pub kind: CompletionKind,
+}
+
+/// What could be written at the cursor, as returned by [`completions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Completions {
+    /// These names, most relevant first. The caller should filter them by
+    /// whatever has been typed so far.
+    Names(Vec<Completion>),
+    /// A symbol exported by this module, named in a `load`, which the caller
+    /// must resolve.
+    Load(String),
+}
+
+/// Work out what could be written at a position (0-based line, and column in
+/// characters) in some source code, which doesn't have to parse.
+/// Returns `None` if we can't make sense of the code.
+pub fn completions(
+    content: &str,
+    dialect: &Dialect,
+    globals: &Globals,
+    line: usize,
+    column: usize,
+) -> Option<Completions> {
+    let start = content
+        .split('\n')
+        .take(line)
+        .map(|x| x.len() + 1)
+        .sum::<usize>();
+    let text = content.get(start..)?.split('\n').next()?;
+    let offset = start + text.char_indices().nth(column).map_or(text.len(), |x| x.0);
+    for closer in CLOSERS {
+        let code = format!(
+            "{}{}{}{}",
+            &content[..offset],
+            MARKER,
+            closer,
+            &content[offset..]
+        );
+        if let Ok(module) = parse("completion.star", code, dialect) {
+            return Some(complete(&module, globals));
+        }
+    }
+    None
+}
+
+fn complete(module: &AstModule, globals: &Globals) -> Completions {
+    if let Some(path) = load_module(module) {
+        return Completions::Load(path);
+    }
+    let mut member = None;
+    let mut call = None;
+    module
+        .statement
+        .visit_expr(|x| find_marker(x, &mut member, &mut call));
+    let mut res = Vec::new();
+    match member {
+        Some(x) => {
+            let kind = CompletionKind::Member;
+            res.extend(members(x, globals).into_map(|name| Completion { name, kind }));
+        }
+        None => names(module, globals, call, &mut res),
+    }
+    // A name may be bound in several scopes, but only the first is relevant
+    let mut seen = HashSet::new();
+    res.retain(|x| !x.name.contains(MARKER) && seen.insert(x.name.clone()));
+    Completions::Names(res)
+}
+
+// The module of a `load` whose symbols include the marker
+fn load_module(module: &AstModule) -> Option<String> {
+    let mut res = None;
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Load(path, args, _) = &x.node {
+            if args.iter().any(|x| x.1.node.contains(MARKER)) {
+                res = Some(path.node.clone());
+            }
+        }
+    });
+    res
+}
+
+fn is_marker(x: &AstExpr) -> bool {
+    match &x.node {
+        Expr::Identifier(x) => x.node.contains(MARKER),
+        _ => false,
+    }
+}
+
+// Find a `.` attribute containing the marker, or a call with the marker as a positional argument
+fn find_marker<'a>(
+    x: &'a AstExpr,
+    member: &mut Option<&'a AstExpr>,
+    call: &mut Option<&'a AstExpr>,
+) {
+    match &x.node {
+        Expr::Dot(x, attr) if attr.node.contains(MARKER) => *member = Some(&**x),
+        Expr::Call(f, args)
+            if args
+                .iter()
+                .any(|x| matches!(&x.node, Argument::Positional(x) if is_marker(x))) =>
+        {
+            *call = Some(&**f)
+        }
+        _ => {}
+    }
+    x.visit_expr(|x| find_marker(x, member, call))
+}
+
+// The methods and attributes of a builtin type, or a global
+fn members(x: &AstExpr, globals: &Globals) -> Vec<String> {
+    fn all_members(x: Value) -> Vec<String> {
+        let x = x.get_aref();
+        let mut res = x.get_members().map_or_else(Vec::new, |x| x.names());
+        res.extend(x.dir_attr());
+        res.sort();
+        res
+    }
+
+    fn type_members(f: impl FnOnce(&mut GlobalsBuilder)) -> Vec<String> {
+        let mut res = GlobalsBuilder::new().with(f).build().names();
+        res.sort();
+        res
+    }
+
+    let string = || type_members(crate::stdlib::string::string_members);
+    let list = || type_members(crate::stdlib::list::list_members);
+    let dict = || type_members(crate::stdlib::dict::dict_members);
+    match &x.node {
+        Expr::Identifier(name) => match globals.get(&name.node) {
+            Some(x) => all_members(x),
+            // We don't know the type of local variables, so guess it's a common one
+            None => string().into_iter().chain(list()).chain(dict()).collect(),
+        },
+        Expr::Literal(AstLiteral::StringLiteral(_)) | Expr::FString(..) => string(),
+        Expr::List(_) | Expr::ListComprehension(..) => list(),
+        Expr::Dict(_) | Expr::DictComprehension(..) => dict(),
+        _ => string().into_iter().chain(list()).chain(dict()).collect(),
+    }
+}
+
+// The names of the parameters that can be passed by name
+fn parameter_names(params: &[AstParameter]) -> Vec<String> {
+    params
+        .iter()
+        .filter_map(|x| match &x.node {
+            Parameter::Normal(x, _) | Parameter::WithDefaultValue(x, _, _) => Some(x.node.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+// The parameters of a global function, from its description as a `def`
+fn global_parameter_names(globals: &Globals, name: &str) -> Vec<String> {
+    let describe = match globals.get(name) {
+        Some(x) => x.describe(name),
+        None => return Vec::new(),
+    };
+    // Anything other than a function is described as a comment
+    match parse("describe.star", describe, &Dialect::Extended) {
+        Ok(module) => match &module.statement.node {
+            Stmt::Def(_, params, ..) => parameter_names(params),
+            _ => Vec::new(),
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+// The names in scope at the marker, named arguments to `call`, and the globals
+fn names(module: &AstModule, globals: &Globals, call: Option<&AstExpr>, res: &mut Vec<Completion>) {
+    let scope = bind::scope(module);
+    let mut scopes = Vec::new();
+    if find_identifier(&scope, &|x| x.node.contains(MARKER), &mut scopes).is_none() {
+        scopes.push(&scope);
+    }
+
+    if let Some(Expr::Identifier(f)) = call.map(|x| &x.node) {
+        let params = match scopes.iter().rev().find_map(|x| x.bound.get(&f.node)) {
+            Some((Assigner::Assign, span)) => match find_def_stmt(&module.statement, *span) {
+                Some(AstStmt {
+                    node: Stmt::Def(_, params, ..),
+                    ..
+                }) => parameter_names(params),
+                _ => Vec::new(),
+            },
+            Some(_) => Vec::new(),
+            None => global_parameter_names(globals, &f.node),
+        };
+        let kind = CompletionKind::NamedArgument;
+        res.extend(params.into_iter().map(|name| Completion { name, kind }));
+    }
+
+    for x in scopes.iter().rev() {
+        let mut names = x.bound.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let kind = CompletionKind::Variable;
+        res.extend(names.into_iter().map(|name| Completion { name, kind }));
+    }
+
+    let mut names = globals.names();
+    names.sort();
+    res.extend(names.into_iter().map(|name| {
+        let kind = match globals.get(&name) {
+            Some(x) if x.get_type() == "function" => CompletionKind::Function,
+            _ => CompletionKind::Variable,
+        };
+        Completion { name, kind }
+    }));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stdlib::standard_environment;
+
+    fn names(content: &str, line: usize, column: usize) -> Vec<(String, CompletionKind)> {
+        let globals = standard_environment().build();
+        match completions(content, &Dialect::Extended, &globals, line, column) {
+            Some(Completions::Names(xs)) => xs.into_map(|x| (x.name, x.kind)),
+            x => panic!("Expected names, got {:?}", x),
+        }
+    }
+
+    fn has(xs: &[(String, CompletionKind)], name: &str, kind: CompletionKind) -> bool {
+        xs.iter().any(|x| x.0 == name && x.1 == kind)
+    }
+
+    #[test]
+    fn test_completion_names() {
+        let code = "x = 1\ndef f(a, b = 2):\n    y = \n    return a\n";
+        let res = names(code, 2, 8);
+        // Locals first, then the module, then the globals
+        assert_eq!(&res[..5].map(|x| x.0.as_str()), &["a", "b", "y", "f", "x"]);
+        assert!(has(&res, "len", CompletionKind::Function));
+        assert!(has(&res, "None", CompletionKind::Variable));
+    }
+
+    #[test]
+    fn test_completion_named_arguments() {
+        let code = "def f(a, b = 2, *args, **kwargs):\n    pass\nf(1, \n";
+        let res = names(code, 2, 5);
+        assert_eq!(&res[..2].map(|x| x.0.as_str()), &["a", "b"]);
+        assert_eq!(res[0].1, CompletionKind::NamedArgument);
+        let res = names("getattr(", 0, 8);
+        assert!(has(&res, "default", CompletionKind::NamedArgument));
+    }
+
+    #[test]
+    fn test_completion_members() {
+        let res = names("'abc'.", 0, 6);
+        assert!(has(&res, "startswith", CompletionKind::Member));
+        assert!(!has(&res, "append", CompletionKind::Member));
+        let res = names("x.ap", 0, 4);
+        assert!(has(&res, "append", CompletionKind::Member));
+        assert!(has(&res, "startswith", CompletionKind::Member));
+    }
+
+    #[test]
+    fn test_completion_load() {
+        let globals = standard_environment().build();
+        assert_eq!(
+            completions(
+                "load(\":a.bzl\", \"x\", \"",
+                &Dialect::Extended,
+                &globals,
+                0,
+                21
+            ),
+            Some(Completions::Load(":a.bzl".to_owned()))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{
+    analysis::bind::{self, Assigner, Bind, Scope},
+    syntax::{
+        ast::{AstString, Stmt},
+        AstModule,
+    },
+};
+use codemap::{Span, SpanLoc};
+
+/// Where an identifier is defined, as returned by [`find_definition`].
+#[derive(Debug)]
+pub enum Definition {
+    /// Defined in this module, by an assignment, `def` or parameter.
+    Location(SpanLoc),
+    /// Bound by a `load`, so really defined as `name` in `module`.
+    /// The location is the symbol in the `load` statement.
+    Load {
+        location: SpanLoc,
+        module: String,
+        name: String,
+    },
+}
+
+// The first identifier satisfying `pred`, and the scopes it is in, innermost last
+pub(crate) fn find_identifier<'a>(
+    scope: &'a Scope,
+    pred: &impl Fn(&AstString) -> bool,
+    scopes: &mut Vec<&'a Scope>,
+) -> Option<&'a AstString> {
+    scopes.push(scope);
+    for x in &scope.inner {
+        match x {
+            Bind::Set(_, x) | Bind::Get(x) if pred(x) => return Some(x),
+            Bind::Scope(inner) => {
+                if let Some(x) = find_identifier(inner, pred, scopes) {
+                    return Some(x);
+                }
+            }
+            _ => {}
+        }
+    }
+    scopes.pop();
+    None
+}
+
+// The module and symbol name for the `load` that binds the local name at `span`
+pub(crate) fn find_load(module: &AstModule, span: Span) -> Option<(String, String)> {
+    let mut res = None;
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Load(path, args, _) = &**x {
+            if let Some((_, name)) = args.iter().find(|(local, _)| local.span == span) {
+                res = Some((path.node.clone(), name.node.clone()));
+            }
+        }
+    });
+    res
+}
+
+// The identifier at a position (0-based line, and column in characters),
+// along with where it is bound, if that is within this module
+pub(crate) fn identifier_at(
+    module: &AstModule,
+    line: usize,
+    column: usize,
+) -> Option<(String, Option<(Assigner, Span)>)> {
+    let file = module.codemap.find_file(module.statement.span.low());
+    if line >= file.num_lines() {
+        return None;
+    }
+    let text = file.source_line(line);
+    let offset = text.char_indices().nth(column).map_or(text.len(), |x| x.0);
+    let pos = file.line_span(line).low() + offset as u64;
+
+    let scope = bind::scope(module);
+    let mut scopes = Vec::new();
+    let name = find_identifier(
+        &scope,
+        &|x| x.span.low() <= pos && pos <= x.span.high(),
+        &mut scopes,
+    )?;
+    let binding = scopes.iter().rev().find_map(|x| x.bound.get(&name.node));
+    Some((name.node.clone(), binding.copied()))
+}
+
+/// Find where the identifier at a given position (0-based line, and column in
+/// characters) is defined. Returns `None` if there is no identifier there, or
+/// it isn't defined in this module, e.g. because it is a builtin.
+pub fn find_definition(module: &AstModule, line: usize, column: usize) -> Option<Definition> {
+    let (assigner, span) = identifier_at(module, line, column)?.1?;
+    let location = module.codemap.look_up_span(span);
+    match assigner {
+        Assigner::Load => {
+            let (module, name) = find_load(module, span)?;
+            Some(Definition::Load {
+                location,
+                module,
+                name,
+            })
+        }
+        Assigner::Argument | Assigner::Assign => Some(Definition::Location(location)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{parse, Dialect};
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn definition(m: &AstModule, line: usize, column: usize) -> Option<String> {
+        find_definition(m, line, column).map(|x| match x {
+            Definition::Location(x) => x.to_string(),
+            Definition::Load {
+                location,
+                module,
+                name,
+            } => format!("{} {}:{}", location, module, name),
+        })
+    }
+
+    #[test]
+    fn test_find_definition() {
+        let m = module(
+            r#"load(":a.bzl", b = "c")
+def f(x, y = b):
+    z = [x for x in y]
+    return x + z
+f(1)
+"#,
+        );
+        assert_eq!(definition(&m, 1, 13).unwrap(), "X:1:16: 1:17 :a.bzl:c");
+        assert_eq!(definition(&m, 3, 11).unwrap(), "X:2:7: 2:8");
+        assert_eq!(definition(&m, 3, 15).unwrap(), "X:3:5: 3:6");
+        assert_eq!(definition(&m, 2, 9).unwrap(), "X:3:16: 3:17");
+        assert_eq!(definition(&m, 4, 0).unwrap(), "X:2:5: 2:6");
+        // The definition of a definition is itself
+        assert_eq!(definition(&m, 1, 4).unwrap(), "X:2:5: 2:6");
+        assert!(definition(&m, 4, 2).is_none());
+        assert!(definition(&m, 10, 0).is_none());
+    }
+}
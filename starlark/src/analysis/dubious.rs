@@ -16,9 +16,10 @@
  */
 
 use crate::{
-    analysis::types::{LintT, LintWarning},
+    analysis::types::{Edit, LintT, LintWarning},
     syntax::{
         ast::{AstExpr, AstLiteral, Expr},
+        lexer::TokenInt,
         AstModule,
     },
 };
@@ -45,16 +46,20 @@ impl LintWarning for Dubious {
 fn duplicate_dictionary_key(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
     #[derive(PartialEq, Eq, Hash)]
     enum Key<'a> {
-        Int(i32),
+        Int(&'a TokenInt),
+        Float(u64),
         String(&'a str),
+        Bytes(&'a [u8]),
         Identifier(&'a str),
     }
 
     fn to_key<'a>(x: &'a AstExpr) -> Option<(Key<'a>, Span)> {
         match &**x {
             Expr::Literal(x) => match &*x {
-                AstLiteral::IntLiteral(x) => Some((Key::Int(x.node), x.span)),
+                AstLiteral::IntLiteral(x) => Some((Key::Int(&x.node), x.span)),
+                AstLiteral::FloatLiteral(x) => Some((Key::Float(x.node.to_bits()), x.span)),
                 AstLiteral::StringLiteral(x) => Some((Key::String(&x.node), x.span)),
+                AstLiteral::BytesLiteral(x) => Some((Key::Bytes(&x.node), x.span)),
             },
             Expr::Identifier(x) => Some((Key::Identifier(&x.node), x.span)),
             _ => None,
@@ -65,14 +70,27 @@ fn duplicate_dictionary_key(module: &AstModule, res: &mut Vec<LintT<Dubious>>) {
         match &**x {
             Expr::Dict(args) => {
                 let mut seen = HashMap::new();
-                for (key, _) in args {
+                for (i, (key, _)) in args.iter().enumerate() {
                     if let Some((key_id, pos)) = to_key(key) {
-                        if let Some(old) = seen.insert(key_id, pos) {
-                            res.push(LintT::new(
-                                codemap,
-                                old,
-                                Dubious::DuplicateKey(key.to_string(), codemap.look_up_span(pos)),
-                            ))
+                        if let Some((j, old)) = seen.insert(key_id, (i, pos)) {
+                            // The later entry wins, so the fix is to delete the earlier
+                            // one, up to the start of the entry after it
+                            let next = &args[j + 1].0;
+                            res.push(
+                                LintT::new(
+                                    codemap,
+                                    old,
+                                    Dubious::DuplicateKey(
+                                        key.to_string(),
+                                        codemap.look_up_span(pos),
+                                    ),
+                                )
+                                .with_edits(vec![Edit::delete(
+                                    codemap,
+                                    old.low(),
+                                    next.span.low(),
+                                )]),
+                            )
                         }
                     }
                 }
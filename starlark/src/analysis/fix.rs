@@ -0,0 +1,131 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Applying the edits attached to lints, to fix them all at once.
+
+use crate::{
+    analysis::types::{Edit, Lint},
+    syntax::AstModule,
+};
+
+// Two edits can't both be applied if they change overlapping code, unless they
+// are the same edit, as when several unused symbols delete the same `load`.
+fn conflicts(x: &Edit, y: &Edit) -> bool {
+    let same = x.span == y.span && x.replacement == y.replacement;
+    !same && x.span.low() < y.span.high() && y.span.low() < x.span.high()
+}
+
+/// Apply the edits of the lints of a module to its source, returning the new
+/// source. A lint whose edits conflict with those of an earlier lint is skipped,
+/// but will usually still be there to fix when the result is linted again.
+pub fn apply_fixes(module: &AstModule, lints: &[Lint]) -> String {
+    let mut edits: Vec<&Edit> = Vec::new();
+    for x in lints {
+        let ok = x
+            .edits
+            .iter()
+            .all(|a| !edits.iter().any(|b| conflicts(a, b)));
+        if ok {
+            edits.extend(&x.edits);
+        }
+    }
+    // Work from the end, so the offsets of the remaining edits stay valid
+    edits.sort_by_key(|x| x.span.low());
+    edits.dedup_by_key(|x| x.span);
+    let file = module.codemap.find_file(module.statement.span.low());
+    let mut source = file.source().to_owned();
+    for x in edits.iter().rev() {
+        let low = (x.span.low() - file.span.low()) as usize;
+        let high = (x.span.high() - file.span.low()) as usize;
+        source.replace_range(low..high, &x.replacement);
+    }
+    source
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        analysis::lint,
+        syntax::{parse, Dialect},
+    };
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn fix(x: &str) -> String {
+        let m = module(x);
+        apply_fixes(&m, &lint(&m, None))
+    }
+
+    #[test]
+    fn test_fix_unused_load() {
+        assert_eq!(
+            fix(r#"load(":a.bzl", "a", "b", c = "d")
+load(":e.bzl", "e")
+load(":f.bzl", "f", "g")
+def h():
+    return b(f)
+"#),
+            r#"load(":a.bzl", "b")
+load(":f.bzl", "f")
+def h():
+    return b(f)
+"#
+        );
+    }
+
+    #[test]
+    fn test_fix_redundant() {
+        assert_eq!(
+            fix(r#"def f(x):
+    for y in x:
+        print(y)
+        continue
+    if x:
+        return
+    print(x)
+    return
+"#),
+            r#"def f(x):
+    for y in x:
+        print(y)
+    if x:
+        return
+    print(x)
+"#
+        );
+        assert_eq!(fix("def f():\n    return\n"), "def f():\n    pass\n");
+    }
+
+    #[test]
+    fn test_fix_duplicate_key() {
+        assert_eq!(
+            fix("x = {'a': 1, 'b': 2, 'a': 3, 'b': 4, 'a': 5}\n"),
+            "x = {'b': 4, 'a': 5}\n"
+        );
+    }
+
+    #[test]
+    fn test_fix_type_check() {
+        assert_eq!(
+            fix("def f(x):\n    return type(x) == list or type(x) != str\n"),
+            "def f(x):\n    return type(x) == type([]) or type(x) != type(\"\")\n"
+        );
+    }
+}
@@ -16,7 +16,7 @@
  */
 
 use crate::{
-    analysis::types::{FileSpanLoc, LintT, LintWarning},
+    analysis::types::{Edit, FileSpanLoc, LintT, LintWarning},
     syntax::{
         ast::{AstExpr, AstLiteral, AstStmt, Expr, Stmt},
         AstModule,
@@ -77,6 +77,30 @@ fn is_fail(x: &AstExpr) -> bool {
     }
 }
 
+// A `while True:` loop without a `break` never finishes normally
+fn is_infinite_loop(x: &AstStmt) -> bool {
+    fn breaks(x: &AstStmt) -> bool {
+        match &**x {
+            Stmt::Break => true,
+            // A `break` inside these does not leave our loop
+            Stmt::For(..) | Stmt::While(..) | Stmt::Def(..) => false,
+            _ => {
+                let mut res = false;
+                x.visit_stmt(|x| res = res || breaks(x));
+                res
+            }
+        }
+    }
+
+    match &**x {
+        Stmt::While(cond, box body) => match &**cond {
+            Expr::Identifier(name) => name.node == "True" && !breaks(body),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
 fn final_return(x: &AstStmt) -> bool {
     match &**x {
         Stmt::Return(_) => true,
@@ -86,6 +110,7 @@ fn final_return(x: &AstStmt) -> bool {
             Some(x) => final_return(x),
         },
         Stmt::IfElse(_, box (x, y)) => final_return(x) && final_return(y),
+        Stmt::While(..) => is_infinite_loop(x),
         _ => false,
     }
 }
@@ -173,6 +198,13 @@ fn reachable(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) ->
             let abort2 = reachable(codemap, y, res);
             abort1 && abort2
         }
+        // An infinite loop makes everything after it unreachable
+        Stmt::While(..) => {
+            x.visit_stmt(|x| {
+                reachable(codemap, x, res);
+            });
+            is_infinite_loop(x)
+        }
         // For all remaining constructs, visit their children to accumulate errors,
         // but even if they are present with returns, you don't guarantee the code with inner returns
         // gets executed.
@@ -188,21 +220,39 @@ fn reachable(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) ->
 // If you have a definition which ends with return, or a loop which ends with continue
 // that is a useless statement that just
 fn redundant(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
-    fn check(is_loop: bool, codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
+    // Delete the statement, unless it is all there is in its block,
+    // in which case it has to be replaced with `pass`
+    fn remove(only: bool, codemap: &CodeMap, x: &AstStmt) -> Vec<Edit> {
+        if only {
+            vec![Edit::new(codemap, x.span, "pass".to_owned())]
+        } else {
+            vec![Edit::delete_statement(codemap, x.span)]
+        }
+    }
+
+    fn check(
+        is_loop: bool,
+        only: bool,
+        codemap: &CodeMap,
+        x: &AstStmt,
+        res: &mut Vec<LintT<FlowIssue>>,
+    ) {
         match &**x {
-            Stmt::Continue if is_loop => {
-                res.push(LintT::new(codemap, x.span, FlowIssue::RedundantContinue))
-            }
-            Stmt::Return(None) if !is_loop => {
-                res.push(LintT::new(codemap, x.span, FlowIssue::RedundantReturn))
-            }
+            Stmt::Continue if is_loop => res.push(
+                LintT::new(codemap, x.span, FlowIssue::RedundantContinue)
+                    .with_edits(remove(only, codemap, x)),
+            ),
+            Stmt::Return(None) if !is_loop => res.push(
+                LintT::new(codemap, x.span, FlowIssue::RedundantReturn)
+                    .with_edits(remove(only, codemap, x)),
+            ),
             Stmt::Statements(xs) if !xs.is_empty() => {
-                check(is_loop, codemap, xs.last().unwrap(), res)
+                check(is_loop, xs.len() == 1, codemap, xs.last().unwrap(), res)
             }
-            Stmt::If(_, box x) => check(is_loop, codemap, x, res),
+            Stmt::If(_, box x) => check(is_loop, true, codemap, x, res),
             Stmt::IfElse(_, box (x, y)) => {
-                check(is_loop, codemap, x, res);
-                check(is_loop, codemap, y, res);
+                check(is_loop, true, codemap, x, res);
+                check(is_loop, true, codemap, y, res);
             }
             _ => {}
         }
@@ -210,8 +260,10 @@ fn redundant(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
 
     fn f(codemap: &CodeMap, x: &AstStmt, res: &mut Vec<LintT<FlowIssue>>) {
         match &**x {
-            Stmt::For(box (_, _, body)) => check(true, codemap, body, res),
-            Stmt::Def(_, _, _, body) => check(false, codemap, body, res),
+            Stmt::For(box (_, _, body)) | Stmt::While(_, box body) => {
+                check(true, true, codemap, body, res)
+            }
+            Stmt::Def(_, _, _, body) => check(false, true, codemap, body, res),
             _ => {}
         }
         // We always want to look inside everything for other types of violation
@@ -415,6 +467,46 @@ def test7():
         assert_eq!(res.map(|x| x.location.begin.line), &[3, 9, 19]);
     }
 
+    #[test]
+    fn test_lint_while() {
+        let m = module(
+            r#"
+def no1() -> "int":
+    while x:
+        return 1
+def no2():
+    while True:
+        if x:
+            break
+        return 1
+def yes1() -> "int":
+    while True:
+        for y in ys:
+            break
+        if x:
+            return 1
+def test():
+    while True:
+        pass
+    no3
+def test2():
+    while True:
+        if x:
+            break
+    reachable
+    while x:
+        continue
+        no4
+"#,
+        );
+        let mut res = Vec::new();
+        stmt(&m.codemap, &m.statement, &mut res);
+        assert_eq!(res.map(|x| x.problem.about()), &["no1", "no2"]);
+        let mut res = Vec::new();
+        reachable(&m.codemap, &m.statement, &mut res);
+        assert_eq!(res.map(|x| x.problem.about()), &["no3", "no4"]);
+    }
+
     #[test]
     fn test_lint_misplaced_load() {
         let m = module(
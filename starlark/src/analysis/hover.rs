@@ -0,0 +1,193 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::{
+    analysis::{
+        bind::Assigner,
+        definition::{find_load, identifier_at},
+    },
+    syntax::{
+        ast::{AstLiteral, AstStmt, Expr, Stmt},
+        AstModule,
+    },
+};
+use codemap::Span;
+use itertools::Itertools;
+use std::iter;
+
+/// What is known about an identifier, as returned by [`find_hover`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hover {
+    /// A `def` in this module.
+    Def(DefSummary),
+    /// Bound by a `load`, so really defined as `name` in `module`.
+    Load { module: String, name: String },
+    /// Not bound in this module, so presumably a global with this name.
+    Global(String),
+}
+
+/// The signature and docstring of a `def`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DefSummary {
+    /// The signature, e.g. `def f(x: "int", y = 1) -> "int"`.
+    pub signature: String,
+    /// The docstring, with the indentation removed.
+    pub docstring: Option<String>,
+}
+
+// The string literal a `def` body starts with, if any
+fn docstring(body: &AstStmt) -> Option<String> {
+    let first = match &body.node {
+        Stmt::Statements(xs) => xs.first()?,
+        _ => body,
+    };
+    let text = match &first.node {
+        Stmt::Expression(x) => match &x.node {
+            Expr::Literal(AstLiteral::StringLiteral(x)) => &x.node,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    // The first line starts straight after the quotes, the rest share an indent
+    let mut lines = text.trim().lines();
+    let first = lines.next().unwrap_or_default();
+    let rest = lines.collect::<Vec<_>>();
+    let indent = rest
+        .iter()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| x.len() - x.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    Some(
+        iter::once(first)
+            .chain(rest.iter().map(|x| x.get(indent..).unwrap_or_default()))
+            .join("\n"),
+    )
+}
+
+fn summarise(x: &AstStmt) -> Option<DefSummary> {
+    match &x.node {
+        Stmt::Def(name, params, ret, body) => {
+            let mut signature = format!(
+                "def {}({})",
+                name.node,
+                params.iter().map(|x| x.node.to_string()).join(", ")
+            );
+            if let Some(ret) = ret {
+                signature += &format!(" -> {}", ret.node);
+            }
+            Some(DefSummary {
+                signature,
+                docstring: docstring(body),
+            })
+        }
+        _ => None,
+    }
+}
+
+// The `def` whose name is at `span`
+pub(crate) fn find_def_stmt(x: &AstStmt, span: Span) -> Option<&AstStmt> {
+    match &x.node {
+        Stmt::Def(name, ..) if name.span == span => Some(x),
+        _ => {
+            let mut res = None;
+            x.visit_stmt(|x| {
+                if res.is_none() {
+                    res = find_def_stmt(x, span);
+                }
+            });
+            res
+        }
+    }
+}
+
+/// Find a top-level `def` by name, e.g. the target of a `load`.
+pub fn find_def(module: &AstModule, name: &str) -> Option<DefSummary> {
+    let mut res = None;
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Def(def_name, ..) = &x.node {
+            if def_name.node == name && res.is_none() {
+                res = summarise(x);
+            }
+        }
+    });
+    res
+}
+
+/// Find what is known about the identifier at a given position (0-based line,
+/// and column in characters). Returns `None` if there is no identifier there,
+/// or it is bound in this module by something other than a `def` or `load`.
+pub fn find_hover(module: &AstModule, line: usize, column: usize) -> Option<Hover> {
+    match identifier_at(module, line, column)? {
+        (name, None) => Some(Hover::Global(name)),
+        (_, Some((Assigner::Load, span))) => {
+            let (module, name) = find_load(module, span)?;
+            Some(Hover::Load { module, name })
+        }
+        (_, Some((_, span))) => find_def_stmt(&module.statement, span)
+            .and_then(summarise)
+            .map(Hover::Def),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{parse, Dialect};
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_find_hover() {
+        let m = module(
+            r#"load(":a.bzl", "b")
+def f(x: "int", *args, y = 1, **kwargs) -> "string":
+    """Do a thing.
+
+    More details.
+      Indented.
+    """
+    def g():
+        pass
+    return g() + b() + len(x)
+"#,
+        );
+        let f = DefSummary {
+            signature: "def f(x: \"int\", *args, y = 1, **kwargs) -> \"string\"".to_owned(),
+            docstring: Some("Do a thing.\n\nMore details.\n  Indented.".to_owned()),
+        };
+        let g = DefSummary {
+            signature: "def g()".to_owned(),
+            docstring: None,
+        };
+        assert_eq!(find_hover(&m, 1, 4), Some(Hover::Def(f.clone())));
+        assert_eq!(find_hover(&m, 9, 11), Some(Hover::Def(g)));
+        assert_eq!(
+            find_hover(&m, 9, 17),
+            Some(Hover::Load {
+                module: ":a.bzl".to_owned(),
+                name: "b".to_owned()
+            })
+        );
+        assert_eq!(find_hover(&m, 9, 24), Some(Hover::Global("len".to_owned())));
+        assert_eq!(find_hover(&m, 9, 27), None);
+        assert_eq!(find_def(&m, "f"), Some(f));
+        assert_eq!(find_def(&m, "g"), None);
+    }
+}
@@ -16,7 +16,7 @@
  */
 
 use crate::{
-    analysis::types::{LintT, LintWarning},
+    analysis::types::{Edit, LintT, LintWarning},
     syntax::{
         ast::{AssignOp, AstExpr, AstStmt, AstString, BinOp, Expr, Stmt},
         AstModule,
@@ -48,7 +48,10 @@ static TYPES: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
         "tuple" => "()",
         "str" => "\"\"",
         "list" => "[]",
-        "int" => "0"
+        "int" => "0",
+        "float" => "0.0",
+        "set" => "set()",
+        "bytes" => "b\"\""
     ]
 });
 
@@ -82,14 +85,22 @@ fn match_bad_type_equality(
             if (*op == BinOp::EqualsTo || *op == BinOp::Different) && is_type_call(lhs) =>
         {
             if let Some(replacement) = lookup_type(rhs, types) {
-                res.push(LintT::new(
-                    codemap,
-                    x.span,
-                    Incompatibility::IncompatibleTypeCheck(
-                        x.to_string(),
-                        format!("{}{}type({})", lhs.node, op, replacement),
-                    ),
-                ))
+                let replacement = format!("type({})", replacement);
+                res.push(
+                    LintT::new(
+                        codemap,
+                        x.span,
+                        Incompatibility::IncompatibleTypeCheck(
+                            x.to_string(),
+                            format!("{}{}{}", lhs.node, op, replacement),
+                        ),
+                    )
+                    .with_edits(vec![Edit::new(
+                        codemap,
+                        rhs.span,
+                        replacement,
+                    )]),
+                )
             }
         }
         _ => {}
@@ -15,19 +15,42 @@
  * limitations under the License.
  */
 
+pub use completion::{completions, Completion, CompletionKind, Completions};
+pub use definition::{find_definition, Definition};
 pub use exported::exported_symbols;
-pub use types::{LineColSpan, Lint};
+pub use fix::apply_fixes;
+pub use hover::{find_def, find_hover, DefSummary, Hover};
+pub use outline::{document_symbols, folding_ranges, FoldingRange, Symbol, SymbolKind};
+pub use references::{
+    check_rename, find_exported_references, find_loaded_references, find_references, Reference,
+    ReferenceKind, References, Referent, RenameError,
+};
+pub use tokens::{semantic_tokens, Token, TokenKind};
+pub use types::{Edit, LineColSpan, Lint, LintConfig, Severity};
 
-use crate::{analysis::types::LintT, syntax::AstModule};
+use crate::{
+    analysis::{suppress::Suppressions, types::LintT},
+    syntax::AstModule,
+};
 
 mod bind;
+mod completion;
+mod definition;
 mod dubious;
 mod exported;
+mod fix;
 mod flow;
+mod hover;
 mod incompatible;
 mod names;
+mod outline;
+mod references;
+mod suppress;
+mod tokens;
 mod types;
 
+/// Run all the lints over a module, except those disabled by a
+/// `# starlark: disable=<short-name>` comment.
 pub fn lint(module: &AstModule, globals: Option<&[&str]>) -> Vec<Lint> {
     let mut res = Vec::new();
     res.extend(flow::flow_issues(module).into_iter().map(LintT::erase));
@@ -42,5 +65,7 @@ pub fn lint(module: &AstModule, globals: Option<&[&str]>) -> Vec<Lint> {
             .into_iter()
             .map(LintT::erase),
     );
+    let suppressions = Suppressions::new(module);
+    res.retain(|x| !suppressions.is_suppressed(x));
     res
 }
@@ -19,14 +19,14 @@ use crate::{
     analysis::{
         bind,
         bind::{Assigner, Bind, Scope},
-        types::{LintT, LintWarning},
+        types::{Edit, LintT, LintWarning},
     },
     syntax::{
         ast::{AstStmt, Expr, Stmt},
         AstModule,
     },
 };
-use codemap::{CodeMap, Span};
+use codemap::{CodeMap, Span, SpanLoc};
 use gazebo::{prelude::*, variants::VariantName};
 use std::collections::{HashMap, HashSet};
 use thiserror::Error;
@@ -86,9 +86,48 @@ pub(crate) fn name_warnings(
     }
     inappropriate_underscore(&module.codemap, &module.statement, true, &mut res);
     use_ignored(&module.codemap, &scope, None, &mut res);
+    unused_load_edits(&module.codemap, &module.statement, &mut res);
     res
 }
 
+// An unused load is fixed by deleting it from the `load` statement, along with
+// a comma, or by deleting the whole statement if none of it is used.
+fn unused_load_edits(codemap: &CodeMap, x: &AstStmt, res: &mut [LintT<NameWarning>]) {
+    fn is_unused(x: &LintT<NameWarning>, location: &SpanLoc) -> bool {
+        matches!(x.problem, NameWarning::UnusedLoad(_)) && &x.location == location
+    }
+
+    match &**x {
+        Stmt::Statements(xs) => {
+            for x in xs {
+                unused_load_edits(codemap, x, res)
+            }
+        }
+        Stmt::Load(_, args, _) => {
+            let locations = args.map(|(local, _)| codemap.look_up_span(local.span));
+            let unused = locations.map(|loc| res.iter().any(|x| is_unused(x, loc)));
+            let spans = args.map(|(local, their)| local.span.merge(their.span));
+            let first_used = unused.iter().position(|x| !x);
+            for (i, loc) in locations.iter().enumerate() {
+                if !unused[i] {
+                    continue;
+                }
+                let edit = match first_used {
+                    None => Edit::delete_statement(codemap, x.span),
+                    Some(first_used) if i < first_used => {
+                        Edit::delete(codemap, spans[i].low(), spans[i + 1].low())
+                    }
+                    Some(_) => Edit::delete(codemap, spans[i - 1].high(), spans[i].high()),
+                };
+                for x in res.iter_mut().filter(|x| is_unused(x, loc)) {
+                    x.edits = vec![edit.clone()];
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 fn undefined_variable(
     codemap: &CodeMap,
     scope: &Scope,
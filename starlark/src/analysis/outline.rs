@@ -0,0 +1,267 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The structure of a module, for an editor to show as an outline, and to fold.
+
+use crate::syntax::{
+    ast::{AssignOp, AstExpr, AstLiteral, AstStmt, Expr, Stmt},
+    AstModule,
+};
+use codemap::{File, Span, SpanLoc};
+use either::Either;
+use std::collections::HashSet;
+
+/// The kind of a [`Symbol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Variable,
+    /// A `load` statement, named after the module it loads.
+    Load,
+}
+
+/// Something defined by a module, as returned by [`document_symbols`].
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The whole statement defining it.
+    pub location: SpanLoc,
+    /// Just the name.
+    pub name_location: SpanLoc,
+    /// The `def`s nested in a `def`, or the names bound by a `load`.
+    pub children: Vec<Symbol>,
+}
+
+/// A range of (0-based, inclusive) lines, as returned by [`folding_ranges`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldingRange {
+    pub begin: usize,
+    pub end: usize,
+}
+
+fn symbol(
+    module: &AstModule,
+    name: String,
+    kind: SymbolKind,
+    span: Span,
+    name_span: Span,
+    children: Vec<Symbol>,
+) -> Symbol {
+    Symbol {
+        name,
+        kind,
+        location: module.codemap.look_up_span(span),
+        name_location: module.codemap.look_up_span(name_span),
+        children,
+    }
+}
+
+// The `def`s in a statement, including those inside `if` and `for`, with those
+// nested inside them as children
+fn defs(module: &AstModule, x: &AstStmt, res: &mut Vec<Symbol>) {
+    match &x.node {
+        Stmt::Def(name, _, _, body) => {
+            let mut children = Vec::new();
+            defs(module, body, &mut children);
+            res.push(symbol(
+                module,
+                name.node.clone(),
+                SymbolKind::Function,
+                x.span,
+                name.span,
+                children,
+            ))
+        }
+        _ => x.visit_stmt(|x| defs(module, x, res)),
+    }
+}
+
+/// The top-level `def`s, assignments and `load`s of a module, in source order.
+/// Only the first assignment to each name is included.
+pub fn document_symbols(module: &AstModule) -> Vec<Symbol> {
+    let mut res = Vec::new();
+    let mut assigned = HashSet::new();
+    let mut top = |x: &AstStmt| match &x.node {
+        Stmt::Def(..) => defs(module, x, &mut res),
+        Stmt::Assign(lhs, AssignOp::Assign, _) => lhs.visit_expr_lvalue(|name| {
+            if assigned.insert(name.node.clone()) {
+                res.push(symbol(
+                    module,
+                    name.node.clone(),
+                    SymbolKind::Variable,
+                    x.span,
+                    name.span,
+                    Vec::new(),
+                ))
+            }
+        }),
+        Stmt::Load(path, args, _) => {
+            let children = args
+                .iter()
+                .map(|(local, _)| {
+                    symbol(
+                        module,
+                        local.node.clone(),
+                        SymbolKind::Variable,
+                        local.span,
+                        local.span,
+                        Vec::new(),
+                    )
+                })
+                .collect();
+            res.push(symbol(
+                module,
+                path.node.clone(),
+                SymbolKind::Load,
+                x.span,
+                path.span,
+                children,
+            ))
+        }
+        _ => {}
+    };
+    match &module.statement.node {
+        Stmt::Statements(xs) => xs.iter().for_each(|x| top(x)),
+        _ => top(&module.statement),
+    }
+    res
+}
+
+// The first and last lines with any code in them
+fn lines(file: &File, span: Span) -> FoldingRange {
+    // Spans often include the newline, or dedent, after their last line
+    let len = file.source_slice(span).trim_end().len().max(1);
+    FoldingRange {
+        begin: file.find_line(span.low()),
+        end: file.find_line(span.low() + (len - 1) as u64),
+    }
+}
+
+fn fold_expr(file: &File, x: &AstExpr, res: &mut Vec<FoldingRange>) {
+    match &x.node {
+        Expr::Tuple(_)
+        | Expr::Call(..)
+        | Expr::List(_)
+        | Expr::Dict(_)
+        | Expr::Set(_)
+        | Expr::ListComprehension(..)
+        | Expr::DictComprehension(..)
+        | Expr::SetComprehension(..)
+        | Expr::Literal(AstLiteral::StringLiteral(_)) => res.push(lines(file, x.span)),
+        _ => {}
+    }
+    x.visit_expr(|x| fold_expr(file, x, res))
+}
+
+fn fold_stmt(file: &File, x: &AstStmt, res: &mut Vec<FoldingRange>) {
+    match &x.node {
+        Stmt::Def(..) | Stmt::If(..) | Stmt::For(..) | Stmt::While(..) => {
+            res.push(lines(file, x.span))
+        }
+        Stmt::IfElse(_, box (then_block, else_block)) => {
+            let then_range = lines(file, then_block.span);
+            res.push(FoldingRange {
+                begin: file.find_line(x.span.low()),
+                end: then_range.end,
+            });
+            // An `elif` is folded as the `if` it is short for
+            if !matches!(else_block.node, Stmt::If(..) | Stmt::IfElse(..)) {
+                res.push(FoldingRange {
+                    begin: then_range.end + 1,
+                    end: lines(file, else_block.span).end,
+                });
+            }
+        }
+        _ => {}
+    }
+    x.visit_children(|x| match x {
+        Either::Left(x) => fold_stmt(file, x, res),
+        Either::Right(x) => fold_expr(file, x, res),
+    })
+}
+
+/// The `def`, `if`, `for` and `while` blocks, and literals, which span multiple lines.
+pub fn folding_ranges(module: &AstModule) -> Vec<FoldingRange> {
+    let file = module.codemap.find_file(module.statement.span.low());
+    let mut res = Vec::new();
+    fold_stmt(&file, &module.statement, &mut res);
+    res.retain(|x| x.begin < x.end);
+    res.sort_by_key(|x| (x.begin, x.end));
+    res.dedup();
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{parse, Dialect};
+    use gazebo::prelude::*;
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let m = module(
+            r#"load(":a.bzl", "b", c = "d")
+x, y = 1, 2
+def f():
+    if True:
+        def g():
+            pass
+x = 3
+"#,
+        );
+        fn describe(x: &Symbol) -> String {
+            let children = x.children.map(describe).join(", ");
+            format!("{:?} {} {} [{}]", x.kind, x.name, x.name_location, children)
+        }
+        assert_eq!(
+            document_symbols(&m).map(describe),
+            &[
+                "Load :a.bzl X:1:6: 1:14 [Variable b X:1:16: 1:19 [], Variable c X:1:21: 1:22 []]",
+                "Variable x X:2:1: 2:2 []",
+                "Variable y X:2:4: 2:5 []",
+                "Function f X:3:5: 3:6 [Function g X:5:13: 5:14 []]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_folding_ranges() {
+        let m = module(
+            r#"def f(x):
+    if x:
+        return [
+            1,
+        ]
+    elif x:
+        pass
+    else:
+        pass
+        pass
+    return 1
+"#,
+        );
+        assert_eq!(
+            folding_ranges(&m).map(|x| (x.begin, x.end)),
+            &[(0, 10), (1, 4), (2, 4), (5, 6), (7, 9)]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Finding every occurrence of a variable, and checking it can be renamed.
+//! Within a module we follow the scopes from `bind`. Across modules, an
+//! exported name is referenced by the `load` statements that name it, which
+//! the caller has to find, since only it knows how to resolve a `load`.
+
+use crate::{
+    analysis::{
+        bind::{self, Assigner, Bind, Scope},
+        definition::{find_load, identifier_at},
+    },
+    syntax::{
+        ast::{Expr, Stmt},
+        parse, AstModule, Dialect,
+    },
+};
+use codemap::{Span, SpanLoc};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// What a variable refers to, as found by [`find_references`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Referent {
+    /// Bound in this module, and not visible outside it.
+    Local,
+    /// A top-level name this module exports, which other modules may `load`.
+    Exported,
+    /// Bound by a `load`, so really defined as `name` in `module`.
+    Load { module: String, name: String },
+    /// Not bound in this module, so presumably a global.
+    Global,
+}
+
+/// How a [`Reference`] is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// An identifier with the name being referenced.
+    Identifier,
+    /// The string naming the symbol in a `load`, which may also bind it.
+    LoadSymbol,
+    /// An identifier bound by a `load` to the symbol under a different name.
+    Alias,
+}
+
+/// An occurrence of a variable.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub location: SpanLoc,
+    pub kind: ReferenceKind,
+    pub(crate) span: Span,
+}
+
+/// Every occurrence of a variable in a module, as returned by [`find_references`].
+#[derive(Debug, Clone)]
+pub struct References {
+    pub name: String,
+    pub referent: Referent,
+    /// In source order, including where the variable is bound.
+    pub references: Vec<Reference>,
+}
+
+/// Why a variable can't be given a new name.
+#[derive(Error, Debug)]
+pub enum RenameError {
+    #[error("`{0}` is not a valid identifier")]
+    InvalidName(String),
+    #[error("Can't rename `{0}`, which isn't defined in this workspace")]
+    Global(String),
+    #[error("Renaming to `{0}` would collide with an existing variable")]
+    Collision(String),
+    #[error("Renaming to `{0}` would shadow, or be shadowed by, another variable")]
+    Shadow(String),
+}
+
+impl Reference {
+    fn new(module: &AstModule, span: Span, kind: ReferenceKind) -> Self {
+        Self {
+            location: module.codemap.look_up_span(span),
+            kind,
+            span,
+        }
+    }
+
+    /// The text to replace this reference with to rename it, or `None` if it
+    /// doesn't mention the name.
+    pub fn replacement(&self, name: &str) -> Option<String> {
+        match self.kind {
+            ReferenceKind::Identifier => Some(name.to_owned()),
+            ReferenceKind::LoadSymbol => {
+                // Keep whichever quotes the string was written with
+                let quote = self.location.file.source_slice(self.span).chars().next()?;
+                Some(format!("{}{}{}", quote, name, quote))
+            }
+            ReferenceKind::Alias => None,
+        }
+    }
+}
+
+// The spans of `load` symbols which also bind the symbol under its own name
+fn unaliased_loads(module: &AstModule) -> HashSet<Span> {
+    let mut res = HashSet::new();
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Load(_, args, _) = &x.node {
+            res.extend(
+                args.iter()
+                    .filter(|x| x.0.span == x.1.span)
+                    .map(|x| x.0.span),
+            );
+        }
+    });
+    res
+}
+
+// Every occurrence of `name`, bound at `binding`, or a global if `None`
+fn occurrences(module: &AstModule, name: &str, binding: Option<Span>) -> Vec<Reference> {
+    let scope = bind::scope(module);
+    let mut resolved = bind::resolve(&scope);
+    // An augmented assignment both gets and sets the same identifier
+    resolved.sort_by_key(|x| x.0.span.low());
+    resolved.dedup_by_key(|x| x.0.span);
+    let unaliased = unaliased_loads(module);
+    resolved
+        .into_iter()
+        .filter(|(x, b)| x.node == name && b.map(|b| b.1) == binding)
+        .map(|(x, _)| {
+            let kind = if unaliased.contains(&x.span) {
+                ReferenceKind::LoadSymbol
+            } else {
+                ReferenceKind::Identifier
+            };
+            Reference::new(module, x.span, kind)
+        })
+        .collect()
+}
+
+/// Find every occurrence in this module of the variable at a given position
+/// (0-based line, and column in characters). Returns `None` if there is no
+/// identifier there.
+pub fn find_references(module: &AstModule, line: usize, column: usize) -> Option<References> {
+    let (name, binding) = identifier_at(module, line, column)?;
+    let referent = match binding {
+        None => Referent::Global,
+        Some((Assigner::Load, span)) => {
+            let (module, name) = find_load(module, span)?;
+            Referent::Load { module, name }
+        }
+        Some((Assigner::Assign, span))
+            if !name.starts_with('_')
+                && bind::scope(module).bound.get(&name) == Some(&(Assigner::Assign, span)) =>
+        {
+            Referent::Exported
+        }
+        Some(_) => Referent::Local,
+    };
+    let references = occurrences(module, &name, binding.map(|x| x.1));
+    Some(References {
+        name,
+        referent,
+        references,
+    })
+}
+
+/// Find every occurrence of the top-level variable `name` in this module,
+/// e.g. after following a `load` to the module that defines it.
+pub fn find_exported_references(module: &AstModule, name: &str) -> Vec<Reference> {
+    match bind::scope(module).bound.get(name) {
+        Some((Assigner::Assign, span)) => occurrences(module, name, Some(*span)),
+        _ => Vec::new(),
+    }
+}
+
+/// Find every occurrence of the symbol `name`, loaded from any module for which
+/// `is_module` is true, e.g. because it resolves to the module defining `name`.
+pub fn find_loaded_references(
+    module: &AstModule,
+    is_module: impl Fn(&str) -> bool,
+    name: &str,
+) -> Vec<Reference> {
+    let mut loads = Vec::new();
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Load(path, args, _) = &x.node {
+            if is_module(&path.node) {
+                loads.extend(args.iter().filter(|x| x.1.node == name));
+            }
+        }
+    });
+
+    let mut res = Vec::new();
+    for (local, symbol) in loads {
+        if local.span != symbol.span {
+            res.push(Reference::new(
+                module,
+                symbol.span,
+                ReferenceKind::LoadSymbol,
+            ));
+        }
+        let mut xs = occurrences(module, &local.node, Some(local.span));
+        for x in &mut xs {
+            if x.kind == ReferenceKind::Identifier && local.node != name {
+                x.kind = ReferenceKind::Alias;
+            }
+        }
+        res.extend(xs);
+    }
+    res.sort_by_key(|x| x.span.low());
+    res
+}
+
+fn is_identifier(name: &str) -> bool {
+    // Let the parser decide, so we reject keywords as well as other characters
+    match parse(
+        "rename.star",
+        format!("{} = None", name),
+        &Dialect::Extended,
+    ) {
+        Ok(module) => match &module.statement.node {
+            Stmt::Assign(lhs, _, _) => match &lhs.node {
+                Expr::Identifier(x) => x.node == name,
+                _ => false,
+            },
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
+// Does anything in this scope, or those nested inside it, mention `name`
+fn mentions(scope: &Scope, name: &str) -> bool {
+    scope.inner.iter().any(|x| match x {
+        Bind::Set(_, x) | Bind::Get(x) => x.node == name,
+        Bind::Scope(inner) => mentions(inner, name),
+        Bind::Flow => false,
+    })
+}
+
+// The scope binding something at one of `spans`
+fn binding_scope<'a>(scope: &'a Scope, spans: &HashSet<Span>) -> Option<&'a Scope> {
+    if scope.bound.values().any(|x| spans.contains(&x.1)) {
+        return Some(scope);
+    }
+    scope.inner.iter().find_map(|x| match x {
+        Bind::Scope(inner) => binding_scope(inner, spans),
+        _ => None,
+    })
+}
+
+/// Check that the `references` in this module, as found by the functions above,
+/// could be renamed to `new_name` without it colliding with, shadowing, or
+/// being shadowed by, another variable.
+pub fn check_rename(
+    module: &AstModule,
+    references: &[Reference],
+    new_name: &str,
+) -> Result<(), RenameError> {
+    if !is_identifier(new_name) {
+        return Err(RenameError::InvalidName(new_name.to_owned()));
+    }
+    // Aliases keep their name, so only the other references are renamed
+    let spans = references
+        .iter()
+        .filter(|x| x.kind != ReferenceKind::Alias)
+        .map(|x| x.span)
+        .collect();
+    let scope = bind::scope(module);
+    match binding_scope(&scope, &spans) {
+        // Only the symbol in a `load` with an alias is renamed, which binds nothing
+        None => Ok(()),
+        Some(x) if x.bound.contains_key(new_name) => {
+            Err(RenameError::Collision(new_name.to_owned()))
+        }
+        Some(x) if mentions(x, new_name) => Err(RenameError::Shadow(new_name.to_owned())),
+        Some(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gazebo::prelude::*;
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    fn locations(xs: &[Reference]) -> Vec<String> {
+        xs.map(|x| format!("{} {:?}", x.location, x.kind))
+    }
+
+    #[test]
+    fn test_find_references() {
+        let m = module(
+            r#"load(":a.bzl", "b", c = "d")
+def f(x):
+    y = [x for x in x]
+    return x + y + b + c
+_z = f(1)
+"#,
+        );
+        let x = find_references(&m, 3, 11).unwrap();
+        assert_eq!(x.referent, Referent::Local);
+        assert_eq!(
+            locations(&x.references),
+            &[
+                "X:2:7: 2:8 Identifier",
+                "X:3:21: 3:22 Identifier",
+                "X:4:12: 4:13 Identifier"
+            ]
+        );
+        let f = find_references(&m, 4, 5).unwrap();
+        assert_eq!(f.referent, Referent::Exported);
+        assert_eq!(f.references.len(), 2);
+        assert_eq!(
+            find_references(&m, 0, 20).unwrap().referent,
+            Referent::Load {
+                module: ":a.bzl".to_owned(),
+                name: "d".to_owned()
+            }
+        );
+        assert_eq!(
+            locations(&find_references(&m, 3, 19).unwrap().references),
+            &["X:1:16: 1:19 LoadSymbol", "X:4:20: 4:21 Identifier"]
+        );
+        assert_eq!(find_references(&m, 4, 1).unwrap().referent, Referent::Local);
+        assert_eq!(find_exported_references(&m, "f").len(), 2);
+        assert!(find_exported_references(&m, "b").is_empty());
+    }
+
+    #[test]
+    fn test_find_loaded_references() {
+        let m = module(
+            r#"load(":a.bzl", "b", c = "d")
+load(":e.bzl", "d")
+b(c(d))
+"#,
+        );
+        assert_eq!(
+            locations(&find_loaded_references(&m, |x| x == ":a.bzl", "d")),
+            &[
+                "X:1:21: 1:22 Alias",
+                "X:1:25: 1:28 LoadSymbol",
+                "X:3:3: 3:4 Alias"
+            ]
+        );
+        assert_eq!(
+            locations(&find_loaded_references(&m, |x| x == ":a.bzl", "b")),
+            &["X:1:16: 1:19 LoadSymbol", "X:3:1: 3:2 Identifier"]
+        );
+        assert!(find_loaded_references(&m, |x| x == ":a.bzl", "c").is_empty());
+    }
+
+    #[test]
+    fn test_check_rename() {
+        let m = module(
+            r#"load(":a.bzl", "b", c = "d")
+def f(x):
+    y = 1
+    return x + y + len(x)
+"#,
+        );
+        let x = find_references(&m, 3, 11).unwrap().references;
+        assert!(check_rename(&m, &x, "z").is_ok());
+        assert!(check_rename(&m, &x, "f").is_ok());
+        assert!(matches!(
+            check_rename(&m, &x, "y"),
+            Err(RenameError::Collision(_))
+        ));
+        assert!(matches!(
+            check_rename(&m, &x, "len"),
+            Err(RenameError::Shadow(_))
+        ));
+        assert!(matches!(
+            check_rename(&m, &x, "def"),
+            Err(RenameError::InvalidName(_))
+        ));
+        assert!(matches!(
+            check_rename(&m, &x, "a b"),
+            Err(RenameError::InvalidName(_))
+        ));
+        let f = find_references(&m, 1, 4).unwrap().references;
+        assert!(matches!(
+            check_rename(&m, &f, "b"),
+            Err(RenameError::Collision(_))
+        ));
+        let d = find_loaded_references(&m, |_| true, "d");
+        assert!(check_rename(&m, &d, "b").is_ok());
+        assert_eq!(d[0].replacement("e"), None);
+        assert_eq!(d[1].replacement("e").unwrap(), "\"e\"");
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Lints can be suppressed with comments such as `# starlark: disable=unused-argument`,
+//! taking a comma-separated list of lint short names.
+//!
+//! * At the end of a line, the comment applies to that line.
+//! * On a line of its own, it applies to the following statement, including any
+//!   nested block, so can be used to cover a whole `def`.
+//! * Written as `# starlark: disable-file=...`, it applies to the whole file.
+
+use crate::{
+    analysis::Lint,
+    syntax::{
+        ast::{AstStmt, Stmt},
+        AstModule, Trivia,
+    },
+};
+use codemap::Span;
+use std::collections::HashSet;
+
+pub(crate) struct Suppressions {
+    /// Lint short names with the range of (0-based, inclusive) lines they are disabled on.
+    lines: Vec<(String, usize, usize)>,
+    /// Lint short names disabled for the whole file.
+    file: HashSet<String>,
+}
+
+/// Parse a suppression comment, returning whether it covers the whole file,
+/// and the lints it disables.
+fn parse_comment(comment: &str) -> Option<(bool, impl Iterator<Item = &str>)> {
+    let rest = comment.trim_start_matches('#').trim_start();
+    let rest = rest.strip_prefix("starlark:")?.trim_start();
+    let (whole_file, names) = if let Some(names) = rest.strip_prefix("disable-file=") {
+        (true, names)
+    } else {
+        (false, rest.strip_prefix("disable=")?)
+    };
+    Some((
+        whole_file,
+        names.split(',').map(str::trim).filter(|x| !x.is_empty()),
+    ))
+}
+
+impl Suppressions {
+    pub(crate) fn new(module: &AstModule) -> Self {
+        let mut res = Self {
+            lines: Vec::new(),
+            file: HashSet::new(),
+        };
+        let file = module.codemap.find_file(module.statement.span.low());
+        let mut statements = None;
+        for trivia in module.trivia_or_lex().iter() {
+            let comment = match &trivia.node {
+                Trivia::Comment(comment) => comment,
+                Trivia::BlankLine => continue,
+            };
+            let (whole_file, names) = match parse_comment(comment) {
+                Some(x) => x,
+                None => continue,
+            };
+            if whole_file {
+                res.file.extend(names.map(str::to_owned));
+                continue;
+            }
+            let line = file.find_line(trivia.span.low());
+            let (lo, hi) = if file.source_line(line).trim_start().starts_with('#') {
+                // Only collect the statements if we need them
+                let statements = statements.get_or_insert_with(|| statement_spans(module));
+                match statements.iter().find(|x| x.low() >= trivia.span.high()) {
+                    None => continue,
+                    Some(x) => (file.find_line(x.low()), file.find_line(x.high())),
+                }
+            } else {
+                (line, line)
+            };
+            res.lines.extend(names.map(|x| (x.to_owned(), lo, hi)));
+        }
+        res
+    }
+
+    pub(crate) fn is_suppressed(&self, lint: &Lint) -> bool {
+        let line = lint.location.begin.line;
+        self.file.contains(&lint.short_name)
+            || self
+                .lines
+                .iter()
+                .any(|(name, lo, hi)| name == &lint.short_name && *lo <= line && line <= *hi)
+    }
+}
+
+// The spans of all the statements, in source order
+fn statement_spans(module: &AstModule) -> Vec<Span> {
+    fn f(x: &AstStmt, res: &mut Vec<Span>) {
+        match &x.node {
+            Stmt::Statements(_) => {}
+            _ => res.push(x.span),
+        }
+        x.visit_stmt(|x| f(x, res));
+    }
+
+    let mut res = Vec::new();
+    f(&module.statement, &mut res);
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        analysis::lint,
+        syntax::{parse, AstModule, Dialect},
+    };
+    use gazebo::prelude::*;
+
+    fn module(x: &str) -> AstModule {
+        parse("X", x.to_owned(), &Dialect::Extended).unwrap()
+    }
+
+    #[test]
+    fn test_lint_suppression() {
+        let m = module(
+            r#"
+def no1(unused1, unused2): # starlark: disable=unused-argument
+    pass
+# starlark: disable=unused-argument,unreachable
+def no2(unused3):
+    return
+    pass
+def yes(unused4):
+    return
+    pass # starlark: disable=unused-assign
+"#,
+        );
+        assert_eq!(
+            lint(&m, None).map(|x| x.short_name.as_str()),
+            &["unreachable", "unused-argument"]
+        );
+        let m = module(
+            r#"
+def f(unused):
+    pass
+# starlark: disable-file=unused-argument
+"#,
+        );
+        assert!(lint(&m, None).is_empty());
+    }
+}
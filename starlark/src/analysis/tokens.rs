@@ -0,0 +1,112 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Classifying identifiers by what they refer to, so an editor can colour them
+//! more precisely than it could from the syntax alone.
+
+use crate::{
+    analysis::bind::{self, Assigner},
+    syntax::AstModule,
+};
+use codemap::SpanLoc;
+use std::collections::HashSet;
+
+/// What an identifier refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A global, not bound in this module.
+    Builtin,
+    /// Bound by an assignment, `def`, `for` or comprehension.
+    Variable,
+    /// A parameter of the enclosing `def` or `lambda`.
+    Parameter,
+    /// Bound by a `load`.
+    Loaded,
+}
+
+/// An identifier, as returned by [`semantic_tokens`].
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub location: SpanLoc,
+    pub kind: TokenKind,
+}
+
+/// Classify every identifier in a module, in source order. If `globals` is
+/// given, identifiers that aren't bound in the module or named in `globals` are
+/// left out, since they are probably mistakes.
+pub fn semantic_tokens(module: &AstModule, globals: Option<&[&str]>) -> Vec<Token> {
+    let globals = globals.map(|xs| xs.iter().copied().collect::<HashSet<_>>());
+    let scope = bind::scope(module);
+    let mut resolved = bind::resolve(&scope);
+    resolved.sort_by_key(|x| x.0.span.low());
+    resolved.dedup_by_key(|x| x.0.span);
+    resolved
+        .into_iter()
+        .filter_map(|(x, binding)| {
+            let kind = match binding {
+                Some((Assigner::Assign, _)) => TokenKind::Variable,
+                Some((Assigner::Argument, _)) => TokenKind::Parameter,
+                Some((Assigner::Load, _)) => TokenKind::Loaded,
+                None => match &globals {
+                    Some(globals) if !globals.contains(x.node.as_str()) => return None,
+                    _ => TokenKind::Builtin,
+                },
+            };
+            Some(Token {
+                location: module.codemap.look_up_span(x.span),
+                kind,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syntax::{parse, Dialect};
+    use gazebo::prelude::*;
+
+    #[test]
+    fn test_semantic_tokens() {
+        let m = parse(
+            "X",
+            r#"load(":a.bzl", "b")
+def f(x):
+    y = len(x)
+    return b(y, z)
+"#
+            .to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let describe = |xs: Vec<Token>| xs.map(|x| format!("{} {:?}", x.location, x.kind));
+        assert_eq!(
+            describe(semantic_tokens(&m, Some(&["len"][..]))),
+            &[
+                "X:1:16: 1:19 Loaded",
+                "X:2:5: 2:6 Variable",
+                "X:2:7: 2:8 Parameter",
+                "X:3:5: 3:6 Variable",
+                "X:3:9: 3:12 Builtin",
+                "X:3:13: 3:14 Parameter",
+                "X:4:12: 4:13 Loaded",
+                "X:4:14: 4:15 Variable",
+            ]
+        );
+        assert_eq!(semantic_tokens(&m, None).len(), 9);
+    }
+}
@@ -15,9 +15,14 @@
  * limitations under the License.
  */
 
-use codemap::{CodeMap, LineCol, Span, SpanLoc};
-use gazebo::variants::VariantName;
-use std::fmt::{self, Display};
+use anyhow::anyhow;
+use codemap::{CodeMap, File, LineCol, Pos, Span, SpanLoc};
+use gazebo::{prelude::*, variants::VariantName};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
 
 pub(crate) trait LintWarning: Display + VariantName {
     fn is_serious(&self) -> bool;
@@ -115,6 +120,59 @@ pub(crate) struct LintT<T> {
     pub location: SpanLoc,
     pub original: String,
     pub problem: T,
+    pub edits: Vec<Edit>,
+}
+
+/// A replacement of part of the source, as part of the fix for a lint.
+#[derive(Debug, Clone)]
+pub struct Edit {
+    /// Which code location to replace.
+    pub location: SpanLoc,
+    /// What to replace it with, empty to delete it.
+    pub replacement: String,
+    pub(crate) span: Span,
+}
+
+impl Edit {
+    pub(crate) fn new(codemap: &CodeMap, span: Span, replacement: String) -> Self {
+        Self {
+            location: codemap.look_up_span(span),
+            replacement,
+            span,
+        }
+    }
+
+    /// Delete the source between two positions.
+    pub(crate) fn delete(codemap: &CodeMap, low: Pos, high: Pos) -> Self {
+        Self::new(
+            codemap,
+            between(codemap.find_file(low), low, high),
+            String::new(),
+        )
+    }
+
+    /// Delete a statement, along with the lines it is on if it has them to itself,
+    /// so we don't leave blank lines behind.
+    pub(crate) fn delete_statement(codemap: &CodeMap, span: Span) -> Self {
+        let file = codemap.find_file(span.low());
+        // Statement spans often include the newline, or dedent, after them
+        let len = file.source_slice(span).trim_end().len() as u64;
+        let first = file.line_span(file.find_line(span.low()));
+        let last = file.line_span(file.find_line(span.low() + len));
+        let before = file.source_slice(between(file, first.low(), span.low()));
+        let after = file.source_slice(between(file, span.low() + len, last.high()));
+        if before.trim().is_empty() && after.trim().is_empty() {
+            Self::delete(codemap, first.low(), last.high())
+        } else {
+            Self::delete(codemap, span.low(), span.low() + len)
+        }
+    }
+}
+
+// The span from one position in a file to another
+fn between(file: &File, low: Pos, high: Pos) -> Span {
+    file.span
+        .subspan(low - file.span.low(), high - file.span.low())
 }
 
 /// A lint produced by the Starlark linter.
@@ -131,6 +189,8 @@ pub struct Lint {
     pub problem: String,
     /// The source at SpanLoc
     pub original: String,
+    /// Edits which together fix the problem, empty if there is no obvious fix.
+    pub edits: Vec<Edit>,
 }
 
 impl Display for Lint {
@@ -139,6 +199,79 @@ impl Display for Lint {
     }
 }
 
+/// A standardised set of severities.
+#[derive(Debug, Dupe, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Advice,
+    Disabled,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+            Severity::Advice => "Advice",
+            Severity::Disabled => "Disabled",
+        })
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "advice" => Ok(Severity::Advice),
+            "disabled" => Ok(Severity::Disabled),
+            _ => Err(anyhow!("Unknown severity `{}`", s)),
+        }
+    }
+}
+
+/// Overrides for the severity of lints, keyed by their short name.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    /// `None` means the lint is turned off entirely.
+    severities: HashMap<String, Option<Severity>>,
+}
+
+impl LintConfig {
+    /// Add an override written as `name=severity`, where `severity` is
+    /// one of the severities, or `off` to not report the lint at all.
+    pub fn add(&mut self, x: &str) -> anyhow::Result<()> {
+        let (name, severity) = x
+            .split1_opt("=")
+            .ok_or_else(|| anyhow!("Expected `name=severity` for a lint, got `{}`", x))?;
+        let severity = match severity {
+            "off" => None,
+            _ => Some(severity.parse()?),
+        };
+        self.severities.insert(name.to_owned(), severity);
+        Ok(())
+    }
+
+    /// Whether a lint should be reported at all.
+    pub fn enabled(&self, name: &str) -> bool {
+        !matches!(self.severities.get(name), Some(None))
+    }
+
+    /// The severity to report a lint with, or `None` if it has been turned off.
+    /// Without an override, serious lints are warnings and the rest are disabled.
+    pub fn severity(&self, x: &Lint) -> Option<Severity> {
+        match self.severities.get(&x.short_name) {
+            Some(severity) => *severity,
+            // Start with all non-serious errors disabled, and ramp up from there
+            None if x.serious => Some(Severity::Warning),
+            None => Some(Severity::Disabled),
+        }
+    }
+}
+
 impl<T: Display> Display for LintT<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}: {}", self.location, self.problem)
@@ -152,9 +285,15 @@ impl<T: LintWarning> LintT<T> {
             original: location.file.source_slice(span).to_owned(),
             location,
             problem,
+            edits: Vec::new(),
         }
     }
 
+    /// Attach the edits which fix this lint.
+    pub(crate) fn with_edits(self, edits: Vec<Edit>) -> Self {
+        Self { edits, ..self }
+    }
+
     pub(crate) fn erase(self) -> Lint {
         Lint {
             location: self.location,
@@ -162,6 +301,7 @@ impl<T: LintWarning> LintT<T> {
             serious: self.problem.is_serious(),
             problem: self.problem.to_string(),
             original: self.original,
+            edits: self.edits,
         }
     }
 }
@@ -201,6 +341,31 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_lint_config() {
+        let mut codemap = CodeMap::new();
+        let file = codemap.add_file("a.bzl".to_owned(), "x = 1".to_owned());
+        let lint = |short_name: &str, serious| Lint {
+            location: codemap.look_up_span(file.span),
+            short_name: short_name.to_owned(),
+            serious,
+            problem: String::new(),
+            original: String::new(),
+            edits: Vec::new(),
+        };
+        let mut config = LintConfig::default();
+        config.add("a=error").unwrap();
+        config.add("b=off").unwrap();
+        assert!(config.add("c=loud").is_err());
+        assert!(config.add("c").is_err());
+        assert!(config.enabled("a"));
+        assert!(!config.enabled("b"));
+        assert_eq!(config.severity(&lint("a", false)), Some(Severity::Error));
+        assert_eq!(config.severity(&lint("b", true)), None);
+        assert_eq!(config.severity(&lint("c", true)), Some(Severity::Warning));
+        assert_eq!(config.severity(&lint("c", false)), Some(Severity::Disabled));
+    }
+
     #[test]
     fn test_line_col_span_display_point() {
         let line_col = LineCol { line: 0, column: 0 };
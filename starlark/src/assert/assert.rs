@@ -38,7 +38,7 @@ use anyhow::anyhow;
 use codemap::CodeMap;
 use gazebo::prelude::*;
 use once_cell::sync::Lazy;
-use std::{collections::HashMap, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
 
 fn mk_environment() -> GlobalsBuilder {
     extended_environment().with(test_methods).with(add_typing)
@@ -85,6 +85,14 @@ fn assert_star(builder: &mut GlobalsBuilder) {
         }
     }
 
+    fn lt(a: Value, b: Value) -> NoneType {
+        if a.compare(b)? != Ordering::Less {
+            Err(anyhow!("assert.lt: expected {} to be less than {}", a, b))
+        } else {
+            Ok(NONE)
+        }
+    }
+
     fn contains(xs: Value, x: Value) -> NoneType {
         if !xs.is_in(x)? {
             Err(anyhow!("assert.contains: expected {} to be in {}", x, xs))
@@ -93,7 +101,7 @@ fn assert_star(builder: &mut GlobalsBuilder) {
         }
     }
 
-    fn r#true(x: Value) -> NoneType {
+    fn r#true(x: Value, _msg: Option<Value>) -> NoneType {
         assert_equals(Value::new_bool(x.to_bool()), Value::new_bool(true))
     }
 
@@ -121,19 +129,9 @@ fn test_methods(builder: &mut GlobalsBuilder) {
         Ok(Struct::new(SmallMap::new()))
     }
 
-    // Approximate version of a method used by the Go test suite
-    fn set(xs: Value) -> Value<'v> {
-        Ok(xs)
-    }
-
     fn assert_eq(a: Value, b: Value) -> NoneType {
         assert_equals(a, b)
     }
-
-    fn print(x: Value) -> NoneType {
-        println!("{}", x);
-        Ok(NONE)
-    }
 }
 
 #[derive(Clone)]
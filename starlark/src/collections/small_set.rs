@@ -15,7 +15,10 @@
  * limitations under the License.
  */
 
-use crate::collections::small_map::SmallMap;
+use crate::collections::{
+    hash::{BorrowHashed, Hashed},
+    small_map::SmallMap,
+};
 use gazebo::prelude::*;
 use indexmap::Equivalent;
 use std::{
@@ -93,6 +96,14 @@ impl<T> SmallSet<T> {
         self.0.into_iter().map(|(t, _)| t)
     }
 
+    pub fn iter_hashed(&self) -> impl Iterator<Item = BorrowHashed<T>> {
+        self.0.iter_hashed().map(|(t, _)| t)
+    }
+
+    pub fn into_iter_hashed(self) -> impl Iterator<Item = Hashed<T>> {
+        self.0.into_iter_hashed().map(|(t, _)| t)
+    }
+
     pub fn insert(&mut self, key: T) -> bool
     where
         T: Hash + Eq,
@@ -100,6 +111,13 @@ impl<T> SmallSet<T> {
         self.0.insert(key, ()).is_none()
     }
 
+    pub fn insert_hashed(&mut self, key: Hashed<T>) -> bool
+    where
+        T: Eq,
+    {
+        self.0.insert_hashed(key, ()).is_none()
+    }
+
     /// Return a reference to the value stored in the set, if it is present,
     /// else `None`.
     ///
@@ -129,6 +147,15 @@ impl<T> SmallSet<T> {
         self.0.remove(key);
     }
 
+    /// Remove the value from the set, returning whether it was present.
+    pub fn remove_hashed<Q>(&mut self, key: BorrowHashed<Q>) -> bool
+    where
+        Q: ?Sized + Equivalent<T>,
+        T: Eq,
+    {
+        self.0.remove_hashed(key).is_some()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
     }
@@ -145,6 +172,14 @@ impl<T> SmallSet<T> {
         self.0.contains_key(key)
     }
 
+    pub fn contains_hashed<Q>(&self, key: BorrowHashed<Q>) -> bool
+    where
+        Q: Equivalent<T> + ?Sized,
+        T: Eq,
+    {
+        self.0.contains_key_hashed(key)
+    }
+
     pub fn clear(&mut self) {
         self.0.clear()
     }
@@ -256,4 +291,15 @@ mod tests {
 
         assert_eq!(s.insert(5), false);
     }
+
+    #[test]
+    fn small_set_hashed() {
+        let mut s = SmallSet::new();
+        assert_eq!(s.insert_hashed(Hashed::new(3)), true);
+        assert_eq!(s.insert_hashed(Hashed::new(3)), false);
+        assert_eq!(s.contains_hashed(Hashed::new(3).borrow()), true);
+        assert_eq!(s.remove_hashed(Hashed::new(3).borrow()), true);
+        assert_eq!(s.remove_hashed(Hashed::new(3).borrow()), false);
+        assert_eq!(s.is_empty(), true);
+    }
 }
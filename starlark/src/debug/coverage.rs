@@ -0,0 +1,299 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Recording which statements and branches run, over any number of
+//! evaluations, driven by `on_stmt` and `on_branch`.
+
+use crate::{
+    debug::stmt_locations,
+    eval::EvaluationContext,
+    syntax::{
+        ast::{AstExpr, AstStmt, BinOp, Expr, Stmt},
+        AstModule,
+    },
+};
+use codemap::{Span, SpanLoc};
+use either::Either;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Mutex,
+};
+
+// Where some code is, as (0-based) begin line and column, then end line and
+// column. Unlike a `Span`, it means the same thing in every evaluation.
+type Position = (usize, usize, usize, usize);
+
+fn position(x: &SpanLoc) -> Position {
+    (x.begin.line, x.begin.column, x.end.line, x.end.column)
+}
+
+#[derive(Debug, Default)]
+struct FileData {
+    statements: BTreeMap<Position, usize>,
+    // How many times each branch went each way, true then false
+    branches: BTreeMap<Position, [usize; 2]>,
+}
+
+/// The coverage of a file, as returned by [`Coverage::summary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub path: String,
+    pub statements: usize,
+    pub statements_hit: usize,
+    /// Each `if`, conditional expression, `and` and `or` counts as two branches.
+    pub branches: usize,
+    pub branches_hit: usize,
+    /// The (0-based) lines with statements, none of which have run.
+    pub missed_lines: Vec<usize>,
+}
+
+/// Statement and branch counts, keyed by file name, so those from different
+/// evaluations of the same file are combined.
+#[derive(Debug, Default)]
+pub struct Coverage(Mutex<HashMap<String, FileData>>);
+
+fn branches_expr(x: &AstExpr, res: &mut Vec<Span>) {
+    match &x.node {
+        Expr::If(..) | Expr::Op(_, BinOp::And, _) | Expr::Op(_, BinOp::Or, _) => res.push(x.span),
+        _ => {}
+    }
+    x.visit_expr(|x| branches_expr(x, res))
+}
+
+fn branches_stmt(x: &AstStmt, res: &mut Vec<Span>) {
+    match &x.node {
+        Stmt::If(..) | Stmt::IfElse(..) => res.push(x.span),
+        _ => {}
+    }
+    x.visit_children(|x| match x {
+        Either::Left(x) => branches_stmt(x, res),
+        Either::Right(x) => branches_expr(x, res),
+    })
+}
+
+// The line counts of a file: a line has run if any statement starting on it has
+fn lines(data: &FileData) -> BTreeMap<usize, usize> {
+    let mut res = BTreeMap::new();
+    for (position, count) in &data.statements {
+        let line = res.entry(position.0).or_insert(0);
+        *line = (*line).max(*count);
+    }
+    res
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_file<R>(&self, x: &SpanLoc, f: impl FnOnce(&mut FileData) -> R) -> R {
+        let mut files = self.0.lock().unwrap();
+        f(files.entry(x.file.name().to_owned()).or_default())
+    }
+
+    /// Add the statements and branches of a module, so any that never run are
+    /// reported as such. Those that do run are recorded whether added or not.
+    pub fn add_module(&self, module: &AstModule) {
+        let mut branches = Vec::new();
+        branches_stmt(&module.statement, &mut branches);
+        let statements = stmt_locations(module);
+        let mut files = self.0.lock().unwrap();
+        for x in statements {
+            let x = module.codemap.look_up_span(x);
+            let data = files.entry(x.file.name().to_owned()).or_default();
+            data.statements.entry(position(&x)).or_insert(0);
+        }
+        for x in branches {
+            let x = module.codemap.look_up_span(x);
+            let data = files.entry(x.file.name().to_owned()).or_default();
+            data.branches.entry(position(&x)).or_insert([0, 0]);
+        }
+    }
+
+    /// Record a statement running, suitable for calling from `on_stmt`.
+    pub fn stmt(&self, span: Span, context: &EvaluationContext) {
+        let x = context.look_up_span(span);
+        self.with_file(&x, |data| {
+            *data.statements.entry(position(&x)).or_insert(0) += 1
+        })
+    }
+
+    /// Record which way a branch went, suitable for calling from `on_branch`.
+    pub fn branch(&self, span: Span, taken: bool, context: &EvaluationContext) {
+        let x = context.look_up_span(span);
+        self.with_file(&x, |data| {
+            data.branches.entry(position(&x)).or_insert([0, 0])[if taken { 0 } else { 1 }] += 1
+        })
+    }
+
+    /// The coverage of each file, sorted by file name.
+    pub fn summary(&self) -> Vec<FileCoverage> {
+        let files = self.0.lock().unwrap();
+        let mut res: Vec<_> = files
+            .iter()
+            .map(|(path, data)| FileCoverage {
+                path: path.clone(),
+                statements: data.statements.len(),
+                statements_hit: data.statements.values().filter(|x| **x > 0).count(),
+                branches: data.branches.len() * 2,
+                branches_hit: data
+                    .branches
+                    .values()
+                    .flat_map(|x| x.iter())
+                    .filter(|x| **x > 0)
+                    .count(),
+                missed_lines: lines(data)
+                    .into_iter()
+                    .filter(|x| x.1 == 0)
+                    .map(|x| x.0)
+                    .collect(),
+            })
+            .collect();
+        res.sort_by(|x, y| x.path.cmp(&y.path));
+        res
+    }
+
+    /// The coverage in LCOV format, as read by `genhtml` and most coverage tools.
+    pub fn lcov(&self) -> String {
+        let files = self.0.lock().unwrap();
+        let mut files: Vec<_> = files.iter().collect();
+        files.sort_by_key(|x| x.0);
+        let mut res = String::new();
+        for (path, data) in files {
+            // Writing to a String can't fail
+            writeln!(res, "TN:").unwrap();
+            writeln!(res, "SF:{}", path).unwrap();
+            for (block, (position, counts)) in data.branches.iter().enumerate() {
+                let never = counts.iter().all(|x| *x == 0);
+                for (branch, count) in counts.iter().enumerate() {
+                    let count = if never {
+                        "-".to_owned()
+                    } else {
+                        count.to_string()
+                    };
+                    writeln!(
+                        res,
+                        "BRDA:{},{},{},{}",
+                        position.0 + 1,
+                        block,
+                        branch,
+                        count
+                    )
+                    .unwrap();
+                }
+            }
+            let branches_hit = data
+                .branches
+                .values()
+                .flat_map(|x| x.iter())
+                .filter(|x| **x > 0)
+                .count();
+            writeln!(res, "BRF:{}", data.branches.len() * 2).unwrap();
+            writeln!(res, "BRH:{}", branches_hit).unwrap();
+            let lines = lines(data);
+            for (line, count) in &lines {
+                writeln!(res, "DA:{},{}", line + 1, count).unwrap();
+            }
+            writeln!(res, "LF:{}", lines.len()).unwrap();
+            writeln!(res, "LH:{}", lines.values().filter(|x| **x > 0).count()).unwrap();
+            writeln!(res, "end_of_record").unwrap();
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        environment::Module,
+        eval::{eval_module, NoLoadFileLoader},
+        stdlib::standard_environment,
+        syntax::{parse, Dialect},
+    };
+
+    fn run(coverage: &Coverage, code: &str) {
+        let module = parse("X", code.to_owned(), &Dialect::Extended).unwrap();
+        coverage.add_module(&module);
+        let globals = standard_environment().build();
+        let env = Module::new("X");
+        let on_stmt = |span, context: &mut EvaluationContext| coverage.stmt(span, context);
+        let on_branch =
+            |span, taken, context: &mut EvaluationContext| coverage.branch(span, taken, context);
+        let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
+        context.on_stmt = Some(&on_stmt);
+        context.on_branch = Some(&on_branch);
+        eval_module(module, &mut context).unwrap();
+    }
+
+    const CODE: &str = r#"
+def f(x):
+    if x:
+        return 1
+    return x or 2
+f(1)
+y = f(0) if False else 3
+"#;
+
+    #[test]
+    fn test_coverage_lcov() {
+        let coverage = Coverage::new();
+        run(&coverage, CODE);
+        assert_eq!(
+            coverage.lcov(),
+            r#"TN:
+SF:X
+BRDA:3,0,0,1
+BRDA:3,0,1,0
+BRDA:5,1,0,-
+BRDA:5,1,1,-
+BRDA:7,2,0,0
+BRDA:7,2,1,1
+BRF:6
+BRH:2
+DA:2,1
+DA:3,1
+DA:4,1
+DA:5,0
+DA:6,1
+DA:7,1
+LF:6
+LH:5
+end_of_record
+"#
+        );
+    }
+
+    #[test]
+    fn test_coverage_merges() {
+        let coverage = Coverage::new();
+        run(&coverage, CODE);
+        run(&coverage, &CODE.replace("f(1)", "f(0)"));
+        assert_eq!(
+            coverage.summary(),
+            vec![FileCoverage {
+                path: "X".to_owned(),
+                statements: 6,
+                statements_hit: 6,
+                branches: 6,
+                branches_hit: 4,
+                missed_lines: Vec::new(),
+            }]
+        );
+    }
+}
@@ -24,6 +24,7 @@ use crate::{
     values::Value,
 };
 use gazebo::{cell::ARef, prelude::*};
+use std::iter;
 
 pub fn inspect_stack(ctx: &EvaluationContext) -> Vec<String> {
     ctx.call_stack()
@@ -70,6 +71,32 @@ pub fn inspect_variables<'v>(ctx: &EvaluationContext<'v, '_>) -> SmallMap<String
     inspect_local_variables(ctx).unwrap_or_else(|| inspect_module_variables(ctx))
 }
 
+/// The variables of each frame, outermost first, so the module variables followed by
+/// one entry per call on `inspect_stack`. Functions written in Rust have no variables.
+pub fn inspect_frame_variables<'v>(
+    ctx: &EvaluationContext<'v, '_>,
+) -> Vec<SmallMap<String, Value<'v>>> {
+    // Every `def` call pushed the previous locals, so match them up innermost first
+    let mut locals = iter::once(&ctx.local_variables).chain(ctx.local_variables_stack.iter().rev());
+    let mut res = Vec::new();
+    for x in ctx.call_stack().to_function_values().into_iter().rev() {
+        let mut vars = SmallMap::new();
+        if let Some(names) = to_scope_names(x) {
+            if let Some(slots) = locals.next() {
+                for (name, slot) in &names.mp {
+                    if let Some(v) = slots.get_slot(*slot) {
+                        vars.insert(name.clone(), v);
+                    }
+                }
+            }
+        }
+        res.push(vars);
+    }
+    res.push(inspect_module_variables(ctx));
+    res.reverse();
+    res
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -86,6 +113,10 @@ mod tests {
                 fields: inspect_variables(ctx),
             })
         }
+
+        fn debug_inspect_frame_variables() -> Vec<Struct<'v>> {
+            Ok(inspect_frame_variables(ctx).into_map(|fields| Struct { fields }))
+        }
     }
 
     #[test]
@@ -122,6 +153,30 @@ def f(x = 1, y = "test"):
     assert_eq(debug_inspect_variables(), struct(x = 1, y = "hello", z = 6, _magic = True))
 f(y = "hello")
 assert_eq(debug_inspect_variables(), struct(root = 12, f = f, _ignore = [True]))
+"#,
+        );
+    }
+
+    #[test]
+    fn test_debug_frame_variables() {
+        let mut a = assert::Assert::new();
+        a.globals_add(debugger);
+        a.pass(
+            r#"
+captured = []
+def f(x):
+    y = x + 1
+    g(y)
+def g(z):
+    sorted([z], key = h)
+def h(w):
+    frames = debug_inspect_frame_variables()
+    captured.append((len(frames), sorted(dir(frames[0])), frames[1:]))
+    return w
+f(1)
+assert_eq(captured[0][0], 6)
+assert_eq(captured[0][1], ["captured", "f", "g", "h"])
+assert_eq(captured[0][2], [struct(x = 1, y = 2), struct(z = 2), struct(), struct(w = 2), struct()])
 "#,
         );
     }
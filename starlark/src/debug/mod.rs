@@ -16,9 +16,11 @@
  */
 
 mod breakpoint;
+mod coverage;
 mod evaluate;
 mod inspect;
 
 pub use breakpoint::*;
+pub use coverage::*;
 pub use evaluate::*;
 pub use inspect::*;
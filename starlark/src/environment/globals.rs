@@ -33,6 +33,7 @@ pub struct Globals(Arc<GlobalsData>);
 struct GlobalsData {
     heap: FrozenHeapRef,
     variables: HashMap<String, FrozenValue>,
+    docstrings: HashMap<String, String>,
 }
 
 // Why are these things RefCell? Because we need to allocate things from the heap
@@ -47,6 +48,8 @@ pub struct GlobalsBuilder {
     variables: HashMap<String, FrozenValue>,
     // Set to Some when we are in a struct builder, otherwise None
     struct_fields: Option<SmallMap<String, FrozenValue>>,
+    // Documentation for the top-level variables
+    docstrings: HashMap<String, String>,
 }
 
 impl Globals {
@@ -62,6 +65,12 @@ impl Globals {
         self.0.variables.keys().cloned().collect()
     }
 
+    /// The documentation for a variable, usually from the doc comment in the
+    /// `#[starlark_module]` that defined it.
+    pub fn docstring(&self, name: &str) -> Option<&str> {
+        self.0.docstrings.get(name).map(|x| x.as_str())
+    }
+
     pub fn heap(&self) -> &FrozenHeapRef {
         &self.0.heap
     }
@@ -81,6 +90,7 @@ impl GlobalsBuilder {
             heap: FrozenHeap::new(),
             variables: HashMap::new(),
             struct_fields: None,
+            docstrings: HashMap::new(),
         }
     }
 
@@ -109,6 +119,7 @@ impl GlobalsBuilder {
         Globals(Arc::new(GlobalsData {
             heap: self.heap.into_ref(),
             variables: self.variables,
+            docstrings: self.docstrings,
         }))
     }
 
@@ -121,6 +132,15 @@ impl GlobalsBuilder {
         };
     }
 
+    /// Set the documentation for a variable. Ignored inside `struct_`, since
+    /// only top-level variables are documented.
+    pub fn set_docstring(&mut self, name: &str, docstring: &str) {
+        if self.struct_fields.is_none() {
+            self.docstrings
+                .insert(name.to_owned(), docstring.to_owned());
+        }
+    }
+
     pub fn alloc<'v, V: AllocFrozenValue<'v>>(&'v self, value: V) -> FrozenValue {
         value.alloc_frozen_value(&self.heap)
     }
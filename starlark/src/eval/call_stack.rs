@@ -30,6 +30,15 @@ use crate::{
 };
 use codemap::{CodeMap, Span, SpanLoc};
 use std::{cell::Cell, fmt, fmt::Debug, sync::Arc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum CallStackError {
+    #[error("Too many recursion levels, call stack depth limit of {0} exceeded, {1}")]
+    TooDeep(usize, String),
+    #[error("Function `{0}` called recursively, which this dialect does not allow")]
+    Recursive(String),
+}
 
 // A value akin to Frame, but can be created cheaply, since it doesn't resolve
 // anything in advance.
@@ -65,15 +74,25 @@ impl Debug for CheapFrame<'_> {
 }
 
 /// Starlark call stack.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct CallStack<'v> {
     stack: Vec<CheapFrame<'v>>,
+    max_depth: usize,
 }
 
 // At 50 we see the C stack overflowing, so limit to 40 (which seems quite
 // low...)
 const MAX_CALLSTACK_RECURSION: usize = 40;
 
+impl Default for CallStack<'_> {
+    fn default() -> Self {
+        Self {
+            stack: Vec::new(),
+            max_depth: MAX_CALLSTACK_RECURSION,
+        }
+    }
+}
+
 impl<'v> CallStack<'v> {
     /// Push an element to the stack. It is important the each `push` is paired
     /// with a `pop`.
@@ -82,13 +101,57 @@ impl<'v> CallStack<'v> {
         function: Value<'v>,
         location: Option<(Arc<CodeMap>, Span)>,
     ) -> anyhow::Result<()> {
-        if self.stack.len() > MAX_CALLSTACK_RECURSION {
-            return Err(ValueError::TooManyRecursionLevel.into());
+        if self.stack.len() > self.max_depth {
+            let deepest = self.describe_deepest(function);
+            return Err(CallStackError::TooDeep(self.max_depth, deepest).into());
         }
         self.stack.push(CheapFrame { function, location });
         Ok(())
     }
 
+    pub(crate) fn set_max_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
+    }
+
+    pub(crate) fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    // Describe how we got too deep to call `function`: if it is already on the
+    // stack, the cycle of calls since it was, otherwise the deepest few frames.
+    fn describe_deepest(&self, function: Value<'v>) -> String {
+        let names = |xs: &[CheapFrame]| {
+            xs.iter()
+                .map(|x| format!("`{}`", x.function.to_repr()))
+                .collect::<Vec<_>>()
+        };
+        // The first entry is just the entire module, so skip it
+        let frames = self.stack.get(1..).unwrap_or_default();
+        match frames.iter().rposition(|x| x.function.ptr_eq(function)) {
+            Some(i) => {
+                let mut cycle = names(&frames[i..]);
+                cycle.push(format!("`{}`", function.to_repr()));
+                format!("in the cycle {}", cycle.join(" -> "))
+            }
+            None => {
+                const DEEPEST: usize = 5;
+                let deepest = &frames[frames.len().saturating_sub(DEEPEST)..];
+                format!("the deepest calls being {}", names(deepest).join(" -> "))
+            }
+        }
+    }
+
+    /// Fail if the function at the top of the stack is also further down, as
+    /// a [`Dialect`](crate::syntax::Dialect) without recursion requires.
+    pub(crate) fn check_not_recursive(&self) -> anyhow::Result<()> {
+        if let Some((top, rest)) = self.stack.split_last() {
+            if rest.iter().any(|x| x.function.ptr_eq(top.function)) {
+                return Err(CallStackError::Recursive(top.function.to_repr()).into());
+            }
+        }
+        Ok(())
+    }
+
     /// Remove the top element from the stack. Called after `push`.
     pub(crate) fn pop(&mut self) {
         let old = self.stack.pop();
@@ -105,6 +168,12 @@ impl<'v> CallStack<'v> {
         self.stack.last()?.resolve_location()
     }
 
+    /// The number of functions being called, not counting the module itself.
+    /// Cheaper than counting [`to_diagnostic_frames`](CallStack::to_diagnostic_frames).
+    pub fn depth(&self) -> usize {
+        self.stack.len().saturating_sub(1)
+    }
+
     pub(crate) fn walk(&mut self, walker: &Walker<'v>) {
         for x in self.stack.iter_mut() {
             walker.walk(&mut x.function);
@@ -126,13 +195,13 @@ impl<'v> CallStack<'v> {
     }
 }
 
-// Maximum recursion level for comparison
-// TODO(dmarting): those are rather short, maybe make it configurable?
+// Maximum recursion level for comparison, unless the `EvaluationContext`
+// says otherwise
 #[cfg(debug_assertions)]
-const MAX_RECURSION: u32 = 200;
+pub(crate) const MAX_RECURSION: u32 = 200;
 
 #[cfg(not(debug_assertions))]
-const MAX_RECURSION: u32 = 3000;
+pub(crate) const MAX_RECURSION: u32 = 3000;
 
 // A thread-local counter is used to detect too deep recursion.
 //
@@ -146,6 +215,27 @@ const MAX_RECURSION: u32 = 3000;
 //   signatures to accept some "context" parameters, but passing it as
 //   thread-local is easier.
 thread_local!(static STACK_DEPTH: Cell<u32> = Cell::new(0));
+thread_local!(static MAX_STACK_DEPTH: Cell<u32> = Cell::new(MAX_RECURSION));
+
+/// Stored previous maximum stack depth before calling `set_max_recursion`.
+///
+/// Stores that previous maximum back to thread-local on drop.
+#[must_use]
+pub(crate) struct MaxRecursionGuard {
+    prev_max: u32,
+}
+
+impl Drop for MaxRecursionGuard {
+    fn drop(&mut self) {
+        MAX_STACK_DEPTH.with(|c| c.set(self.prev_max));
+    }
+}
+
+/// Set the maximum stack depth, until the returned guard is dropped.
+pub(crate) fn set_max_recursion(max: u32) -> MaxRecursionGuard {
+    let prev_max = MAX_STACK_DEPTH.with(|c| c.replace(max));
+    MaxRecursionGuard { prev_max }
+}
 
 /// Stored previous stack depth before calling `try_inc`.
 ///
@@ -175,7 +265,7 @@ fn inc() -> StackGuard {
 
 /// Check stack depth does not exceed configured max stack depth.
 fn check() -> anyhow::Result<()> {
-    if STACK_DEPTH.with(Cell::get) >= MAX_RECURSION {
+    if STACK_DEPTH.with(Cell::get) >= MAX_STACK_DEPTH.with(Cell::get) {
         return Err(ValueError::TooManyRecursionLevel.into());
     }
     Ok(())
@@ -18,13 +18,13 @@
 //! List/dict/set comprenension evaluation.
 
 use crate::{
-    collections::SmallMap,
+    collections::{SmallMap, SmallSet},
     eval::{
         context::EvaluationContext, stmt::AssignCompiled, thrw, Compiler, EvalCompiled,
         EvalException,
     },
     syntax::ast::{AstClause, AstExpr, Clause},
-    values::{dict::Dict, Value},
+    values::{dict::Dict, set::Set, Value},
 };
 use codemap::Span;
 use gazebo::prelude::*;
@@ -39,6 +39,14 @@ impl Compiler<'_> {
         eval_list(x, clauses)
     }
 
+    pub fn set_comprehension(&mut self, x: AstExpr, clauses: Vec<AstClause>) -> EvalCompiled {
+        self.scope.enter_compr();
+        let clauses = compile_clauses(clauses, self);
+        let x = self.expr(x);
+        self.scope.exit_compr();
+        eval_set(x, clauses)
+    }
+
     pub fn dict_comprehension(
         &mut self,
         k: AstExpr,
@@ -94,6 +102,25 @@ fn eval_list(x: EvalCompiled, clauses: Vec<ClauseCompiled>) -> EvalCompiled {
     }
 }
 
+fn eval_set(x: EvalCompiled, clauses: Vec<ClauseCompiled>) -> EvalCompiled {
+    // Reuse the list machinery, then hash the elements at the end
+    let clauses = eval_one_dimensional_comprehension_list(clauses, box move |me, context| {
+        let x = x(context)?;
+        me.push(x);
+        Ok(())
+    });
+
+    box move |context| {
+        let mut r = Vec::new();
+        clauses(&mut r, context)?;
+        let mut res = SmallSet::with_capacity(r.len());
+        for x in r {
+            res.insert_hashed(x.get_hashed()?);
+        }
+        Ok(context.heap.alloc(Set::new(res)))
+    }
+}
+
 fn eval_dict(k: EvalCompiled, v: EvalCompiled, clauses: Vec<ClauseCompiled>) -> EvalCompiled {
     let clauses = eval_one_dimensional_comprehension_dict(clauses, box move |me, context| {
         let k = k(context)?;
@@ -144,6 +171,10 @@ fn eval_one_dimensional_comprehension_dict(
             let iterable = (c.over)(context)?;
             let freeze_for_iteration = iterable.get_aref();
             'f: for i in &thrw(iterable.iterate(context.heap), c.over_span, context)? {
+                thrw(context.step(), c.over_span, context)?;
+                // The result isn't on the heap yet, so check it would fit
+                let reserve = accumulator.len() * mem::size_of::<(Value, Value)>();
+                thrw(context.heap.check_reserve(reserve), c.over_span, context)?;
                 (c.var)(i, context)?;
                 for ifc in &c.ifs {
                     if !ifc(context)?.to_bool() {
@@ -185,6 +216,10 @@ fn eval_one_dimensional_comprehension_list(
             let iterable = (c.over)(context)?;
             let freeze_for_iteration = iterable.get_aref();
             'f: for i in &thrw(iterable.iterate(context.heap), c.over_span, context)? {
+                thrw(context.step(), c.over_span, context)?;
+                // The result isn't on the heap yet, so check it would fit
+                let reserve = accumulator.len() * mem::size_of::<Value>();
+                thrw(context.heap.check_reserve(reserve), c.over_span, context)?;
                 (c.var)(i, context)?;
                 for ifc in &c.ifs {
                     if !ifc(context)?.to_bool() {
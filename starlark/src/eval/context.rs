@@ -20,12 +20,34 @@ use crate::{
     environment::{
         slots::LocalSlots, EnvironmentError, FrozenModuleRef, FrozenModuleValue, Globals, Module,
     },
-    eval::call_stack::CallStack,
-    values::{FrozenHeap, Heap, Value, ValueRef, Walker},
+    eval::{
+        call_stack::{self, CallStack, MAX_RECURSION},
+        profile::{ProfileMode, TimeProfile},
+        PrintHandler, StderrPrintHandler,
+    },
+    values::{FrozenHeap, Heap, HeapLimit, Value, ValueRef, Walker},
 };
 use codemap::{CodeMap, Span, SpanLoc};
 use gazebo::any::AnyLifetime;
-use std::{mem, sync::Arc};
+use std::{
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use thiserror::Error;
+
+/// Why evaluation stopped before it finished, with the span of the statement
+/// or loop it stopped at, and the call stack.
+#[derive(Debug, Error)]
+pub enum InterruptError {
+    #[error("Evaluation exceeded its budget of {0} steps")]
+    StepBudgetExceeded(u64),
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+}
 
 /// A structure holding all the data about the evaluation context
 /// (scope, load statement resolver, ...)
@@ -42,7 +64,7 @@ pub struct EvaluationContext<'v, 'a> {
     pub(crate) local_variables: LocalSlots<'v>,
     // When we enter a function, push the old local_variables here
     // Ensures we have access to all the GC roots
-    local_variables_stack: Vec<LocalSlots<'v>>,
+    pub(crate) local_variables_stack: Vec<LocalSlots<'v>>,
     // Globals used to resolve global variables.
     pub(crate) globals: &'a Globals,
     // The Starlark-level call-stack of functions.
@@ -51,8 +73,10 @@ pub struct EvaluationContext<'v, 'a> {
     pub(crate) loader: &'a dyn FileLoader,
     // The codemap that corresponds to this module.
     pub(crate) codemap: Arc<CodeMap>,
-    // Should we enable profiling or not
-    pub(crate) profiling: bool,
+    // What we are profiling, if anything, and where to write it
+    pub(crate) profiling: Option<(ProfileMode, PathBuf)>,
+    // The timings, when profiling time
+    pub(crate) time_profile: TimeProfile,
     // Is GC disabled for some reason
     pub(crate) disable_gc: bool,
     // Size of the heap when we last performed a GC
@@ -63,6 +87,18 @@ pub struct EvaluationContext<'v, 'a> {
     pub(crate) check_types: bool,
     // Callback on every statement
     pub on_stmt: Option<&'a dyn Fn(Span, &mut EvaluationContext<'v, 'a>)>,
+    // Callback on every `if`, conditional expression, `and` and `or`, with
+    // whether the condition (or left operand) was true
+    pub on_branch: Option<&'a dyn Fn(Span, bool, &mut EvaluationContext<'v, 'a>)>,
+    // Statements and loop iterations run so far, and how many are allowed
+    pub(crate) steps: u64,
+    pub(crate) step_budget: Option<u64>,
+    // Set, possibly from another thread, to stop evaluation
+    pub(crate) cancellation: Option<Arc<AtomicBool>>,
+    // How deeply comparisons may recurse into nested values
+    max_compare_depth: u32,
+    // Where the output of `print` goes, defaults to stderr
+    pub print_handler: &'a dyn PrintHandler,
     /// Field that can be used for any purpose you want (can store types you define)
     pub extra: Option<&'a dyn AnyLifetime<'a>>,
     /// Field that can be used for any purpose you want (can store heap-resident `Value<'v>`)
@@ -86,10 +122,17 @@ impl<'v, 'a> EvaluationContext<'v, 'a> {
             extra_v: None,
             last_heap_size: 0,
             disable_gc: false,
-            profiling: false,
+            profiling: None,
+            time_profile: TimeProfile::default(),
             check_types: true,
             heap: env.heap(),
             on_stmt: None,
+            on_branch: None,
+            steps: 0,
+            step_budget: None,
+            cancellation: None,
+            max_compare_depth: MAX_RECURSION,
+            print_handler: &StderrPrintHandler,
         }
     }
 
@@ -100,6 +143,125 @@ impl<'v, 'a> EvaluationContext<'v, 'a> {
         self.disable_gc = true;
     }
 
+    /// Profile the modules evaluated from now on, writing the results to `path`
+    /// at the end of each call to `eval_module`. Disables garbage collection,
+    /// since the profiles rely on values not moving.
+    pub fn enable_profile<P: AsRef<Path>>(&mut self, mode: ProfileMode, path: P) {
+        self.disable_gc();
+        self.profiling = Some((mode, path.as_ref().to_owned()));
+    }
+
+    pub(crate) fn profile_mode(&self) -> Option<ProfileMode> {
+        self.profiling.as_ref().map(|x| x.0)
+    }
+
+    pub(crate) fn profile_stmt(&mut self, span: Span) {
+        match self.profile_mode() {
+            None | Some(ProfileMode::Heap) => {}
+            Some(_) => self.time_profile.stmt(span, &self.codemap),
+        }
+    }
+
+    fn profile_enter(&mut self, function: Value<'v>) {
+        match self.profile_mode() {
+            None => {}
+            Some(ProfileMode::Heap) => self.heap.record_call_enter(function),
+            Some(_) => self.time_profile.enter(function),
+        }
+    }
+
+    pub(crate) fn profile_exit(&mut self) {
+        match self.profile_mode() {
+            None => {}
+            Some(ProfileMode::Heap) => self.heap.record_call_exit(),
+            Some(_) => self.time_profile.exit(),
+        }
+    }
+
+    pub(crate) fn write_profile(&self) -> anyhow::Result<()> {
+        match &self.profiling {
+            None => {}
+            Some((ProfileMode::Heap, path)) => self.heap.write_profile(path)?,
+            Some((mode, path)) => self.time_profile.write(*mode, path)?,
+        }
+        Ok(())
+    }
+
+    /// Stop evaluation with [`InterruptError::StepBudgetExceeded`] once it has
+    /// taken more than `steps` steps in total, a step being a statement, or an
+    /// iteration of a loop or comprehension.
+    pub fn set_step_budget(&mut self, steps: u64) {
+        self.step_budget = Some(steps);
+    }
+
+    /// The number of steps taken so far, as limited by
+    /// [`set_step_budget`](EvaluationContext::set_step_budget).
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Stop evaluation with [`InterruptError::Cancelled`] at the next step
+    /// after `token` is set, which can be done from another thread, e.g. when
+    /// a deadline passes.
+    pub fn set_cancellation(&mut self, token: Arc<AtomicBool>) {
+        self.cancellation = Some(token);
+    }
+
+    /// Fail with a "Too many recursion levels" error when calls are nested
+    /// more than `depth` deep, which defaults to 40. Each call uses the Rust
+    /// stack, so a much larger limit may need a larger stack.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.call_stack.set_max_depth(depth);
+    }
+
+    /// Fail with a "Too many recursion levels" error when comparing values
+    /// recurses more than `depth` deep, as with deeply nested or cyclic lists.
+    /// The `repr` of such values shows anything nested deeper as `...`.
+    pub fn set_max_compare_depth(&mut self, depth: u32) {
+        self.max_compare_depth = depth;
+    }
+
+    // Apply the limits of `parent` to a context evaluating a module it loads,
+    // so they can't be escaped by doing the work in the loaded module
+    pub(crate) fn inherit_limits(&mut self, parent: &EvaluationContext) {
+        self.steps = parent.steps;
+        self.step_budget = parent.step_budget;
+        self.cancellation = parent.cancellation.clone();
+        let depth = parent.call_stack.max_depth();
+        self.set_max_call_depth(depth.saturating_sub(parent.call_stack.depth()));
+        self.max_compare_depth = parent.max_compare_depth;
+        // The loaded module has its own heap, so gets what is left of ours
+        let limit = parent.heap.limit();
+        let usage = parent.heap.usage();
+        self.heap.set_limit(HeapLimit {
+            bytes: limit.bytes.map(|x| x.saturating_sub(usage.bytes)),
+            objects: limit.objects.map(|x| x.saturating_sub(usage.objects)),
+        });
+    }
+
+    // Apply `set_max_compare_depth` until the guard is dropped, since comparisons
+    // can't see the context
+    pub(crate) fn compare_depth_guard(&self) -> call_stack::MaxRecursionGuard {
+        call_stack::set_max_recursion(self.max_compare_depth)
+    }
+
+    // Take a step, failing if we are over budget, have been cancelled, or the
+    // heap is over its limit
+    pub(crate) fn step(&mut self) -> anyhow::Result<()> {
+        self.steps += 1;
+        self.heap().check_limit()?;
+        match self.step_budget {
+            Some(budget) if self.steps > budget => {
+                return Err(InterruptError::StepBudgetExceeded(budget).into());
+            }
+            _ => {}
+        }
+        match &self.cancellation {
+            Some(token) if token.load(Ordering::Relaxed) => Err(InterruptError::Cancelled.into()),
+            _ => Ok(()),
+        }
+    }
+
     pub fn call_stack(&self) -> &CallStack<'v> {
         &self.call_stack
     }
@@ -115,6 +277,14 @@ impl<'v, 'a> EvaluationContext<'v, 'a> {
         self.codemap.look_up_span(span)
     }
 
+    // Report a branch to `on_branch`, returning which way it went
+    pub(crate) fn branch(&mut self, span: Span, taken: bool) -> bool {
+        if let Some(f) = self.on_branch {
+            f(span, taken, self)
+        }
+        taken
+    }
+
     /// Called to add an entry to the call stack, from the caller.
     /// Called for all types of function (including those written in Rust)
     pub(crate) fn with_call_stack<R>(
@@ -124,15 +294,12 @@ impl<'v, 'a> EvaluationContext<'v, 'a> {
         within: impl FnOnce(&mut Self) -> anyhow::Result<R>,
     ) -> anyhow::Result<R> {
         self.call_stack.push(function, location)?;
-        if self.profiling {
-            self.heap.record_call_enter(function);
-        }
+        let _guard = self.compare_depth_guard();
+        self.profile_enter(function);
         // Make sure we always call .pop regardless
         let res = within(self);
         self.call_stack.pop();
-        if self.profiling {
-            self.heap.record_call_exit();
-        }
+        self.profile_exit();
         res
     }
 
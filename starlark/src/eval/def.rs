@@ -75,6 +75,8 @@ struct DefInfo {
     // after the parameters are evaluated.
     #[derivative(Debug = "ignore")]
     body: EvalCompiled,
+    // Whether the dialect allows calling this function while it is running
+    enable_recursion: bool,
 }
 
 impl Compiler<'_> {
@@ -109,7 +111,11 @@ impl Compiler<'_> {
         let body = self.stmt(suite);
         let scope_names = self.scope.exit_def();
 
-        let info = Arc::new(DefInfo { scope_names, body });
+        let info = Arc::new(DefInfo {
+            scope_names,
+            body,
+            enable_recursion: self.enable_recursion,
+        });
 
         fn run<'v>(
             x: &Option<EvalCompiled>,
@@ -353,6 +359,9 @@ impl<'a, 'v, V: ValueLike<'v>, RefV: AsValueRef<'v>> DefInvokerGen<'v, 'a, V, Re
     pub fn invoke(self, context: &mut EvaluationContext<'v, '_>) -> anyhow::Result<Value<'v>> {
         // println!("invoking {}", self.def.stmt.name.node);
         let DefInvokerGen { collect, def } = self;
+        if !def.stmt.enable_recursion {
+            context.call_stack.check_not_recursive()?;
+        }
         let slots = collect.done(context.heap)?;
 
         if context.check_types {
@@ -17,11 +17,15 @@
 
 //! Evaluation of an expression.
 use crate::{
-    collections::{Hashed, SmallMap},
+    collections::{Hashed, SmallMap, SmallSet},
     environment::EnvironmentError,
     errors::Diagnostic,
     eval::{context::EvaluationContext, scope::Slot, thrw, Compiler, EvalCompiled, EvalException},
-    syntax::ast::{Argument, AstExpr, AstLiteral, BinOp, Expr, Stmt, Visibility},
+    stdlib::string::format_string,
+    syntax::{
+        ast::{Argument, AstExpr, AstLiteral, BinOp, Expr, Stmt, Visibility},
+        lexer::TokenInt,
+    },
     values::{
         dict::FrozenDict, function::WrappedMethod, list::FrozenList, FrozenHeap, FrozenValue, *,
     },
@@ -30,7 +34,7 @@ use codemap::{Span, Spanned};
 use either::Either;
 use function::{FunctionInvoker, NativeAttribute};
 use gazebo::prelude::*;
-use std::{cmp::Ordering, collections::HashMap};
+use std::{cmp::Ordering, collections::HashMap, mem};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
@@ -154,6 +158,40 @@ fn eval_dot(
     }
 }
 
+// Split the format string of an f-string, as produced by the lexer, into the
+// literal text before each field along with the field itself, and the literal
+// text after the last field. The lexer only ever escapes braces in the
+// literal text, and never puts braces inside a field.
+fn split_fstring(format: &str) -> (Vec<(String, String)>, String) {
+    let mut fields = Vec::new();
+    let mut literal = String::new();
+    let mut it = format.chars().peekable();
+    while let Some(c) = it.next() {
+        match c {
+            '{' if it.peek() == Some(&'{') => {
+                it.next();
+                literal.push('{');
+            }
+            '}' if it.peek() == Some(&'}') => {
+                it.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut field = String::from("{");
+                for c in &mut it {
+                    field.push(c);
+                    if c == '}' {
+                        break;
+                    }
+                }
+                fields.push((mem::take(&mut literal), field));
+            }
+            c => literal.push(c),
+        }
+    }
+    (fields, literal)
+}
+
 impl Compiler<'_> {
     fn exprs(
         &mut self,
@@ -177,8 +215,13 @@ impl Compiler<'_> {
 impl AstLiteral {
     fn compile(&self, heap: &FrozenHeap) -> FrozenValue {
         match self {
-            AstLiteral::IntLiteral(i) => FrozenValue::new_int(i.node),
+            AstLiteral::IntLiteral(i) => match &i.node {
+                TokenInt::I32(i) => FrozenValue::new_int(*i),
+                TokenInt::BigInt(i) => heap.alloc((**i).clone()),
+            },
+            AstLiteral::FloatLiteral(x) => heap.alloc(x.node),
             AstLiteral::StringLiteral(x) => heap.alloc(x.node.as_str()),
+            AstLiteral::BytesLiteral(x) => heap.alloc(bytes::StarlarkBytes(x.node.clone())),
         }
     }
 }
@@ -188,7 +231,9 @@ impl Expr {
         match self {
             Expr::Literal(x) => match x {
                 AstLiteral::IntLiteral(_) => Some(x),
+                AstLiteral::FloatLiteral(_) => Some(x),
                 AstLiteral::StringLiteral(_) => Some(x),
+                AstLiteral::BytesLiteral(_) => Some(x),
             },
             _ => None,
         }
@@ -196,7 +241,10 @@ impl Expr {
 
     fn unpack_int_literal(&self) -> Option<i32> {
         match self {
-            Expr::Literal(AstLiteral::IntLiteral(i)) => Some(i.node),
+            Expr::Literal(AstLiteral::IntLiteral(Spanned {
+                node: TokenInt::I32(i),
+                ..
+            })) => Some(*i),
             _ => None,
         }
     }
@@ -365,12 +413,24 @@ impl Compiler<'_> {
                     Ok(context.heap.alloc(dict::Dict::new(r)))
                 }
             }
+            Expr::Set(exprs) => {
+                let exprs = self.exprs(exprs);
+                box move |context| {
+                    let xs = exprs(context)?;
+                    let mut r = SmallSet::with_capacity(xs.len());
+                    for x in xs {
+                        r.insert_hashed(thrw(x.get_hashed(), span, context)?);
+                    }
+                    Ok(context.heap.alloc(set::Set::new(r)))
+                }
+            }
             Expr::If(box (cond, then_expr, else_expr)) => {
                 let cond = self.expr(cond);
                 let then_expr = self.expr(then_expr);
                 let else_expr = self.expr(else_expr);
                 box move |context| {
-                    if cond(context)?.to_bool() {
+                    let taken = cond(context)?.to_bool();
+                    if context.branch(span, taken) {
                         then_expr(context)
                     } else {
                         else_expr(context)
@@ -468,6 +528,10 @@ impl Compiler<'_> {
                     box move |_| Ok(Value::new_frozen(val))
                 }
             },
+            Expr::BitNot(expr) => {
+                let expr = self.expr(*expr);
+                box move |context| thrw(expr(context)?.bit_not(context.heap), span, context)
+            }
             Expr::Op(left, op, right) => {
                 if let Some(x) = Expr::reduces_to_string(op, &left, &right) {
                     let val = self.heap.alloc(x);
@@ -478,11 +542,19 @@ impl Compiler<'_> {
                     match op {
                         BinOp::Or => box move |context| {
                             let l = l(context)?;
-                            if l.to_bool() { Ok(l) } else { r(context) }
+                            if context.branch(span, l.to_bool()) {
+                                Ok(l)
+                            } else {
+                                r(context)
+                            }
                         },
                         BinOp::And => box move |context| {
                             let l = l(context)?;
-                            Ok(if !l.to_bool() { l } else { r(context)? })
+                            Ok(if !context.branch(span, l.to_bool()) {
+                                l
+                            } else {
+                                r(context)?
+                            })
                         },
                         BinOp::EqualsTo => eval_equals(span, l, r, |x| x),
                         BinOp::Different => eval_equals(span, l, r, |x| !x),
@@ -525,6 +597,9 @@ impl Compiler<'_> {
                                 context,
                             )
                         },
+                        BinOp::Division => box move |context| {
+                            thrw(l(context)?.div(r(context)?, context.heap), span, context)
+                        },
                         BinOp::FloorDivision => box move |context| {
                             thrw(
                                 l(context)?.floor_div(r(context)?, context.heap),
@@ -532,14 +607,63 @@ impl Compiler<'_> {
                                 context,
                             )
                         },
-                        BinOp::Pipe => {
-                            box move |context| thrw(l(context)?.pipe(r(context)?), span, context)
-                        }
+                        BinOp::Pipe => box move |context| {
+                            thrw(l(context)?.pipe(r(context)?, context.heap), span, context)
+                        },
+                        BinOp::BitAnd => box move |context| {
+                            thrw(
+                                l(context)?.bit_and(r(context)?, context.heap),
+                                span,
+                                context,
+                            )
+                        },
+                        BinOp::BitXor => box move |context| {
+                            thrw(
+                                l(context)?.bit_xor(r(context)?, context.heap),
+                                span,
+                                context,
+                            )
+                        },
+                        BinOp::LeftShift => box move |context| {
+                            thrw(
+                                l(context)?.left_shift(r(context)?, context.heap),
+                                span,
+                                context,
+                            )
+                        },
+                        BinOp::RightShift => box move |context| {
+                            thrw(
+                                l(context)?.right_shift(r(context)?, context.heap),
+                                span,
+                                context,
+                            )
+                        },
                     }
                 }
             }
             Expr::ListComprehension(x, clauses) => self.list_comprehension(*x, clauses),
             Expr::DictComprehension(box (k, v), clauses) => self.dict_comprehension(k, v, clauses),
+            Expr::SetComprehension(x, clauses) => self.set_comprehension(*x, clauses),
+            Expr::FString(format, exprs) => {
+                // Format each field on its own, so an error in one points at its expression
+                let (fields, rest) = split_fstring(&format.node);
+                let fields = fields
+                    .into_iter()
+                    .zip(exprs)
+                    .map(|((literal, field), x)| (literal, field, x.span, self.expr(x)))
+                    .collect::<Vec<_>>();
+                box move |context| {
+                    let mut res = String::new();
+                    for (literal, field, span, x) in &fields {
+                        res.push_str(literal);
+                        let x = x(context)?;
+                        let x = thrw(format_string(field, &[x], &SmallMap::new()), *span, context)?;
+                        res.push_str(&x);
+                    }
+                    res.push_str(&rest);
+                    Ok(context.heap.alloc(res))
+                }
+            }
             Expr::Literal(x) => {
                 let val = x.compile(self.heap);
                 box move |_| Ok(Value::new_frozen(val))
@@ -20,17 +20,35 @@
 
 use crate::{
     environment::{FrozenModule, Globals, Module},
-    eval::eval,
-    syntax::Dialect,
+    eval::{eval, eval_module, EvaluationContext},
+    syntax::{ast::Stmt, parse, AstModule, Dialect},
     values::Value,
 };
-use anyhow::anyhow;
-use std::collections::HashMap;
+use anyhow::{anyhow, Context};
+use codemap::{Span, SpanLoc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
 
 /// A trait for loading file using the load statement path.
 pub trait FileLoader {
     /// Open the file given by the load statement `path`.
     fn load(&self, path: &str) -> anyhow::Result<&FrozenModule>;
+
+    /// Open the file given by a load statement in the module `context` is
+    /// evaluating, so a loader which evaluates the file can apply the same
+    /// limits. Defaults to [`load`](FileLoader::load).
+    fn load_in(
+        &self,
+        path: &str,
+        _context: &mut EvaluationContext<'_, '_>,
+    ) -> anyhow::Result<&FrozenModule> {
+        self.load(path)
+    }
 }
 
 /// File loader which returns error unconditionally.
@@ -100,3 +118,478 @@ pub fn eval_with_modules<'v>(
         ReturnFileLoader { modules },
     )
 }
+
+#[derive(Debug, Error)]
+enum FileLoaderError {
+    #[error("Invalid label `{0}`, expected `@repository//package:file.bzl`")]
+    InvalidLabel(String),
+    #[error("Unknown repository `@{0}`")]
+    UnknownRepository(String),
+    #[error("Load cycle {0}, via the loads at {1}")]
+    Cycle(String, String),
+}
+
+// A module being evaluated by a `FileSystemLoader`
+struct Loading {
+    // The name we gave the module, e.g. `//package:file.bzl`
+    name: String,
+    path: PathBuf,
+    repository: Option<String>,
+    // The module of each `load` statement, and where it is
+    loads: Vec<(String, SpanLoc)>,
+    // The module we are loading, if any
+    pending: Option<String>,
+}
+
+/// [`FileLoader`] which loads modules from the filesystem, evaluating each one
+/// at most once and caching the result, and failing on `load` cycles.
+///
+/// A module can be given as:
+///
+/// * `//package:file.bzl`, relative to the root of the repository of the
+///   module doing the loading, or the main repository.
+/// * `@repository//package:file.bzl`, relative to the root of a repository
+///   added with [`add_repository`](FileSystemLoader::add_repository).
+/// * `:file.bzl`, `file.bzl` or `../package/file.bzl`, relative to the
+///   directory of the module doing the loading, or the main repository.
+///
+/// The loader evaluates modules itself, so can be used directly to evaluate
+/// the first module, e.g. `loader.load("//package:main.bzl")`. A module loaded
+/// by another gets the step budget, cancellation, heap limit, depth limits and
+/// print handler of the [`EvaluationContext`] loading it.
+pub struct FileSystemLoader<'a> {
+    /// Called on every statement of the modules this loader evaluates, like
+    /// [`EvaluationContext::on_stmt`].
+    pub on_stmt: Option<&'a dyn Fn(Span, &mut EvaluationContext)>,
+    /// Called on every branch of the modules this loader evaluates, like
+    /// [`EvaluationContext::on_branch`].
+    pub on_branch: Option<&'a dyn Fn(Span, bool, &mut EvaluationContext)>,
+    /// Called with every module this loader parses, before evaluating it, e.g.
+    /// for [`Coverage::add_module`](crate::debug::Coverage::add_module).
+    pub on_module: Option<&'a dyn Fn(&AstModule)>,
+    root: PathBuf,
+    repositories: HashMap<String, PathBuf>,
+    globals: &'a Globals,
+    dialect: Dialect,
+    // Boxed, and never removed or replaced, so the modules outlive `load`
+    modules: RefCell<HashMap<PathBuf, Box<FrozenModule>>>,
+    // The modules being evaluated, each loading the next
+    loading: RefCell<Vec<Loading>>,
+}
+
+// The modules of the `load` statements in a module
+fn loads(module: &AstModule) -> Vec<(String, SpanLoc)> {
+    let mut res = Vec::new();
+    module.statement.visit_stmt(|x| {
+        if let Stmt::Load(path, _, _) = &x.node {
+            res.push((path.node.clone(), module.codemap.look_up_span(x.span)));
+        }
+    });
+    res
+}
+
+impl<'a> FileSystemLoader<'a> {
+    /// A loader with `root` as the root of the main repository, evaluating
+    /// modules with `globals` and `dialect`.
+    pub fn new<P: AsRef<Path>>(root: P, globals: &'a Globals, dialect: &Dialect) -> Self {
+        Self {
+            on_stmt: None,
+            on_branch: None,
+            on_module: None,
+            root: root.as_ref().to_owned(),
+            repositories: HashMap::new(),
+            globals,
+            dialect: dialect.clone(),
+            modules: RefCell::new(HashMap::new()),
+            loading: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Resolve `@name//package:file.bzl` relative to `root`.
+    pub fn add_repository<P: AsRef<Path>>(&mut self, name: &str, root: P) {
+        self.repositories
+            .insert(name.to_owned(), root.as_ref().to_owned());
+    }
+
+    /// Call `f`, which evaluates the module at `path` with an
+    /// [`EvaluationContext`] using this loader, e.g. to give the first module a
+    /// context of its own. Its loads are then resolved relative to `path`.
+    pub fn evaluating<R>(&self, path: &Path, f: impl FnOnce() -> R) -> R {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        self.loading.borrow_mut().push(Loading {
+            name: self.name(None, &path),
+            path,
+            repository: None,
+            loads: Vec::new(),
+            pending: None,
+        });
+        let res = f();
+        self.loading.borrow_mut().pop();
+        res
+    }
+
+    fn repository_root(&self, repository: Option<&str>) -> anyhow::Result<&Path> {
+        match repository {
+            None => Ok(&self.root),
+            Some(x) => match self.repositories.get(x) {
+                Some(root) => Ok(root),
+                None => Err(FileLoaderError::UnknownRepository(x.to_owned()).into()),
+            },
+        }
+    }
+
+    /// The file a `load` of `module` refers to, where the `load` is in the file
+    /// `current`, or in the main repository if there is no current file. Tools
+    /// can use this to follow a `load` to the same file evaluation would.
+    pub fn resolve(&self, module: &str, current: Option<&Path>) -> anyhow::Result<PathBuf> {
+        let current = current.map(|x| (self.repository_of(x), x));
+        Ok(self.resolve_from(module, current)?.1)
+    }
+
+    // The repository whose root contains `path`, or `None` for the main repository
+    fn repository_of(&self, path: &Path) -> Option<&str> {
+        let canonical = |x: &Path| fs::canonicalize(x).unwrap_or_else(|_| x.to_owned());
+        let path = canonical(path);
+        self.repositories
+            .iter()
+            .map(|(name, root)| (name, canonical(root)))
+            .filter(|(_, root)| path.starts_with(root))
+            .max_by_key(|(_, root)| root.components().count())
+            .map(|(name, _)| name.as_str())
+    }
+
+    // The repository and file of a module, as named by `load` in `current`,
+    // the repository and file of the module doing the loading
+    fn resolve_from(
+        &self,
+        module: &str,
+        current: Option<(Option<&str>, &Path)>,
+    ) -> anyhow::Result<(Option<String>, PathBuf)> {
+        let current_repository = current.and_then(|x| x.0).map(|x| x.to_owned());
+        let (repository, label) = if let Some(x) = module.strip_prefix('@') {
+            match x.split_once("//") {
+                Some((repository, label)) => (Some(repository.to_owned()), label),
+                None => return Err(FileLoaderError::InvalidLabel(module.to_owned()).into()),
+            }
+        } else if let Some(label) = module.strip_prefix("//") {
+            (current_repository, label)
+        } else {
+            let dir = match current {
+                Some((_, path)) => path.parent().unwrap_or(path),
+                None => self.repository_root(None)?,
+            };
+            let file = module.strip_prefix(':').unwrap_or(module);
+            return Ok((current_repository, dir.join(file)));
+        };
+        let file = match label.split_once(':') {
+            Some((package, file)) => Path::new(package).join(file),
+            None => PathBuf::from(label),
+        };
+        let root = self.repository_root(repository.as_deref())?;
+        Ok((repository, root.join(file)))
+    }
+
+    // The name of a module, as a label if it is within its repository
+    fn name(&self, repository: Option<&str>, path: &Path) -> String {
+        let root = self
+            .repository_root(repository)
+            .ok()
+            .and_then(|x| fs::canonicalize(x).ok());
+        let relative = root.as_ref().and_then(|x| path.strip_prefix(x).ok());
+        match relative {
+            Some(relative) => format!(
+                "{}//{}:{}",
+                repository.map_or(String::new(), |x| format!("@{}", x)),
+                relative
+                    .parent()
+                    .map_or(String::new(), |x| x.to_string_lossy().replace('\\', "/")),
+                relative.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            None => path.display().to_string(),
+        }
+    }
+
+    // Fail if `path` is already being evaluated, describing the chain of loads
+    // that got us back to it
+    fn check_cycle(&self, path: &Path) -> anyhow::Result<()> {
+        let loading = self.loading.borrow();
+        let start = match loading.iter().position(|x| x.path == path) {
+            None => return Ok(()),
+            Some(start) => start,
+        };
+        let cycle = &loading[start..];
+        let mut names: Vec<_> = cycle.iter().map(|x| format!("`{}`", x.name)).collect();
+        names.push(names[0].clone());
+        let spans: Vec<_> = cycle
+            .iter()
+            .filter_map(|x| {
+                let pending = x.pending.as_ref()?;
+                let load = x.loads.iter().find(|load| &load.0 == pending)?;
+                Some(load.1.to_string())
+            })
+            .collect();
+        Err(FileLoaderError::Cycle(names.join(" -> "), spans.join(", ")).into())
+    }
+
+    fn eval_file(
+        &self,
+        repository: Option<String>,
+        path: &Path,
+        parent: Option<&mut EvaluationContext>,
+    ) -> anyhow::Result<FrozenModule> {
+        let name = self.name(repository.as_deref(), path);
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Reading `{}` to load it", path.display()))?;
+        // Locations are given by path, so tools can find the file
+        let ast = parse(&path.to_string_lossy(), content, &self.dialect)?;
+        if let Some(on_module) = self.on_module {
+            on_module(&ast);
+        }
+        let env = Module::new(&name);
+        self.loading.borrow_mut().push(Loading {
+            name,
+            path: path.to_owned(),
+            repository,
+            loads: loads(&ast),
+            pending: None,
+        });
+        let res = {
+            let mut context = EvaluationContext::new(&env, self.globals, self);
+            context.on_stmt = self.on_stmt;
+            context.on_branch = self.on_branch;
+            match parent {
+                None => eval_module(ast, &mut context),
+                Some(parent) => {
+                    context.inherit_limits(parent);
+                    context.print_handler = parent.print_handler;
+                    let res = eval_module(ast, &mut context);
+                    parent.steps = context.steps;
+                    res
+                }
+            }
+        };
+        self.loading.borrow_mut().pop();
+        res?;
+        Ok(env.freeze())
+    }
+}
+
+impl FileSystemLoader<'_> {
+    fn load_from(
+        &self,
+        module: &str,
+        parent: Option<&mut EvaluationContext>,
+    ) -> anyhow::Result<&FrozenModule> {
+        let (repository, path) = {
+            let loading = self.loading.borrow();
+            let current = loading
+                .last()
+                .map(|x| (x.repository.as_deref(), x.path.as_path()));
+            self.resolve_from(module, current)?
+        };
+        let path = fs::canonicalize(&path)
+            .with_context(|| format!("Finding `{}` to load `{}`", path.display(), module))?;
+        if let Some(x) = self.modules.borrow().get(&path) {
+            let x: *const FrozenModule = &**x;
+            // Safe because the box is never removed or replaced
+            return Ok(unsafe { &*x });
+        }
+        if let Some(x) = self.loading.borrow_mut().last_mut() {
+            x.pending = Some(module.to_owned());
+        }
+        self.check_cycle(&path)?;
+        let res = box self.eval_file(repository, &path, parent)?;
+        let x: *const FrozenModule = &*res;
+        self.modules.borrow_mut().insert(path, res);
+        // Safe because the box is never removed or replaced
+        Ok(unsafe { &*x })
+    }
+}
+
+impl FileLoader for FileSystemLoader<'_> {
+    fn load(&self, module: &str) -> anyhow::Result<&FrozenModule> {
+        self.load_from(module, None)
+    }
+
+    fn load_in(
+        &self,
+        module: &str,
+        context: &mut EvaluationContext<'_, '_>,
+    ) -> anyhow::Result<&FrozenModule> {
+        self.load_from(module, Some(context))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        errors::Diagnostic,
+        eval::{eval_module, InterruptError},
+        stdlib::standard_environment,
+    };
+    use std::{cell::Cell, ptr};
+
+    fn write(root: &Path, files: &[(&str, &str)]) {
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_file_system_loader() {
+        let root = std::env::temp_dir().join("starlark_file_system_loader_test");
+        let _ignore = fs::remove_dir_all(&root);
+        write(
+            &root,
+            &[
+                (
+                    "main/pkg/a.bzl",
+                    "load(':b.bzl', 'b')\nload('../c.bzl', 'c')\nload('@ext//:d.bzl', 'd')\na = b + c + d",
+                ),
+                ("main/pkg/b.bzl", "load('//:c.bzl', 'c')\nb = c * 10"),
+                ("main/c.bzl", "c = 1"),
+                ("ext/d.bzl", "load('//sub:e.bzl', 'e')\nd = e"),
+                ("ext/sub/e.bzl", "e = 100"),
+            ],
+        );
+        let globals = standard_environment().build();
+        let mut loader = FileSystemLoader::new(root.join("main"), &globals, &Dialect::Standard);
+        loader.add_repository("ext", root.join("ext"));
+
+        let a = loader.load("//pkg:a.bzl").unwrap();
+        assert_eq!(a.get("a").unwrap().unpack_int(), Some(111));
+        // Each module is only evaluated once
+        assert!(ptr::eq(
+            loader.load("c.bzl").unwrap(),
+            loader.load("//:c.bzl").unwrap()
+        ));
+        assert!(loader.load("@missing//:x.bzl").is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_system_loader_resolve() {
+        let root = std::env::temp_dir().join("starlark_file_system_loader_resolve_test");
+        let _ignore = fs::remove_dir_all(&root);
+        write(&root, &[("main/pkg/a.bzl", ""), ("ext/sub/d.bzl", "")]);
+        let root = fs::canonicalize(&root).unwrap();
+        let globals = standard_environment().build();
+        let mut loader = FileSystemLoader::new(root.join("main"), &globals, &Dialect::Standard);
+        loader.add_repository("ext", root.join("ext"));
+
+        let a = root.join("main/pkg/a.bzl");
+        let a = Some(a.as_path());
+        let d = root.join("ext/sub/d.bzl");
+        let d = Some(d.as_path());
+        let resolve =
+            |module: &str, current: Option<&Path>| loader.resolve(module, current).unwrap();
+        assert_eq!(resolve("//pkg:b.bzl", None), root.join("main/pkg/b.bzl"));
+        assert_eq!(resolve(":b.bzl", a), root.join("main/pkg/b.bzl"));
+        assert_eq!(resolve("../c.bzl", a), root.join("main/pkg/../c.bzl"));
+        assert_eq!(resolve("@ext//sub:e.bzl", a), root.join("ext/sub/e.bzl"));
+        // `//` is relative to the repository of the module doing the loading
+        assert_eq!(resolve("//:e.bzl", d), root.join("ext/e.bzl"));
+        assert_eq!(resolve("//:c.bzl", a), root.join("main/c.bzl"));
+        assert!(loader.resolve("@missing//:x.bzl", a).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_system_loader_cycle() {
+        let root = std::env::temp_dir().join("starlark_file_system_loader_cycle_test");
+        let _ignore = fs::remove_dir_all(&root);
+        write(
+            &root,
+            &[
+                ("a.bzl", "load('b.bzl', 'b')\na = b"),
+                ("b.bzl", "x = 1\nload('//:a.bzl', 'a')\nb = a"),
+            ],
+        );
+        let globals = standard_environment().build();
+        let loader = FileSystemLoader::new(&root, &globals, &Dialect::Standard);
+        let e = loader.load("a.bzl").unwrap_err();
+        let e = e.downcast_ref::<Diagnostic>().unwrap();
+        let path = |x: &str| {
+            fs::canonicalize(root.join(x))
+                .unwrap()
+                .display()
+                .to_string()
+        };
+        assert_eq!(
+            e.message.to_string(),
+            format!(
+                "Load cycle `//:a.bzl` -> `//:b.bzl` -> `//:a.bzl`, via the loads at {}:1:1: 1:19, {}:2:1: 2:23",
+                path("a.bzl"),
+                path("b.bzl")
+            )
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_system_loader_limits() {
+        let root = std::env::temp_dir().join("starlark_file_system_loader_limits_test");
+        let _ignore = fs::remove_dir_all(&root);
+        write(
+            &root,
+            &[(
+                "spin.bzl",
+                "def f():\n    for _ in range(1000000000):\n        pass\nx = f()",
+            )],
+        );
+        let globals = standard_environment().build();
+        let stmts = Cell::new(0);
+        let on_stmt = |_: Span, _: &mut EvaluationContext| stmts.set(stmts.get() + 1);
+        let mut loader = FileSystemLoader::new(&root, &globals, &Dialect::Standard);
+        loader.on_stmt = Some(&on_stmt);
+
+        let env = Module::new("main");
+        let mut context = EvaluationContext::new(&env, &globals, &loader);
+        context.set_step_budget(1000);
+        let main = parse(
+            "main",
+            "load('spin.bzl', 'x')".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        let e = eval_module(main, &mut context).unwrap_err();
+        let e = e.downcast_ref::<Diagnostic>().unwrap();
+        assert!(matches!(
+            e.message.downcast_ref(),
+            Some(InterruptError::StepBudgetExceeded(1000))
+        ));
+        // The loaded module's steps count towards the budget
+        assert!(context.steps() > 1000);
+        assert!(stmts.get() > 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_file_system_loader_evaluating() {
+        let root = std::env::temp_dir().join("starlark_file_system_loader_evaluating_test");
+        let _ignore = fs::remove_dir_all(&root);
+        write(&root, &[("pkg/lib.bzl", "x = 1")]);
+        let globals = standard_environment().build();
+        let loader = FileSystemLoader::new(&root, &globals, &Dialect::Standard);
+
+        let env = Module::new("main");
+        let mut context = EvaluationContext::new(&env, &globals, &loader);
+        let main = parse(
+            "main",
+            "load(':lib.bzl', 'x')".to_owned(),
+            &Dialect::Standard,
+        )
+        .unwrap();
+        // Relative to `pkg`, rather than the root
+        loader
+            .evaluating(&root.join("pkg/main.bzl"), || {
+                eval_module(main, &mut context)
+            })
+            .unwrap();
+        assert_eq!(env.get("x").unwrap().unpack_int(), Some(1));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -36,14 +36,18 @@ use std::{fmt::Debug, mem, sync::Arc};
 use thiserror::Error;
 
 pub use crate::eval::file_loader::*;
-pub use context::EvaluationContext;
+pub use context::{EvaluationContext, InterruptError};
 pub use parameters::{Parameters, ParametersCollect};
+pub use print_handler::*;
+pub use profile::ProfileMode;
 pub(crate) use scope::ScopeNames;
 
 pub(crate) mod call_stack;
 mod expr;
 mod file_loader;
 mod parameters;
+mod print_handler;
+mod profile;
 mod scope;
 mod stmt;
 
@@ -124,13 +128,19 @@ pub(crate) struct Compiler<'a> {
     globals: &'a Globals,
     errors: Vec<anyhow::Error>,
     codemap: Arc<CodeMap>,
+    enable_recursion: bool,
 }
 
 pub fn eval_module<'v>(
     modu: AstModule,
     context: &mut EvaluationContext<'v, '_>,
 ) -> anyhow::Result<Value<'v>> {
-    let AstModule { codemap, statement } = modu;
+    let AstModule {
+        codemap,
+        statement,
+        enable_recursion,
+        ..
+    } = modu;
     let module_env = context.assert_module_env();
 
     let scope = Scope::enter_module(module_env.name(), module_env.names(), &statement);
@@ -143,6 +153,7 @@ pub fn eval_module<'v>(
         globals: context.globals,
         errors: Vec::new(),
         codemap: codemap.dupe(),
+        enable_recursion,
     };
     let stmt = compiler.stmt(statement);
 
@@ -166,26 +177,28 @@ pub fn eval_module<'v>(
         .call_stack
         .push(Value::new_none(), Some((codemap, span)))
         .unwrap();
-    if context.profiling {
-        // Make sure we don't GC the excess entries
-        context.disable_gc();
-        context.heap.record_call_enter(Value::new_none());
+    match context.profile_mode() {
+        None => {}
+        Some(ProfileMode::Heap) => context.heap.record_call_enter(Value::new_none()),
+        Some(_) => context.time_profile.enter_module(module_env.name()),
     }
 
     // Evaluation
+    let compare_depth = context.compare_depth_guard();
     let res = stmt(context);
+    mem::drop(compare_depth);
 
     // Clean up the world, putting everything back
     context.call_stack.pop();
-    if context.profiling {
-        context.heap.record_call_exit();
-        context.heap.write_profile("starlark_profile.csv").unwrap();
-    }
+    context.profile_exit();
+    let profiled = context.write_profile();
     context.codemap = old_codemap;
     context.local_variables = old_locals;
 
-    // Return the result of evaluation
-    Ok(res?)
+    // Return the result of evaluation, or failing that, of writing the profile
+    let res = res?;
+    profiled?;
+    Ok(res)
 }
 
 pub fn eval_function<'v>(
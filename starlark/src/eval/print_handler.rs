@@ -0,0 +1,37 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Define where the output of the `print` function goes.
+
+use codemap::SpanLoc;
+
+/// A trait for handling the messages passed to `print`.
+pub trait PrintHandler {
+    /// Output a message, which doesn't end with a newline. The `location` is
+    /// that of the call to `print`, if known.
+    fn println(&self, message: &str, location: Option<SpanLoc>) -> anyhow::Result<()>;
+}
+
+/// Print handler which writes each message to stderr, the default.
+pub struct StderrPrintHandler;
+
+impl PrintHandler for StderrPrintHandler {
+    fn println(&self, message: &str, _location: Option<SpanLoc>) -> anyhow::Result<()> {
+        eprintln!("{}", message);
+        Ok(())
+    }
+}
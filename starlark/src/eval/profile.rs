@@ -0,0 +1,283 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Profiling where the time goes, by call stack and by statement.
+
+use crate::values::Value;
+use codemap::{CodeMap, Span};
+use gazebo::prelude::*;
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// What to profile, as passed to
+/// [`EvaluationContext::enable_profile`](crate::eval::EvaluationContext::enable_profile).
+#[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
+pub enum ProfileMode {
+    /// The allocations made by each function, written as CSV.
+    Heap,
+    /// The time spent in each function and statement, both directly and
+    /// including what it calls, written as CSV.
+    Time,
+    /// The time spent in each call stack, in microseconds, written as folded
+    /// stacks for `flamegraph.pl` or `inferno-flamegraph`. Statements are frames
+    /// too, so the graph shows which line of a function made each call.
+    Flame,
+}
+
+/// The call stack, interleaving each function with the statement it is
+/// running, and how long was spent with each stack.
+#[derive(Debug, Default)]
+pub(crate) struct TimeProfile {
+    // Function names and statement locations, interned
+    ids: HashMap<String, usize>,
+    names: Vec<String>,
+    statement: Vec<bool>,
+    // Calls of a function, or runs of a statement
+    counts: Vec<usize>,
+    // The ids of function values and statement spans we have seen before,
+    // keyed by pointer, so we only work out each name once.
+    // Only valid because profiling disables GC.
+    functions: HashMap<usize, usize>,
+    statements: HashMap<(usize, Span), usize>,
+    stack: Vec<usize>,
+    stacks: HashMap<Vec<usize>, Duration>,
+    last: Option<Instant>,
+}
+
+impl TimeProfile {
+    fn id(&mut self, name: String, statement: bool) -> usize {
+        let next = self.names.len();
+        match self.ids.entry(name) {
+            Entry::Occupied(x) => *x.get(),
+            Entry::Vacant(x) => {
+                self.names.push(x.key().clone());
+                self.statement.push(statement);
+                self.counts.push(0);
+                x.insert(next);
+                next
+            }
+        }
+    }
+
+    // Charge the time since the last change to the current stack
+    fn change(&mut self) {
+        let now = Instant::now();
+        if let Some(last) = self.last {
+            let time = now.checked_duration_since(last).unwrap_or_default();
+            match self.stacks.get_mut(self.stack.as_slice()) {
+                Some(x) => *x += time,
+                None => {
+                    self.stacks.insert(self.stack.clone(), time);
+                }
+            }
+        }
+    }
+
+    // Time spent on our own bookkeeping shouldn't be charged to anyone
+    fn push(&mut self, id: usize) {
+        self.counts[id] += 1;
+        self.stack.push(id);
+        self.last = Some(Instant::now());
+    }
+
+    fn pop_statement(&mut self) {
+        if let Some(top) = self.stack.last() {
+            if self.statement[*top] {
+                self.stack.pop();
+            }
+        }
+    }
+
+    pub(crate) fn enter_module(&mut self, name: &str) {
+        self.change();
+        let id = self.id(name.to_owned(), false);
+        self.push(id)
+    }
+
+    pub(crate) fn enter(&mut self, function: Value) {
+        self.change();
+        let id = match self.functions.get(&function.ptr_value()) {
+            Some(id) => *id,
+            None => {
+                let id = self.id(function.to_repr(), false);
+                self.functions.insert(function.ptr_value(), id);
+                id
+            }
+        };
+        self.push(id)
+    }
+
+    pub(crate) fn stmt(&mut self, span: Span, codemap: &Arc<CodeMap>) {
+        self.change();
+        let key = (Arc::as_ptr(codemap) as usize, span);
+        let id = match self.statements.get(&key) {
+            Some(id) => *id,
+            None => {
+                let loc = codemap.look_up_span(span);
+                let id = self.id(format!("{}:{}", loc.file.name(), loc.begin.line + 1), true);
+                self.statements.insert(key, id);
+                id
+            }
+        };
+        self.pop_statement();
+        self.push(id)
+    }
+
+    /// Leave a function or module, along with the statement it was running.
+    pub(crate) fn exit(&mut self) {
+        self.change();
+        self.pop_statement();
+        self.stack.pop();
+        self.last = Some(Instant::now());
+    }
+
+    // Stacks as lines of `a;b;c`, sorted so the output is deterministic
+    fn folded(&self) -> Vec<(String, Duration)> {
+        let mut res: Vec<_> = self
+            .stacks
+            .iter()
+            .map(|(stack, time)| {
+                let names = stack.map(|x| self.names[*x].replace(';', ","));
+                (names.join(";"), *time)
+            })
+            .collect();
+        res.sort();
+        res
+    }
+
+    fn write_flame(&self, file: &mut impl Write) -> io::Result<()> {
+        for (stack, time) in self.folded() {
+            let micros = time.as_micros();
+            if micros > 0 {
+                writeln!(file, "{} {}", stack, micros)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_time(&self, file: &mut impl Write) -> io::Result<()> {
+        let mut time = vec![Duration::default(); self.names.len()];
+        let mut time_rec = vec![Duration::default(); self.names.len()];
+        for (stack, t) in &self.stacks {
+            if let Some(top) = stack.last() {
+                time[*top] += *t;
+            }
+            // Recursive calls are on the stack more than once, but only count once
+            for x in stack.iter().collect::<HashSet<_>>() {
+                time_rec[*x] += *t;
+            }
+        }
+        let mut rows: Vec<usize> = (0..self.names.len()).collect();
+        rows.sort_by_key(|x| (-(time[*x].as_nanos() as i128), &self.names[*x]));
+
+        writeln!(file, "Name,Time(s),TimeRec(s),Calls")?;
+        for x in rows {
+            writeln!(
+                file,
+                "\"{}\",{:.6},{:.6},{}",
+                self.names[x].replace('"', "\"\""),
+                time[x].as_secs_f64(),
+                time_rec[x].as_secs_f64(),
+                self.counts[x]
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn write(&self, mode: ProfileMode, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        match mode {
+            ProfileMode::Flame => self.write_flame(&mut file),
+            _ => self.write_time(&mut file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        environment::Module,
+        eval::{eval_module, EvaluationContext, NoLoadFileLoader, ProfileMode},
+        stdlib::standard_environment,
+        syntax::{parse, Dialect},
+    };
+    use std::fs;
+
+    fn profile(mode: ProfileMode) -> String {
+        let path = std::env::temp_dir().join(format!("starlark_profile_test_{:?}", mode));
+        let module = parse(
+            "X",
+            "def f(x):\n    return len(x)\nf([1])\nf([2])\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let globals = standard_environment().build();
+        let env = Module::new("X");
+        let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
+        context.enable_profile(mode, &path);
+        eval_module(module, &mut context).unwrap();
+        let res = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        res
+    }
+
+    #[test]
+    fn test_profile_time() {
+        let res = profile(ProfileMode::Time);
+        let mut rows: Vec<_> = res.lines().collect();
+        assert_eq!(rows.remove(0), "Name,Time(s),TimeRec(s),Calls");
+        // The time depends on the machine, so just check the names and counts
+        let mut rows: Vec<_> = rows
+            .iter()
+            .map(|x| {
+                let cells: Vec<_> = x.rsplitn(4, ',').collect();
+                format!("{} {}", cells[3], cells[0])
+            })
+            .collect();
+        rows.sort();
+        // The functions are `f` and `len`, named by their signatures
+        assert_eq!(rows.len(), 7);
+        assert_eq!(
+            &rows[..5],
+            &[
+                "\"X\" 1",
+                "\"X:1\" 1",
+                "\"X:2\" 2",
+                "\"X:3\" 1",
+                "\"X:4\" 1",
+            ]
+        );
+        assert_eq!(rows[5], "\"f(x)\" 2");
+        assert!(rows[6].starts_with("\"len(") && rows[6].ends_with(" 2"));
+    }
+
+    #[test]
+    fn test_profile_flame() {
+        let res = profile(ProfileMode::Flame);
+        for x in res.lines() {
+            let (stack, micros) = x.rsplit_once(' ').unwrap();
+            assert!(stack == "X" || stack.starts_with("X;X:"), "{}", x);
+            assert!(micros.parse::<u128>().unwrap() > 0);
+        }
+    }
+}
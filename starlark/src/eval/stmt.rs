@@ -129,7 +129,7 @@ impl Compiler<'_> {
                 let e = self.expr(*e);
                 let s = s.node;
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     let e: Value = e(context)?;
                     let v = thrw(e.get_attr(&s, context.heap), span, context)?;
                     let rhs = rhs(context)?;
@@ -149,7 +149,7 @@ impl Compiler<'_> {
                 let e = self.expr(e);
                 let idx = self.expr(idx);
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     let e: Value = e(context)?;
                     let idx = idx(context)?;
                     let v = thrw(e.at(idx, context.heap), span, context)?;
@@ -170,7 +170,7 @@ impl Compiler<'_> {
                 let name = ident.node;
                 match self.scope.get_name_or_panic(&name) {
                     Slot::Local(slot) => box move |context| {
-                        before_stmt(span, context);
+                        before_stmt(span, context)?;
                         let v = thrw(context.get_slot_local(slot, &name), span, context)?;
                         let rhs = rhs(context)?;
                         let v = thrw(op(v, rhs, context), span_op, context)?;
@@ -178,7 +178,7 @@ impl Compiler<'_> {
                         Ok(Value::new_none())
                     },
                     Slot::Module(slot) => box move |context| {
-                        before_stmt(span, context);
+                        before_stmt(span, context)?;
                         let v = thrw(context.get_slot_module(slot, &name), span, context)?;
                         let rhs = rhs(context)?;
                         let v = thrw(op(v, rhs, context), span_op, context)?;
@@ -188,7 +188,7 @@ impl Compiler<'_> {
                 }
             }
             _ => box move |context| {
-                before_stmt(span, context);
+                before_stmt(span, context)?;
                 thrw(Err(AssignError::IncorrectLeftValue.into()), span, context)
             },
         }
@@ -196,7 +196,7 @@ impl Compiler<'_> {
 }
 
 // This function should be called before every meaningful statement.
-// The purposes are GC, profiling and debugging.
+// The purposes are GC, profiling, debugging and limiting evaluation.
 //
 // There are two requirements to perform a GC:
 //
@@ -224,10 +224,15 @@ impl Compiler<'_> {
 //
 // For the moment we only GC when executing a statement at the root of the
 // module, which we know is safe with respect to all three conditions.
-fn before_stmt(span: Span, context: &mut EvaluationContext) {
+fn before_stmt<'v>(
+    span: Span,
+    context: &mut EvaluationContext<'v, '_>,
+) -> Result<(), EvalException<'v>> {
+    thrw(context.step(), span, context)?;
     if let Some(f) = context.on_stmt {
         f(span, context)
     }
+    context.profile_stmt(span);
 
     // We only actually GC if there have been GC_THRESHOLD bytes allocated since the
     // last time we GC'd
@@ -245,6 +250,7 @@ fn before_stmt(span: Span, context: &mut EvaluationContext) {
         }
         context.last_heap_size = context.heap.allocated_bytes();
     }
+    Ok(())
 }
 
 impl Stmt {
@@ -299,7 +305,7 @@ impl Compiler<'_> {
                     node: Expr::Identifier(name),
                 });
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     lhs(rhs(context)?, context)?;
                     Ok(Value::new_none())
                 }
@@ -310,10 +316,11 @@ impl Compiler<'_> {
                 let over = self.expr(over);
                 let st = self.stmt(body);
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     let iterable = over(context)?;
                     let freeze_for_iteration = iterable.get_aref();
                     for v in &thrw(iterable.iterate(context.heap), over_span, context)? {
+                        thrw(context.step(), over_span, context)?;
                         var(v, context)?;
                         match st(context) {
                             Err(EvalException::Break) => break,
@@ -326,23 +333,42 @@ impl Compiler<'_> {
                     Ok(Value::new_none())
                 }
             }
+            Stmt::While(cond, box body) => {
+                let cond_span = cond.span;
+                let cond = self.expr(cond);
+                let st = self.stmt(body);
+                box move |context| {
+                    before_stmt(span, context)?;
+                    while cond(context)?.to_bool() {
+                        thrw(context.step(), cond_span, context)?;
+                        match st(context) {
+                            Err(EvalException::Break) => break,
+                            Err(EvalException::Continue) => {}
+                            Err(e) => return Err(e),
+                            _ => {}
+                        }
+                    }
+                    Ok(Value::new_none())
+                }
+            }
             Stmt::Return(Some(e)) => {
                 let e = self.expr(e);
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     Err(EvalException::Return(e(context)?))
                 }
             }
             Stmt::Return(None) => box move |context| {
-                before_stmt(span, context);
+                before_stmt(span, context)?;
                 Err(EvalException::Return(Value::new_none()))
             },
             Stmt::If(cond, box then_block) => {
                 let cond = self.expr(cond);
                 let then_block = self.stmt(then_block);
                 box move |context| {
-                    before_stmt(span, context);
-                    if cond(context)?.to_bool() {
+                    before_stmt(span, context)?;
+                    let taken = cond(context)?.to_bool();
+                    if context.branch(span, taken) {
                         then_block(context)
                     } else {
                         Ok(Value::new_none())
@@ -354,8 +380,9 @@ impl Compiler<'_> {
                 let then_block = self.stmt(then_block);
                 let else_block = self.stmt(else_block);
                 box move |context| {
-                    before_stmt(span, context);
-                    if cond(context)?.to_bool() {
+                    before_stmt(span, context)?;
+                    let taken = cond(context)?.to_bool();
+                    if context.branch(span, taken) {
                         then_block(context)
                     } else {
                         else_block(context)
@@ -382,7 +409,7 @@ impl Compiler<'_> {
             Stmt::Expression(e) => {
                 let e = self.expr(e);
                 box move |context| {
-                    before_stmt(span, context);
+                    before_stmt(span, context)?;
                     e(context)
                 }
             }
@@ -392,7 +419,7 @@ impl Compiler<'_> {
                     AssignOp::Assign => {
                         let lhs = self.assign(*lhs);
                         box move |context| {
-                            before_stmt(span, context);
+                            before_stmt(span, context)?;
                             lhs(rhs(context)?, context)?;
                             Ok(Value::new_none())
                         }
@@ -406,6 +433,9 @@ impl Compiler<'_> {
                     AssignOp::Multiplier => {
                         self.assign_modify(span, *lhs, rhs, |l, r, context| l.mul(r, context.heap))
                     }
+                    AssignOp::Divider => {
+                        self.assign_modify(span, *lhs, rhs, |l, r, context| l.div(r, context.heap))
+                    }
                     AssignOp::FloorDivider => {
                         self.assign_modify(span, *lhs, rhs, |l, r, context| {
                             l.floor_div(r, context.heap)
@@ -413,6 +443,19 @@ impl Compiler<'_> {
                     }
                     AssignOp::Percent => self
                         .assign_modify(span, *lhs, rhs, |l, r, context| l.percent(r, context.heap)),
+                    AssignOp::BitOr => {
+                        self.assign_modify(span, *lhs, rhs, |l, r, context| l.pipe(r, context.heap))
+                    }
+                    AssignOp::BitAnd => self
+                        .assign_modify(span, *lhs, rhs, |l, r, context| l.bit_and(r, context.heap)),
+                    AssignOp::BitXor => self
+                        .assign_modify(span, *lhs, rhs, |l, r, context| l.bit_xor(r, context.heap)),
+                    AssignOp::LeftShift => self.assign_modify(span, *lhs, rhs, |l, r, context| {
+                        l.left_shift(r, context.heap)
+                    }),
+                    AssignOp::RightShift => self.assign_modify(span, *lhs, rhs, |l, r, context| {
+                        l.right_shift(r, context.heap)
+                    }),
                 }
             }
             Stmt::Load(name, v, _) => {
@@ -425,8 +468,10 @@ impl Compiler<'_> {
                     )
                 });
                 box move |context| {
-                    before_stmt(span, context);
-                    let loadenv = context.loader.load(&name).map_err(EvalException::Error)?;
+                    before_stmt(span, context)?;
+                    let loader = context.loader;
+                    let loadenv = loader.load_in(&name, context);
+                    let loadenv = thrw(loadenv, span, context)?;
                     let modu = context.assert_module_env();
                     for (new_name, orig_name, span) in &symbols {
                         let value = thrw(modu.load_symbol(loadenv, orig_name), *span, context)?;
@@ -439,15 +484,15 @@ impl Compiler<'_> {
                 }
             }
             Stmt::Pass => box move |context| {
-                before_stmt(span, context);
+                before_stmt(span, context)?;
                 Ok(Value::new_none())
             },
             Stmt::Break => box move |context| {
-                before_stmt(span, context);
+                before_stmt(span, context)?;
                 Err(EvalException::Break)
             },
             Stmt::Continue => box move |context| {
-                before_stmt(span, context);
+                before_stmt(span, context)?;
                 Err(EvalException::Continue)
             },
         }
@@ -19,13 +19,14 @@ use crate::{
     self as starlark,
     assert::{self, Assert},
     environment::{GlobalsBuilder, Module},
-    errors::eprint_error,
-    eval::{eval_module, EvaluationContext, NoLoadFileLoader},
+    errors::{eprint_error, Diagnostic},
+    eval::{eval_module, EvaluationContext, InterruptError, NoLoadFileLoader},
+    stdlib::{extended_environment, standard_environment},
     syntax::{parse, Dialect},
     values::{
         any::StarlarkAny,
         none::{NoneType, NONE},
-        Heap, Value,
+        Heap, HeapError, HeapLimit, Value,
     },
 };
 use gazebo::any::AnyLifetime;
@@ -35,9 +36,11 @@ use std::{
     collections::HashMap,
     mem,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, Mutex,
     },
+    thread,
+    time::Duration,
 };
 
 #[test]
@@ -209,6 +212,71 @@ def foo(x):
     assert::fail("for x in []:\n  return 1", "outside of a `def`");
 }
 
+#[test]
+fn test_while() {
+    assert::is_true(
+        "
+def f(n):
+    res = []
+    while n > 0:
+        n -= 1
+        if n == 5:
+            continue
+        if n == 2:
+            break
+        res.append(n)
+    return res
+f(8) == [7, 6, 4, 3]",
+    );
+    assert::is_true(
+        "
+x = 0
+while True:
+    x += 1
+    if x == 10:
+        break
+x == 10",
+    );
+    assert::fail("while True:\n  return 1", "outside of a `def`");
+    let mut a = Assert::new();
+    a.dialect(&Dialect::Standard);
+    a.fail(
+        "def f():\n  while True:\n    pass",
+        "`while` is not allowed",
+    );
+}
+
+#[test]
+fn test_fstrings() {
+    assert::is_true(
+        r#"
+x = 1
+y = "a"
+f"{x} and {y!r}, {x + 1:.2f} {{}}" == '1 and "a", 2.00 {}'
+"#,
+    );
+    assert::all_true(
+        r#"
+f"" == ""
+f'{ {"k": [1, 2]}["k"] }' == "[1, 2]"
+f"{1 != 2}" == "True"
+rf"\n{1}" == "\\n1"
+f"\x41{'b'}\n" == "Ab\n"
+"#,
+    );
+    assert::fail("f'{undefined}'", "Variable `undefined` not found");
+    assert::fail("f'{\"a\":d}'", "'d' requires an int");
+    assert::fail("f'{1!x}'", "conversion must be");
+    // A bad format is reported at the expression it applies to
+    let e = Assert::new().fail("x = 1\nf'{x} {\"a\":d}'", "'d' requires an int");
+    let diagnostic = e.downcast_ref::<Diagnostic>().unwrap();
+    let (span, codemap) = diagnostic.span.as_ref().unwrap();
+    assert_eq!(codemap.find_file(span.low()).source_slice(*span), "\"a\"");
+    let mut a = Assert::new();
+    a.dialect(&Dialect::Standard);
+    a.fail("f'{1}'", "f-strings are not allowed");
+}
+
 #[test]
 fn test_tabs_fail() {
     let mut a = Assert::new();
@@ -715,6 +783,38 @@ v == '{}' or v == '{}'"#,
     ))
 }
 
+#[test]
+fn test_docstrings() {
+    #[starlark_module]
+    fn module(builder: &mut GlobalsBuilder) {
+        /// The answer.
+        const answer: i32 = 42;
+
+        /// Add one.
+        ///
+        /// ```
+        /// # starlark::assert::eq(r#"
+        /// inc(1) == 2
+        /// # "#);
+        /// ```
+        fn inc(x: i32) -> i32 {
+            Ok(x + 1)
+        }
+
+        fn dec(x: i32) -> i32 {
+            Ok(x - 1)
+        }
+    }
+
+    let globals = GlobalsBuilder::new().with(module).build();
+    assert_eq!(globals.docstring("answer"), Some("The answer."));
+    assert_eq!(
+        globals.docstring("inc"),
+        Some("Add one.\n\n```\ninc(1) == 2\n```")
+    );
+    assert_eq!(globals.docstring("dec"), None);
+}
+
 #[test]
 // Test that we can express something that loads symbols into the exported module
 fn test_load_symbols() {
@@ -1246,25 +1346,15 @@ fn test_go() {
         ],
     );
     // Skip benchmark.star, for benchmarking not testing
-    assert.conformance(&ignore_bad_lines(
-        test_case!("bool.star"),
-        &[
-            "0.0", // Floats, unsupported
-        ],
-    ));
+    assert.conformance(test_case!("bool.star"));
     assert.conformance(&ignore_bad_lines(
         test_case!("builtin.star"),
         &[
-            "2.0",                   // Floats, unsupported
-            "<<",                    // Bit shift, unsupported
             "[] not in {123: \"\"}", // We disagree, see test_not_in_unhashable
-            // Exponents, unsupported
-            "1e15",
-            "1e100",
-            // Set, unsupported
-            "set(",
-            "(myset)",
-            "(myset,",
+            // We have more set methods than Go
+            "dir(myset)",
+            // We render function names differently
+            "str(getattr(myset",
             // Has fields, unsupported
             "hasfields()",
             "(hf)",
@@ -1304,7 +1394,7 @@ fn test_go() {
             "Verify position of an \"unhashable key\"", // FIXME: we should do better
         ],
     );
-    // Skip float.star, since we don't support floats
+    assert.conformance(test_case!("float.star"));
     assert.conformance(&ignore_bad_lines(
         test_case!("function.star"),
         &[
@@ -1314,13 +1404,24 @@ fn test_go() {
             "hf",                 // We don't support hasfield
         ],
     ));
-    // Skip int.star, a lot of bit mask stuff, floats and int's outside our range
+    assert.conformance(&ignore_bad_lines(
+        test_case!("int.star"),
+        &[
+            "assert.fails(int,", // We follow Python, int() is 0
+            // Without a base we detect it from the prefix, and allow leading zeros
+            "invalid literal with base 10",
+            "invalid literal.*base 10",
+            "invalid literal.*base 0",
+            "1 << 512",              // We allow shifts of up to 512 bits
+            "(123.1, 123.1, 123.1)", // We follow Python, %o and %x require an int
+            "\"%d\" % True",         // We follow Python, True formats as 1
+        ],
+    ));
     // Skip list.star, our strings disagree about whether they are lists of codepoints or lists of 1-char strings
     assert.conformance_except(
         &ignore_bad_lines(
             test_case!("misc.star"),
             &[
-                "2.0",                          // We don't support float
                 "'<built-in function freeze>'", // Different display of functions
             ],
         ),
@@ -1330,8 +1431,9 @@ fn test_go() {
     );
     // Skip module.star, we don't support modules
     // Skip paths.star, a path support library, not tests
-    // Skip recursion.star, we don't support `while` loops, which is what this mostly tests
-    // Skip set.star, we don't support set
+    // Needs `while` and recursion, which Dialect::Extended enables
+    assert.conformance(test_case!("recursion.star"));
+    assert.conformance(test_case!("set.star"));
     // Skip string.star, our String's are fundamentally different
     assert.conformance(
         // Our elems() works differently
@@ -1344,3 +1446,239 @@ fn test_go() {
         ),
     );
 }
+
+// Evaluate some code, with a limit set on the context
+fn eval_limited(code: &str, limit: impl FnOnce(&mut EvaluationContext)) -> anyhow::Result<()> {
+    let globals = extended_environment().build();
+    let env = Module::new("X");
+    let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
+    limit(&mut context);
+    eval_module(
+        parse("X", code.to_owned(), &Dialect::Extended)?,
+        &mut context,
+    )?;
+    Ok(())
+}
+
+fn interrupted(e: &anyhow::Error) -> Option<&InterruptError> {
+    e.downcast_ref::<Diagnostic>()?.message.downcast_ref()
+}
+
+#[test]
+fn test_step_budget() {
+    // One step for the statement, and one for each iteration
+    eval_limited("x = [i for i in range(10)]", |c| c.set_step_budget(11)).unwrap();
+    let e = eval_limited("x = [i for i in range(10)]", |c| c.set_step_budget(10)).unwrap_err();
+    assert!(matches!(
+        interrupted(&e),
+        Some(InterruptError::StepBudgetExceeded(10))
+    ));
+
+    let e = eval_limited(
+        "def f():\n    return [x for x in range(1000000000)]\nf()\n",
+        |c| c.set_step_budget(1000),
+    )
+    .unwrap_err();
+    let diagnostic = e.downcast_ref::<Diagnostic>().unwrap();
+    assert!(diagnostic.span.is_some());
+    assert_eq!(diagnostic.call_stack.len(), 1);
+}
+
+#[test]
+fn test_cancellation() {
+    let token = Arc::new(AtomicBool::new(false));
+    let cancel = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        cancel.store(true, Ordering::Relaxed);
+    });
+    let e = eval_limited("for x in range(1000000000):\n    pass\n", |c| {
+        c.set_cancellation(token)
+    })
+    .unwrap_err();
+    assert!(matches!(interrupted(&e), Some(InterruptError::Cancelled)));
+}
+
+fn heap_limit(bytes: Option<usize>, objects: Option<usize>) -> impl FnOnce(&mut EvaluationContext) {
+    move |c| c.heap().set_limit(HeapLimit { bytes, objects })
+}
+
+fn heap_error(e: &anyhow::Error) -> Option<&HeapError> {
+    e.downcast_ref::<Diagnostic>()?.message.downcast_ref()
+}
+
+#[test]
+fn test_heap_limit() {
+    const MB: Option<usize> = Some(1_000_000);
+    eval_limited("x = [0] * 1000", heap_limit(MB, None)).unwrap();
+    // Repetition fails before building the result
+    for code in &["[0] * 1000000000", "(0,) * 1000000000", "'a' * 1000000000"] {
+        let e = eval_limited(code, heap_limit(MB, None)).unwrap_err();
+        assert!(matches!(
+            heap_error(&e),
+            Some(HeapError::BytesExceeded(1_000_000))
+        ));
+    }
+    // Growth without allocating anything on the heap still counts
+    for code in &[
+        "x = []\nfor i in range(1000000000):\n    x.append(i)\n",
+        "x = [i for i in range(1000000000)]",
+    ] {
+        let e = eval_limited(code, heap_limit(MB, None)).unwrap_err();
+        assert!(matches!(
+            heap_error(&e),
+            Some(HeapError::BytesExceeded(1_000_000))
+        ));
+    }
+
+    eval_limited(
+        "x = [str(i) for i in range(100)]",
+        heap_limit(None, Some(1000)),
+    )
+    .unwrap();
+    let e = eval_limited(
+        "x = [str(i) for i in range(10000)]",
+        heap_limit(None, Some(1000)),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        heap_error(&e),
+        Some(HeapError::ObjectsExceeded(1000))
+    ));
+}
+
+#[test]
+fn test_heap_limit_growth() {
+    const MB: Option<usize> = Some(1_000_000);
+    let loop_body = |body: &str| {
+        format!(
+            "def f():\n    x = {{}}\n    for i in range(1000000000):\n        {}\nf()",
+            body
+        )
+    };
+    let mut codes: Vec<String> = [
+        "list(range(1000000000))",
+        "tuple(range(1000000000))",
+        "sorted(range(1000000000))",
+        "reversed(range(1000000000))",
+        "enumerate(range(1000000000))",
+        "set(range(1000000000))",
+        "[].extend(range(1000000000))",
+    ]
+    .iter()
+    .map(|x| (*x).to_owned())
+    .collect();
+    codes.push(loop_body("x[i] = i"));
+    codes.push(loop_body("x.update([(i, i)])"));
+    codes.push(loop_body("x.setdefault(i, i)"));
+    codes.push(loop_body("x = set() if i == 0 else x\n        x.add(i)"));
+    for code in &codes {
+        let e = eval_limited(code, heap_limit(MB, None)).unwrap_err();
+        assert!(
+            matches!(heap_error(&e), Some(HeapError::BytesExceeded(1_000_000))),
+            "{}: {}",
+            code,
+            e
+        );
+    }
+}
+
+#[test]
+fn test_heap_usage() {
+    let globals = standard_environment().build();
+    let env = Module::new("X");
+    let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
+    let before = context.heap().usage();
+    eval_module(
+        parse("X", "x = 'a' * 10000".to_owned(), &Dialect::Extended).unwrap(),
+        &mut context,
+    )
+    .unwrap();
+    let after = context.heap().usage();
+    assert!(after.bytes >= before.bytes + 10000);
+    assert!(after.objects > before.objects);
+    assert_eq!(context.heap().peak_usage(), after);
+}
+
+fn message(e: &anyhow::Error) -> String {
+    e.downcast_ref::<Diagnostic>().unwrap().message.to_string()
+}
+
+#[test]
+fn test_max_call_depth() {
+    let code = "def f(n):\n    return 0 if n == 0 else f(n - 1)\nf(30)";
+    eval_limited(code, |_| {}).unwrap();
+    let e = eval_limited(code, |c| c.set_max_call_depth(10)).unwrap_err();
+    assert_eq!(
+        message(&e),
+        "Too many recursion levels, call stack depth limit of 10 exceeded, in the cycle `f(n)` -> `f(n)`"
+    );
+
+    let code = "def a():\n    b()\ndef b():\n    c()\ndef c():\n    pass\na()";
+    eval_limited(code, |c| c.set_max_call_depth(3)).unwrap();
+    let e = eval_limited(code, |c| c.set_max_call_depth(2)).unwrap_err();
+    assert_eq!(
+        message(&e),
+        "Too many recursion levels, call stack depth limit of 2 exceeded, the deepest calls being `a()` -> `b()`"
+    );
+}
+
+#[test]
+fn test_max_compare_depth() {
+    let code = r#"
+def nest():
+    x = [1]
+    for _ in range(50):
+        x = [x]
+    return x
+nest() == nest()
+"#;
+    eval_limited(code, |_| {}).unwrap();
+    let e = eval_limited(code, |c| c.set_max_compare_depth(20)).unwrap_err();
+    assert_eq!(message(&e), "Too many recursion levels");
+}
+
+#[test]
+fn test_repr_depth() {
+    let code = r#"
+def cycle():
+    x = []
+    x.append(x)
+    return x
+def cycle_dict():
+    x = {}
+    x["x"] = x
+    return x
+def nest():
+    x = [1]
+    for _ in range(5):
+        x = [x]
+    return x
+"#;
+    eval_limited(
+        &format!("{}repr(cycle())\nrepr(cycle_dict())", code),
+        |_| {},
+    )
+    .unwrap();
+    let check =
+        "for x in [cycle(), nest()]:\n    if repr(x) != '[[[...]]]':\n        fail(repr(x))";
+    eval_limited(&format!("{}{}", code, check), |c| {
+        c.set_max_compare_depth(3)
+    })
+    .unwrap();
+}
+
+#[test]
+fn test_recursion_dialect() {
+    let mut a = Assert::new();
+    a.dialect_set(|d| d.enable_recursion = false);
+    a.pass("def f(x):\n    return x\nf(f(1))");
+    a.fail(
+        "def f(n):\n    return 0 if n == 0 else f(n - 1)\nf(1)",
+        "`f(n)` called recursively",
+    );
+    a.fail(
+        "def f(n):\n    return g(n)\ndef g(n):\n    return 0 if n == 0 else f(n - 1)\nf(1)",
+        "`f(n)` called recursively",
+    );
+}
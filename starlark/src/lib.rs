@@ -28,10 +28,11 @@
 //! https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md)
 //! and the Python 3 documentation when things were unclear.
 //!
-//! This interpreter does not support most of the go extensions (e.g. bitwise
-//! operator or floating point). It does not include the `set()` type either
-//! (the Java implementation use a custom type, `depset`, instead). It uses
-//! signed 64-bit integer.
+//! This interpreter supports floating point numbers, bitwise operators, `bytes`
+//! values and the `set()` type from the go extensions (the Java implementation
+//! use a custom type, `depset`, instead). Integers are arbitrary-precision,
+//! stored as `i32` when they fit. Python-style f-strings (`f"{x}"`) are
+//! available when [`Dialect::enable_fstrings`](syntax::Dialect) is set.
 //!
 //! # Usage
 //!
@@ -72,8 +73,10 @@
 //!
 //! The [values](values) module provide the following types:
 //!
-//! * integer (signed 64bit), bool, and NoneType,
+//! * integer (arbitrary-precision, see [bigint](values::bigint)), bool, and NoneType,
+//! * [float](values::float),
 //! * [string](values::string),
+//! * [bytes](values::bytes),
 //! * [dictionary](values::dict),
 //! * [list](values::list),
 //! * [tuple](values::tuple), and
@@ -0,0 +1,77 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Methods for the `bytes` type.
+
+use crate as starlark;
+use crate::{environment::GlobalsBuilder, values::bytes::StarlarkBytes};
+use gazebo::{cell::ARef, prelude::*};
+use std::fmt::Write;
+
+#[starlark_module]
+pub(crate) fn bytes_members(builder: &mut GlobalsBuilder) {
+    /// `B.decode()` returns the string obtained by decoding the bytes B
+    /// as UTF-8. Invalid UTF-8 sequences are replaced by the replacement
+    /// character `U+FFFD`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// b"hello".decode() == "hello"
+    /// b"\xe4\xb8\x96".decode() == "世"
+    /// b"a\xffb".decode() == "a�b"
+    /// # "#);
+    /// ```
+    fn decode(this: ARef<StarlarkBytes>) -> String {
+        Ok(this.decode())
+    }
+
+    /// `B.elems()` returns the sequence of byte values in B, as a list
+    /// of `int` values.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// b"abc".elems() == [97, 98, 99]
+    /// b"".elems() == []
+    /// # "#);
+    /// ```
+    fn elems(this: ARef<StarlarkBytes>) -> Vec<i32> {
+        Ok(this.0.map(|x| *x as i32))
+    }
+
+    /// `B.hex()` returns a string with two lowercase hexadecimal digits
+    /// for each byte in B.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// b"\x00\xffA".hex() == "00ff41"
+    /// b"".hex() == ""
+    /// # "#);
+    /// ```
+    fn hex(this: ARef<StarlarkBytes>) -> String {
+        let mut res = String::with_capacity(this.0.len() * 2);
+        for b in this.0.iter() {
+            write!(res, "{:02x}", b).unwrap();
+        }
+        Ok(res)
+    }
+}
@@ -276,6 +276,7 @@ pub(crate) fn dict_members(registry: &mut GlobalsBuilder) {
         if let Some(r) = this.content.get_hashed(key.borrow()) {
             return Ok(*r);
         }
+        heap.add_extra_bytes(mem::size_of::<(Value, Value)>());
         this.content.insert_hashed(key, default);
         Ok(default)
     }
@@ -36,12 +36,14 @@ pub fn global(builder: &mut GlobalsBuilder) {
         for v in &seq.iterate(heap)? {
             if func.is_none() {
                 if !v.is_none() {
+                    heap.check_reserve_items::<Value>(res.len() + 1)?;
                     res.push(v);
                 }
             } else {
                 let mut inv = func.new_invoker(heap)?;
                 inv.push_pos(v);
                 if inv.invoke(func, None, ctx)?.to_bool() {
+                    heap.check_reserve_items::<Value>(res.len() + 1)?;
                     res.push(v);
                 }
             }
@@ -54,6 +56,7 @@ pub fn global(builder: &mut GlobalsBuilder) {
         for v in &seq.iterate(heap)? {
             let mut inv = func.new_invoker(heap)?;
             inv.push_pos(v);
+            heap.check_reserve_items::<Value>(res.len() + 1)?;
             res.push(inv.invoke(func, None, ctx)?);
         }
         Ok(res)
@@ -88,6 +91,7 @@ pub fn global(builder: &mut GlobalsBuilder) {
             let p = v.ptr_value();
             if !seen.contains(&p) {
                 seen.insert(p);
+                heap.check_reserve_items::<Value>(res.len() + 1)?;
                 res.push(v);
             }
         }
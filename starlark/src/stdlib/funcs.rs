@@ -23,17 +23,22 @@ use crate::{
     collections::SmallMap,
     environment::GlobalsBuilder,
     values::{
+        bigint::StarlarkBigInt,
+        bytes::StarlarkBytes,
         dict::Dict,
+        float::StarlarkFloat,
         function::{NativeAttribute, WrappedMethod},
-        none::NoneType,
+        none::{NoneType, NONE},
         range::Range,
         tuple::Tuple,
-        Heap, Value, ValueLike,
+        Heap, Value, ValueError, ValueLike,
     },
 };
 use anyhow::anyhow;
 use gazebo::prelude::*;
-use std::{cmp::Ordering, num::NonZeroI32};
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Num, ToPrimitive};
+use std::{cmp::Ordering, num::NonZeroI64};
 
 fn unpack_pair<'v>(it: Value<'v>, heap: &'v Heap) -> anyhow::Result<(Value<'v>, Value<'v>)> {
     match it.iterate(heap) {
@@ -55,6 +60,13 @@ fn unpack_pair<'v>(it: Value<'v>, heap: &'v Heap) -> anyhow::Result<(Value<'v>,
     ))
 }
 
+/// Unpack an argument of `range`, which may be an `int` outside of the `i32` range.
+fn unpack_range_bound(x: Value) -> anyhow::Result<i64> {
+    StarlarkBigInt::to_bigint(x)?
+        .to_i64()
+        .ok_or_else(|| ValueError::IntegerOverflow.into())
+}
+
 #[starlark_module]
 pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     const None: NoneType = NoneType::None;
@@ -155,6 +167,51 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         Ok(x.to_bool())
     }
 
+    /// [bytes](
+    /// https://github.com/google/starlark-go/blob/master/doc/spec.md#bytes
+    /// ): constructs a `bytes` value.
+    ///
+    /// `bytes(x)` returns the UTF-8 encoding of `x` if it is a string,
+    /// `x` itself if it is a `bytes` value, and otherwise treats `x` as an
+    /// iterable of `int` values in the range 0-255.
+    ///
+    /// With no argument, `bytes()` returns an empty `bytes` value.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// bytes() == b""
+    /// bytes("héllo") == b"h\xc3\xa9llo"
+    /// bytes([104, 105]) == b"hi"
+    /// bytes(b"hi") == b"hi"
+    /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// bytes([256])   # error: not a valid byte
+    /// # "#, "not a valid byte");
+    /// ```
+    fn bytes(ref a: Option<Value>) -> Value<'v> {
+        let a = match a {
+            None => return Ok(heap.alloc(StarlarkBytes(Vec::new()))),
+            Some(a) => a,
+        };
+        if StarlarkBytes::from_value(a).is_some() {
+            Ok(a)
+        } else if let Some(s) = a.unpack_str() {
+            Ok(heap.alloc(StarlarkBytes(s.as_bytes().to_vec())))
+        } else {
+            let mut res = Vec::new();
+            for x in &a.iterate(heap)? {
+                heap.check_reserve_items::<u8>(res.len() + 1)?;
+                match x.unpack_int() {
+                    Some(i) if (0..256).contains(&i) => res.push(i as u8),
+                    _ => return Err(anyhow!("{} is not a valid byte", x.to_repr())),
+                }
+            }
+            Ok(heap.alloc(StarlarkBytes(res)))
+        }
+    }
+
     /// [chr](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#bool
     /// ): returns a string encoding a codepoint.
@@ -228,6 +285,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                     }
                     None => {
                         for el in &a.iterate(heap)? {
+                            heap.check_reserve_items::<(Value, Value)>(result.len() + 1)?;
                             let (k, v) = unpack_pair(el, heap)?;
                             result.insert_hashed(k.get_hashed()?, v);
                         }
@@ -293,9 +351,76 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             .iterate(heap)?
             .iter()
             .enumerate()
-            .map(|(k, v)| (k as i32 + offset, v))
-            .collect();
-        Ok(v)
+            .map(|(k, v)| (k as i32 + offset, v));
+        heap.collect_limited(v)
+    }
+
+    /// [float](
+    /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#float
+    /// ): interprets its argument as a floating-point number.
+    ///
+    /// If x is a `float`, the result is x.
+    /// If x is an `int`, the result is the nearest floating point value to x.
+    /// If x is a `bool`, the result is 1.0 for `True` and 0.0 for `False`.
+    ///
+    /// If x is a string, the string is interpreted as a floating-point literal.
+    /// The strings `"inf"`, `"-inf"` and `"nan"` (in any case) are accepted.
+    ///
+    /// It is an error if x is an `int` or a string too large to be represented
+    /// as a finite float.
+    ///
+    /// With no arguments, `float()` returns `0.0`.
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// float() == 0.0
+    /// float(1) == 1.0
+    /// float(1.5) == 1.5
+    /// float(True) == 1.0
+    /// float("1.5") == 1.5
+    /// float("-1e3") == -1000.0
+    /// float("Inf") > 1e300
+    /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// float("hello")   # error: not a valid float
+    /// # "#, "not a valid float");
+    /// # starlark::assert::fail(r#"
+    /// float(1 << 512 << 512)   # error: too large
+    /// # "#, "too large");
+    /// ```
+    fn float(ref a: Option<Value>) -> f64 {
+        let a = match a {
+            None => return Ok(0.0),
+            Some(a) => a,
+        };
+        if let Some(f) = StarlarkFloat::unpack_number(a) {
+            if f.is_infinite() && a.downcast_ref::<StarlarkFloat>().is_none() {
+                Err(anyhow!("int too large to convert to float"))
+            } else {
+                Ok(f)
+            }
+        } else if let Some(b) = a.unpack_bool() {
+            Ok(if b { 1.0 } else { 0.0 })
+        } else if let Some(s) = a.unpack_str() {
+            let lower = s.trim().to_lowercase();
+            match lower.as_str() {
+                "inf" | "+inf" | "infinity" | "+infinity" => Ok(f64::INFINITY),
+                "-inf" | "-infinity" => Ok(f64::NEG_INFINITY),
+                "nan" | "+nan" | "-nan" => Ok(f64::NAN),
+                _ => match lower.parse::<f64>() {
+                    Ok(f) if f.is_infinite() => {
+                        Err(anyhow!("{} is too large to convert to float", a.to_repr()))
+                    }
+                    Ok(f) => Ok(f),
+                    Err(_) => Err(anyhow!("{} is not a valid float", a.to_repr())),
+                },
+            }
+        } else {
+            Err(anyhow!(
+                "float() argument must be a string or a number, not '{}'",
+                a.get_type()
+            ))
+        }
     }
 
     /// [getattr](
@@ -403,14 +528,18 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// int('16', 10) == 16
     /// int('16', 8) == 14
     /// int('16', 16) == 22
+    /// int(2.7) == 2
+    /// int(-2.7) == -2
+    /// int('4294967296') == 4294967296
+    /// int('-100000000', 16) == -4294967296
     /// # "#);
     /// # starlark::assert::fail(r#"
     /// int("hello")   # error: not a valid number
     /// # "#, "not a valid number");
     /// ```
-    fn int(ref a: Option<Value>, base: Option<Value>) -> i32 {
+    fn int(ref a: Option<Value>, base: Option<Value>) -> Value<'v> {
         if a.is_none() {
-            return Ok(0);
+            return Ok(Value::new_int(0));
         }
         let a = a.unwrap();
         if let Some(s) = a.unpack_str() {
@@ -465,8 +594,8 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                 }
                 _ => s,
             };
-            match i32::from_str_radix(s, base) {
-                Ok(i) => Ok(sign * i),
+            match BigInt::from_str_radix(s, base) {
+                Ok(i) => Ok(heap.alloc(if sign < 0 { -i } else { i })),
                 Err(x) => Err(anyhow!(
                     "{} is not a valid number in base {}: {}",
                     a.to_repr(),
@@ -480,7 +609,14 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
                     "int() cannot convert non-string with explicit base '{}'",
                     base.to_repr()
                 )),
-                None => Ok(a.to_int()?),
+                None if StarlarkBigInt::is_int(a) => Ok(a),
+                None => match a.downcast_ref::<StarlarkFloat>() {
+                    Some(f) => match BigInt::from_f64(f.0.trunc()) {
+                        Some(i) => Ok(heap.alloc(i)),
+                        None => Err(anyhow!("cannot convert float {} to integer", a.to_repr())),
+                    },
+                    None => Ok(Value::new_int(a.to_int()?)),
+                },
             }
         }
     }
@@ -529,13 +665,10 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// # "#, "not supported");
     /// ```
     fn list(ref a: Option<Value>) -> Vec<Value<'v>> {
-        let mut l = Vec::new();
-        if let Some(a) = a {
-            for x in &a.iterate(heap)? {
-                l.push(x)
-            }
+        match a {
+            None => Ok(Vec::new()),
+            Some(a) => heap.collect_limited(&a.iterate(heap)?),
         }
-        Ok(l)
     }
 
     /// [max](
@@ -692,6 +825,29 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         ))
     }
 
+    /// [print](
+    /// https://github.com/bazelbuild/starlark/blob/master/spec.md#print
+    /// ): print a message.
+    ///
+    /// `print(*args, sep=" ")` converts each argument to a string, as `str`
+    /// does, joins them with `sep`, and passes the result to the print handler
+    /// of the evaluation, which writes it to stderr unless told otherwise.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// print("hello", 42) == None
+    /// print("a", "b", sep=", ") == None
+    /// # "#);
+    /// ```
+    fn print(args: Vec<Value>, sep @ " ": &str) -> NoneType {
+        let message = args.map(|x| x.to_str()).join(sep);
+        ctx.print_handler
+            .println(&message, ctx.call_stack().top_location())?;
+        Ok(NONE)
+    }
+
     /// [range](
     /// https://github.com/google/skylark/blob/a0e5de7e63b47e716cca7226662a4c95d47bf873/doc/spec.md#range
     /// ): return a range of integers
@@ -722,15 +878,20 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// list(range(3, 10))                      == [3, 4, 5, 6, 7, 8, 9]
     /// list(range(3, 10, 2))                   == [3, 5, 7, 9]
     /// list(range(10, 3, -2))                  == [10, 8, 6, 4]
+    /// list(range(2**40, 2**40 + 2))           == [2**40, 2**40 + 1]
     /// # "#);
     /// ```
-    fn range(ref a1: i32, ref a2: Option<i32>, ref step @ 1: i32) -> Range {
-        let start = match a2 {
-            None => 0,
-            Some(_) => a1,
+    fn range(ref a1: Value, ref a2: Option<Value>, ref step: Option<Value>) -> Range {
+        let a1 = unpack_range_bound(a1)?;
+        let (start, stop) = match a2 {
+            None => (0, a1),
+            Some(a2) => (a1, unpack_range_bound(a2)?),
+        };
+        let step = match step {
+            None => 1,
+            Some(step) => unpack_range_bound(step)?,
         };
-        let stop = a2.unwrap_or(a1);
-        let step = match NonZeroI32::new(step) {
+        let step = match NonZeroI64::new(step) {
             Some(step) => step,
             None => return Err(anyhow!("Third argument of range (step) cannot be zero")),
         };
@@ -772,7 +933,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// # "#);
     /// ```
     fn reversed(ref a: Value) -> Vec<Value<'v>> {
-        let mut v: Vec<Value> = a.iterate(heap)?.iter().collect();
+        let mut v = heap.collect_limited(&a.iterate(heap)?)?;
         v.reverse();
         Ok(v)
     }
@@ -803,10 +964,11 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
         let it = x.iterate(heap)?;
         let x = it.iter();
         let mut it = match key {
-            None => x.map(|x| (x, x)).collect(),
+            None => heap.collect_limited(x.map(|x| (x, x)))?,
             Some(key) => {
                 let mut v = Vec::new();
                 for el in x {
+                    heap.check_reserve_items::<(Value, Value)>(v.len() + 1)?;
                     let mut inv = key.new_invoker(heap)?;
                     inv.push_pos(el);
                     v.push((el, inv.invoke(key, None, ctx)?));
@@ -876,13 +1038,10 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
     /// tuple([1,2,3]) == (1, 2, 3)
     /// # "#);
     fn tuple(ref a: Option<Value>) -> Tuple<'v> {
-        let mut l = Vec::new();
-        if let Some(a) = a {
-            for x in &a.iterate(heap)? {
-                l.push(x)
-            }
+        match a {
+            None => Ok(Tuple::new(Vec::new())),
+            Some(a) => Ok(Tuple::new(heap.collect_limited(&a.iterate(heap)?)?)),
         }
-        Ok(Tuple::new(l))
     }
 
     /// [type](
@@ -927,6 +1086,7 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
             let mut idx = 0;
             for e in &arg.iterate(heap)? {
                 if first {
+                    heap.check_reserve_items::<Value>(v.len() + 1)?;
                     v.push(heap.alloc((e,)));
                     idx += 1;
                 } else if idx < v.len() {
@@ -943,7 +1103,15 @@ pub(crate) fn global_functions(builder: &mut GlobalsBuilder) {
 
 #[cfg(test)]
 mod tests {
-    use crate::assert;
+    use crate::{
+        assert,
+        environment::Module,
+        eval::{eval_module, EvaluationContext, NoLoadFileLoader, PrintHandler},
+        stdlib::standard_environment,
+        syntax::{parse, Dialect},
+    };
+    use codemap::SpanLoc;
+    use std::cell::RefCell;
 
     #[test]
     fn test_constants() {
@@ -956,4 +1124,35 @@ mod tests {
     fn test_error_codes() {
         assert::fail("chr(0x110000)", "not a valid UTF-8");
     }
+
+    #[test]
+    fn test_print() {
+        struct Capture(RefCell<Vec<String>>);
+
+        impl PrintHandler for Capture {
+            fn println(&self, message: &str, location: Option<SpanLoc>) -> anyhow::Result<()> {
+                self.0
+                    .borrow_mut()
+                    .push(format!("{} {}", location.unwrap(), message));
+                Ok(())
+            }
+        }
+
+        let module = parse(
+            "X",
+            "def f():\n    print('a', 1, sep='-')\nprint('b', [])\nf()\n".to_owned(),
+            &Dialect::Extended,
+        )
+        .unwrap();
+        let globals = standard_environment().build();
+        let env = Module::new("X");
+        let capture = Capture(RefCell::new(Vec::new()));
+        let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
+        context.print_handler = &capture;
+        eval_module(module, &mut context).unwrap();
+        assert_eq!(
+            *capture.0.borrow(),
+            &["X:3:1: 3:15 b []", "X:2:5: 2:27 a-1"]
+        );
+    }
 }
@@ -31,6 +31,7 @@ use crate::{
     },
 };
 use anyhow::anyhow;
+use std::mem;
 
 #[starlark_module]
 pub(crate) fn list_members(builder: &mut GlobalsBuilder) {
@@ -55,6 +56,7 @@ pub(crate) fn list_members(builder: &mut GlobalsBuilder) {
     /// ```
     fn append(this: Value, ref el: Value) -> NoneType {
         let mut this = List::from_value_mut(this, heap)?.unwrap();
+        heap.add_extra_bytes(mem::size_of::<Value>());
         this.push(el);
         Ok(NONE)
     }
@@ -118,8 +120,9 @@ pub(crate) fn list_members(builder: &mut GlobalsBuilder) {
     /// # "#);
     /// ```
     fn extend(this: Value, ref other: Value) -> NoneType {
-        let other: Vec<Value<'v>> = other.iterate(heap)?.iter().collect();
+        let other = heap.collect_limited(&other.iterate(heap)?)?;
         let mut this = List::from_value_mut(this, heap)?.unwrap();
+        heap.add_extra_bytes(other.len() * mem::size_of::<Value>());
         this.extend(other);
         Ok(NONE)
     }
@@ -198,6 +201,7 @@ pub(crate) fn list_members(builder: &mut GlobalsBuilder) {
     fn insert(this: Value, ref index: i32, ref el: Value) -> NoneType {
         let mut this = List::from_value_mut(this, heap)?.unwrap();
         let index = convert_index(this.len() as i32, index);
+        heap.add_extra_bytes(mem::size_of::<Value>());
         this.insert(index, el);
         Ok(NONE)
     }
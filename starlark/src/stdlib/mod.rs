@@ -22,6 +22,7 @@ use crate::environment::GlobalsBuilder;
 pub use macros::*;
 
 mod breakpoint;
+pub(crate) mod bytes;
 pub(crate) mod dict;
 pub(crate) mod enumeration;
 mod extra;
@@ -29,6 +30,7 @@ mod funcs;
 pub(crate) mod list;
 mod macros;
 pub(crate) mod record;
+pub(crate) mod set;
 pub(crate) mod string;
 pub(crate) mod structs;
 mod typing;
@@ -43,12 +45,13 @@ pub fn standard_environment() -> GlobalsBuilder {
     GlobalsBuilder::new().with(funcs::global_functions)
 }
 
-/// Default global environment with extensions, namely `add_struct`, `add_extra_functions`
+/// Default global environment with extensions, namely `add_struct`, `add_set`, `add_extra_functions`
 pub fn extended_environment() -> GlobalsBuilder {
     standard_environment()
         .with(add_struct)
         .with(add_record)
         .with(add_enum)
+        .with(add_set)
         .with(add_extra_functions)
 }
 
@@ -65,6 +68,11 @@ pub fn add_enum(builder: &mut GlobalsBuilder) {
     enumeration::global(builder)
 }
 
+/// Add the `set` function and type to the global environment.
+pub fn add_set(builder: &mut GlobalsBuilder) {
+    set::global(builder)
+}
+
 /// Add functions like `filter` and `partial` to the global environment.
 pub fn add_extra_functions(builder: &mut GlobalsBuilder) {
     extra::global(builder)
@@ -0,0 +1,185 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `set` function and methods for the `set` type.
+
+use crate as starlark;
+use crate::{
+    environment::GlobalsBuilder,
+    values::{
+        none::{NoneType, NONE},
+        set::{RefSet, Set},
+        Value, ValueError,
+    },
+};
+use std::mem;
+
+#[starlark_module]
+pub fn global(builder: &mut GlobalsBuilder) {
+    /// Creates a set, which preserves the insertion order of its elements.
+    ///
+    /// `set(x)` returns a new set containing the elements of the iterable `x`.
+    /// With no argument, `set()` returns a new empty set.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set() == set([])
+    /// list(set([3, 1, 3, 2])) == [3, 1, 2]
+    /// len(set("abc".elems())) == 3
+    /// # "#);
+    /// ```
+    fn set(ref a: Option<Value>) -> Set<'v> {
+        match a {
+            None => Ok(Set::default()),
+            Some(a) => Set::from_iterable(a, heap),
+        }
+    }
+}
+
+#[starlark_module]
+pub(crate) fn set_members(builder: &mut GlobalsBuilder) {
+    /// `S.add(x)` adds `x` to the set S, and returns `None`.
+    /// Adding an element which is already present does nothing.
+    ///
+    /// `add` fails if the set is frozen or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1])
+    /// x.add(2)
+    /// x.add(1)
+    /// x == set([1, 2])
+    /// # "#);
+    /// ```
+    fn add(this: Value, ref el: Value) -> NoneType {
+        let mut this = Set::from_value_mut(this, heap)?.unwrap();
+        if this.add(el)? {
+            heap.add_extra_bytes(mem::size_of::<Value>());
+        }
+        Ok(NONE)
+    }
+
+    /// `S.remove(x)` removes `x` from the set S, and returns `None`.
+    ///
+    /// `remove` fails if `x` is not in the set, or if the set is frozen
+    /// or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.remove(2)
+    /// x == set([1])
+    /// # "#);
+    /// # starlark::assert::fail(r#"
+    /// set([1]).remove(2) # error: not found
+    /// # "#, "not found");
+    /// ```
+    fn remove(this: Value, ref el: Value) -> NoneType {
+        let mut this = Set::from_value_mut(this, heap)?.unwrap();
+        if this.discard(el)? {
+            Ok(NONE)
+        } else {
+            Err(ValueError::KeyNotFound(el.to_repr()).into())
+        }
+    }
+
+    /// `S.discard(x)` removes `x` from the set S if present, and returns `None`.
+    ///
+    /// `discard` fails if the set is frozen or has active iterators.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// x = set([1, 2])
+    /// x.discard(2)
+    /// x.discard(3)
+    /// x == set([1])
+    /// # "#);
+    /// ```
+    fn discard(this: Value, ref el: Value) -> NoneType {
+        let mut this = Set::from_value_mut(this, heap)?.unwrap();
+        this.discard(el)?;
+        Ok(NONE)
+    }
+
+    /// `S.union(x)` returns a new set with the elements of S followed by
+    /// the elements of the iterable `x`. It is equivalent to `S | set(x)`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).union([2, 3]) == set([1, 2, 3])
+    /// # "#);
+    /// ```
+    fn union(this: RefSet, ref other: Value) -> Set<'v> {
+        Ok(this.0.union(&Set::from_iterable(other, heap)?.content))
+    }
+
+    /// `S.intersection(x)` returns a new set with the elements of S which
+    /// are also in the iterable `x`. It is equivalent to `S & set(x)`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).intersection([2, 3]) == set([2])
+    /// # "#);
+    /// ```
+    fn intersection(this: RefSet, ref other: Value) -> Set<'v> {
+        Ok(this
+            .0
+            .intersection(&Set::from_iterable(other, heap)?.content))
+    }
+
+    /// `S.difference(x)` returns a new set with the elements of S which
+    /// are not in the iterable `x`. It is equivalent to `S - set(x)`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::is_true(r#"
+    /// set([1, 2]).difference([2, 3]) == set([1])
+    /// # "#);
+    /// ```
+    fn difference(this: RefSet, ref other: Value) -> Set<'v> {
+        Ok(this.0.difference(&Set::from_iterable(other, heap)?.content))
+    }
+
+    /// `S.issubset(x)` returns `True` if every element of S is also in
+    /// the iterable `x`.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// set([1, 2]).issubset([1, 2, 3])
+    /// not set([1, 4]).issubset([1, 2, 3])
+    /// set().issubset([])
+    /// # "#);
+    /// ```
+    fn issubset(this: RefSet, ref other: Value) -> bool {
+        Ok(this.0.is_subset(&Set::from_iterable(other, heap)?.content))
+    }
+}
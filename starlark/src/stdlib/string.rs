@@ -25,7 +25,12 @@ use crate::{
         macros::{NoneOr, UnpackValue},
         util::convert_indices,
     },
-    values::{Heap, TypedValue, Value, ValueError},
+    values::{
+        bigint::StarlarkBigInt,
+        bytes::StarlarkBytes,
+        float::{write_compact, write_decimal, write_scientific, StarlarkFloat},
+        Heap, TypedValue, Value, ValueError,
+    },
 };
 use anyhow::anyhow;
 use gazebo::prelude::*;
@@ -39,6 +44,10 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
     args: &[Value],
     kwargs: &SmallMap<&str, Value>,
 ) -> anyhow::Result<String> {
+    let (capture, spec) = match capture.find(':') {
+        Some(x) => (capture.get(..x).unwrap(), capture.get(x + 1..).unwrap()),
+        None => (capture, ""),
+    };
     let (n, conv) = {
         if let Some(x) = capture.find('!') {
             (capture.get(1..x).unwrap(), capture.get(x + 1..).unwrap())
@@ -61,7 +70,7 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             ));
         }
     };
-    if n.is_empty() {
+    let value = if n.is_empty() {
         if *captured_by_index {
             return Err(anyhow!(
                 "Cannot mix manual field specification and automatic field numbering in format string",
@@ -69,7 +78,7 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
         } else {
             *captured_by_order = true;
             if let Some(x) = it.next() {
-                return Ok(conv(x));
+                x
             } else {
                 return Err(anyhow!("Not enough parameters in format string"));
             }
@@ -85,7 +94,7 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             if i < 0 || i >= (args.len() as i32) {
                 return Err(ValueError::IndexOutOfBound(i).into());
             }
-            Ok(conv(args[i as usize]))
+            args[i as usize]
         }
     } else {
         if let Some(x) = n.chars().find(|c| match c {
@@ -98,9 +107,105 @@ fn format_capture<'v, T: Iterator<Item = Value<'v>>>(
             ));
         }
         match kwargs.get(n) {
-            None => Err(ValueError::KeyNotFound(n.to_owned().into_boxed_str().to_repr()).into()),
-            Some(v) => Ok(conv(*v)),
+            None => {
+                return Err(
+                    ValueError::KeyNotFound(n.to_owned().into_boxed_str().to_repr()).into(),
+                );
+            }
+            Some(v) => *v,
         }
+    };
+    format_spec(spec, value, conv)
+}
+
+/// Apply a format specifier of the form `[.precision][type]`,
+/// where `type` is one of `s`, `d`, `f`, `e`, `E`, `g` or `G`.
+fn format_spec(spec: &str, x: Value, conv: &dyn Fn(Value) -> String) -> anyhow::Result<String> {
+    let (precision, typ) = match spec.strip_prefix('.') {
+        None => (None, spec),
+        Some(rest) => {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            match rest[..digits].parse::<usize>() {
+                Ok(precision) => (Some(precision), &rest[digits..]),
+                Err(_) => return Err(anyhow!("Invalid format specifier '{}'", spec)),
+            }
+        }
+    };
+    let number = || {
+        StarlarkFloat::unpack_number(x).ok_or_else(|| {
+            anyhow!(
+                "Format specifier '{}' requires a number, got '{}'",
+                spec,
+                x.get_type()
+            )
+        })
+    };
+    let float_precision = precision.unwrap_or(6);
+    let mut res = String::new();
+    match typ {
+        "" if precision.is_none() => return Ok(conv(x)),
+        "s" if precision.is_none() => return Ok(conv(x)),
+        "" => write_compact(&mut res, number()?, float_precision, false),
+        "d" if precision.is_none() => match StarlarkBigInt::unpack_bigint(x) {
+            Some(i) => res = i.to_string(),
+            None => return Err(anyhow!("Format specifier 'd' requires an int")),
+        },
+        "f" => write_decimal(&mut res, number()?, float_precision, false),
+        "e" => write_scientific(&mut res, number()?, float_precision, false),
+        "E" => write_scientific(&mut res, number()?, float_precision, true),
+        "g" => write_compact(&mut res, number()?, float_precision, false),
+        "G" => write_compact(&mut res, number()?, float_precision, true),
+        _ => return Err(anyhow!("Unsupported format specifier '{}'", spec)),
+    }
+    Ok(res)
+}
+
+/// The implementation of `str.format`, also used to evaluate f-strings.
+pub(crate) fn format_string(
+    this: &str,
+    args: &[Value],
+    kwargs: &SmallMap<&str, Value>,
+) -> anyhow::Result<String> {
+    let mut it = args.iter().copied();
+    let mut captured_by_index = false;
+    let mut captured_by_order = false;
+    let mut result = String::new();
+    let mut capture = String::new();
+    for c in this.chars() {
+        match (c, capture.as_str()) {
+            ('{', "") | ('}', "") => capture.push(c),
+            (.., "") => result.push(c),
+            ('{', "{") => {
+                result.push('{');
+                capture.clear();
+            }
+            ('{', "}") => return Err(anyhow!("Standalone '}}' in format string `{}`", this)),
+            ('{', ..) => return Err(anyhow!("Unmatched '{' in format string")),
+            ('}', "}") => {
+                result.push('}');
+                capture.clear();
+            }
+            ('}', ..) => {
+                result += &format_capture(
+                    &capture,
+                    &mut it,
+                    &mut captured_by_index,
+                    &mut captured_by_order,
+                    args,
+                    kwargs,
+                )?;
+                capture.clear();
+            }
+            (.., "}") => return Err(anyhow!("Standalone '}}' in format string `{}`", this)),
+            _ => capture.push(c),
+        }
+    }
+    match capture.as_str() {
+        "}" => Err(anyhow!("Standalone '}}' in format string `{}`", this)),
+        "" => Ok(result),
+        _ => Err(anyhow!("Unmatched '{' in format string")),
     }
 }
 
@@ -305,6 +410,20 @@ pub(crate) fn string_members(builder: &mut GlobalsBuilder) {
         Ok(counter)
     }
 
+    /// `S.encode()` returns the UTF-8 encoding of the string S as a `bytes` value.
+    ///
+    /// Examples:
+    ///
+    /// ```
+    /// # starlark::assert::all_true(r#"
+    /// "abc".encode() == b"abc"
+    /// "世".encode() == b"\xe4\xb8\x96"
+    /// # "#);
+    /// ```
+    fn encode(this: &str) -> StarlarkBytes {
+        Ok(StarlarkBytes(this.as_bytes().to_vec()))
+    }
+
     /// [string.endswith](
     /// https://github.com/google/skylark/blob/3705afa472e466b8b061cce44b47c9ddc6db696d/doc/spec.md#string·endswith
     /// ): determine if a string ends with a given suffix.
@@ -405,7 +524,9 @@ pub(crate) fn string_members(builder: &mut GlobalsBuilder) {
     ///
     /// The *format specifier*, after a colon, specifies field width,
     /// alignment, padding, and numeric precision.
-    /// Currently it must be empty, but it is reserved for future use.
+    /// Currently only a precision and a presentation type are supported,
+    /// as `[.precision][type]`, where `type` is one of `s`, `d`,
+    /// `f`, `e`, `E`, `g` or `G`.
     ///
     /// Examples:
     ///
@@ -416,48 +537,12 @@ pub(crate) fn string_members(builder: &mut GlobalsBuilder) {
     /// "a{}b{}c".format(1, 2) == "a1b2c"
     /// "({1}, {0})".format("zero", "one") == "(one, zero)"
     /// "Is {0!r} {0!s}?".format("heterological") == "Is \"heterological\" heterological?"
+    /// "{:.2f} {x:e} {:g}".format(0.5, 1e20, x=1.5) == "0.50 1.500000e+00 1e+20"
+    /// "{:d}".format(4294967296) == "4294967296"
     /// # "#);
     /// ```
     fn format(this: &str, args: Vec<Value>, kwargs: SmallMap<&str, Value>) -> String {
-        let mut it = args.iter().copied();
-        let mut captured_by_index = false;
-        let mut captured_by_order = false;
-        let mut result = String::new();
-        let mut capture = String::new();
-        for c in this.chars() {
-            match (c, capture.as_str()) {
-                ('{', "") | ('}', "") => capture.push(c),
-                (.., "") => result.push(c),
-                ('{', "{") => {
-                    result.push('{');
-                    capture.clear();
-                }
-                ('{', "}") => return Err(anyhow!("Standalone '}}' in format string `{}`", this)),
-                ('{', ..) => return Err(anyhow!("Unmatched '{' in format string")),
-                ('}', "}") => {
-                    result.push('}');
-                    capture.clear();
-                }
-                ('}', ..) => {
-                    result += &format_capture(
-                        &capture,
-                        &mut it,
-                        &mut captured_by_index,
-                        &mut captured_by_order,
-                        &args,
-                        &kwargs,
-                    )?;
-                    capture.clear();
-                }
-                (.., "}") => return Err(anyhow!("Standalone '}}' in format string `{}`", this)),
-                _ => capture.push(c),
-            }
-        }
-        match capture.as_str() {
-            "}" => Err(anyhow!("Standalone '}}' in format string `{}`", this)),
-            "" => Ok(result),
-            _ => Err(anyhow!("Unmatched '{' in format string")),
-        }
+        format_string(this, &args, &kwargs)
     }
 
     /// [string.index](
@@ -20,12 +20,15 @@ use crate::{
     environment::GlobalsBuilder,
     values::{
         bool::BOOL_VALUE_TYPE_NAME,
+        bytes::StarlarkBytes,
         dict::Dict,
+        float::StarlarkFloat,
         function::FUNCTION_VALUE_TYPE_NAME,
         int::INT_VALUE_TYPE_NAME,
         list::List,
         none::{NoneType, NONE},
         range::Range,
+        set::Set,
         string::STRING_VALUE_TYPE_NAME,
         structs::Struct,
         tuple::Tuple,
@@ -39,13 +42,16 @@ pub fn global(builder: &mut GlobalsBuilder) {
     const Void: &str = "void";
     const Bool: &str = BOOL_VALUE_TYPE_NAME;
     const Int: &str = INT_VALUE_TYPE_NAME;
+    const Float: &str = StarlarkFloat::TYPE;
     const String: &str = STRING_VALUE_TYPE_NAME;
+    const Bytes: &str = StarlarkBytes::TYPE;
     const Function: &str = FUNCTION_VALUE_TYPE_NAME;
     const List: &str = List::TYPE;
     const Tuple: &str = Tuple::TYPE;
     const Dict: &str = Dict::TYPE;
     const Struct: &str = Struct::TYPE;
     const Range: &str = Range::TYPE;
+    const Set: &str = Set::TYPE;
 
     #[allow(non_snake_case)]
     fn Union(args: Vec<Value>) -> Value<'v> {
@@ -17,6 +17,10 @@
 
 //! AST for parsed starlark files.
 
+use crate::{
+    syntax::lexer::TokenInt,
+    values::{bytes::collect_bytes_repr, float::StarlarkFloat, TypedValue},
+};
 use codemap::{CodeMap, Span, Spanned};
 use derivative::Derivative;
 use gazebo::prelude::*;
@@ -34,8 +38,11 @@ pub type AstArgument = Spanned<Argument>;
 pub type AstString = Spanned<String>;
 pub type AstParameter = Spanned<Parameter>;
 pub type AstClause = Spanned<Clause>;
-pub type AstInt = Spanned<i32>;
+pub type AstInt = Spanned<TokenInt>;
+pub type AstFloat = Spanned<f64>;
+pub type AstBytes = Spanned<Vec<u8>>;
 pub type AstStmt = Spanned<Stmt>;
+pub type AstTrivia = Spanned<Trivia>;
 
 // We don't care _that_ much about the size of these structures,
 // but we equally don't want to regress without noticing.
@@ -49,6 +56,27 @@ pub struct AstModule {
     #[derivative(Debug = "ignore")]
     pub(crate) codemap: Arc<CodeMap>,
     pub(crate) statement: AstStmt,
+    // Only recorded when parsed with `parse_with_trivia`
+    pub(crate) trivia: Option<Vec<AstTrivia>>,
+    // From the dialect, since it is only checked when functions are called
+    pub(crate) enable_recursion: bool,
+}
+
+impl AstModule {
+    /// The comments and blank lines in the module, in source order, if it
+    /// was parsed with [`parse_with_trivia`](crate::syntax::parse_with_trivia).
+    pub fn trivia(&self) -> Option<&[AstTrivia]> {
+        self.trivia.as_deref()
+    }
+}
+
+/// The parts of the source that don't affect evaluation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Trivia {
+    /// A comment, including the leading `#`, excluding trailing whitespace.
+    Comment(String),
+    /// A line containing only whitespace. The span covers that whitespace.
+    BlankLine,
 }
 
 // A trait rather than a function to allow .ast() chaining in the parser.
@@ -83,7 +111,9 @@ pub enum Parameter {
 #[derive(Debug, Clone)]
 pub enum AstLiteral {
     IntLiteral(AstInt),
+    FloatLiteral(AstFloat),
     StringLiteral(AstString),
+    BytesLiteral(AstBytes),
 }
 
 #[derive(Debug)]
@@ -104,12 +134,17 @@ pub enum Expr {
     Not(Box<AstExpr>),
     Minus(Box<AstExpr>),
     Plus(Box<AstExpr>),
+    BitNot(Box<AstExpr>),
     Op(Box<AstExpr>, BinOp, Box<AstExpr>),
     If(Box<(AstExpr, AstExpr, AstExpr)>), // Order: condition, v1, v2 <=> v1 if condition else v2
     List(Vec<AstExpr>),
     Dict(Vec<(AstExpr, AstExpr)>),
+    Set(Vec<AstExpr>),
     ListComprehension(Box<AstExpr>, Vec<AstClause>),
     DictComprehension(Box<(AstExpr, AstExpr)>, Vec<AstClause>),
+    SetComprehension(Box<AstExpr>, Vec<AstClause>),
+    /// An f-string, as a format string with an empty field for each expression
+    FString(AstString, Vec<AstExpr>),
 }
 
 #[derive(Debug)]
@@ -135,8 +170,13 @@ pub enum BinOp {
     Addition,
     Multiplication,
     Percent,
+    Division,
     FloorDivision,
     Pipe,
+    BitAnd,
+    BitXor,
+    LeftShift,
+    RightShift,
 }
 
 #[derive(Debug, Clone, Copy, Dupe, PartialEq, Eq)]
@@ -145,8 +185,14 @@ pub enum AssignOp {
     Increment,
     Decrement,
     Multiplier,
+    Divider,
     FloorDivider,
     Percent,
+    BitOr,
+    BitAnd,
+    BitXor,
+    LeftShift,
+    RightShift,
 }
 
 #[derive(Debug, Copy, Clone, Dupe)]
@@ -167,6 +213,7 @@ pub enum Stmt {
     If(AstExpr, Box<AstStmt>),
     IfElse(AstExpr, Box<(AstStmt, AstStmt)>),
     For(Box<(AstExpr, AstExpr, AstStmt)>),
+    While(AstExpr, Box<AstStmt>),
     Def(
         AstString,
         Vec<AstParameter>,
@@ -205,8 +252,13 @@ impl Display for BinOp {
             BinOp::Addition => f.write_str(" + "),
             BinOp::Multiplication => f.write_str(" * "),
             BinOp::Percent => f.write_str(" % "),
+            BinOp::Division => f.write_str(" / "),
             BinOp::FloorDivision => f.write_str(" // "),
             BinOp::Pipe => f.write_str(" | "),
+            BinOp::BitAnd => f.write_str(" & "),
+            BinOp::BitXor => f.write_str(" ^ "),
+            BinOp::LeftShift => f.write_str(" << "),
+            BinOp::RightShift => f.write_str(" >> "),
         }
     }
 }
@@ -216,10 +268,16 @@ impl Display for AssignOp {
         match *self {
             AssignOp::Assign => f.write_str(" = "),
             AssignOp::Increment => f.write_str(" += "),
-            AssignOp::Decrement => f.write_str(" += "),
+            AssignOp::Decrement => f.write_str(" -= "),
             AssignOp::Multiplier => f.write_str(" *= "),
+            AssignOp::Divider => f.write_str(" /= "),
             AssignOp::FloorDivider => f.write_str(" //= "),
             AssignOp::Percent => f.write_str(" %= "),
+            AssignOp::BitOr => f.write_str(" |= "),
+            AssignOp::BitAnd => f.write_str(" &= "),
+            AssignOp::BitXor => f.write_str(" ^= "),
+            AssignOp::LeftShift => f.write_str(" <<= "),
+            AssignOp::RightShift => f.write_str(" >>= "),
         }
     }
 }
@@ -243,17 +301,50 @@ where
     Ok(())
 }
 
+fn fmt_string_char(f: &mut Formatter<'_>, c: char) -> fmt::Result {
+    match c {
+        '\n' => f.write_str("\\n"),
+        '\t' => f.write_str("\\t"),
+        '\r' => f.write_str("\\r"),
+        '\0' => f.write_str("\\0"),
+        '"' => f.write_str("\\\""),
+        '\\' => f.write_str("\\\\"),
+        x => f.write_str(&x.to_string()),
+    }
+}
+
 fn fmt_string_literal(f: &mut Formatter<'_>, s: &str) -> fmt::Result {
     f.write_str("\"")?;
     for c in s.chars() {
+        fmt_string_char(f, c)?;
+    }
+    f.write_str("\"")
+}
+
+// Put each expression back into its field of the format string
+fn fmt_fstring(f: &mut Formatter<'_>, format: &str, xs: &[AstExpr]) -> fmt::Result {
+    f.write_str("f\"")?;
+    let mut xs = xs.iter();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
         match c {
-            '\n' => f.write_str("\\n")?,
-            '\t' => f.write_str("\\t")?,
-            '\r' => f.write_str("\\r")?,
-            '\0' => f.write_str("\\0")?,
-            '"' => f.write_str("\\\"")?,
-            '\\' => f.write_str("\\\\")?,
-            x => f.write_str(&x.to_string())?,
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                f.write_str("{{")?;
+            }
+            '{' => {
+                // A space stops an expression like `{1: 2}` reading as `{{`
+                let x = xs.next().unwrap().node.to_string();
+                let sep = if x.starts_with('{') { " " } else { "" };
+                write!(f, "{{{}{}", sep, x)?;
+                for c in &mut chars {
+                    fmt_string_char(f, c)?;
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            _ => fmt_string_char(f, c)?,
         }
     }
     f.write_str("\"")
@@ -263,7 +354,13 @@ impl Display for AstLiteral {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             AstLiteral::IntLiteral(i) => i.node.fmt(f),
+            AstLiteral::FloatLiteral(x) => f.write_str(&StarlarkFloat(x.node).to_repr()),
             AstLiteral::StringLiteral(s) => fmt_string_literal(f, &s.node),
+            AstLiteral::BytesLiteral(b) => {
+                let mut s = String::new();
+                collect_bytes_repr(&b.node, &mut s);
+                f.write_str(&s)
+            }
         }
     }
 }
@@ -314,6 +411,7 @@ impl Display for Expr {
             Expr::Not(e) => write!(f, "(not {})", e.node),
             Expr::Minus(e) => write!(f, "-{}", e.node),
             Expr::Plus(e) => write!(f, "+{}", e.node),
+            Expr::BitNot(e) => write!(f, "~{}", e.node),
             Expr::Op(l, op, r) => write!(f, "({}{}{})", l.node, op, r.node),
             Expr::If(box (cond, v1, v2)) => {
                 write!(f, "({} if {} else {})", v1.node, cond.node, v2.node)
@@ -328,6 +426,11 @@ impl Display for Expr {
                 comma_separated_fmt(f, v, |x, f| write!(f, "{}: {}", x.0.node, x.1.node), false)?;
                 f.write_str("}")
             }
+            Expr::Set(v) => {
+                f.write_str("{")?;
+                comma_separated_fmt(f, v, |x, f| x.node.fmt(f), false)?;
+                f.write_str("}")
+            }
             Expr::ListComprehension(e, v) => {
                 write!(f, "[{}", e.node)?;
                 comma_separated_fmt(f, v, |x, f| x.node.fmt(f), false)?;
@@ -338,7 +441,13 @@ impl Display for Expr {
                 comma_separated_fmt(f, c, |x, f| x.node.fmt(f), false)?;
                 f.write_str("}}")
             }
+            Expr::SetComprehension(e, v) => {
+                write!(f, "{{{}", e.node)?;
+                comma_separated_fmt(f, v, |x, f| x.node.fmt(f), false)?;
+                f.write_str("}")
+            }
             Expr::Literal(x) => x.fmt(f),
+            Expr::FString(s, xs) => fmt_fstring(f, &s.node, xs),
         }
     }
 }
@@ -414,6 +523,10 @@ impl Stmt {
                 writeln!(f, "{}for {} in {}:", tab, bind.node, coll.node)?;
                 suite.node.fmt_with_tab(f, tab + "  ")
             }
+            Stmt::While(cond, box suite) => {
+                writeln!(f, "{}while {}:", tab, cond.node)?;
+                suite.node.fmt_with_tab(f, tab + "  ")
+            }
             Stmt::Def(name, params, return_type, suite) => {
                 write!(f, "{}def {}(", tab, name.node)?;
                 comma_separated_fmt(f, params, |x, f| x.node.fmt(f), false)?;
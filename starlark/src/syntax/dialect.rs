@@ -33,6 +33,12 @@ enum DialectError {
     KeywordOnlyArguments,
     #[error("type annotations are not allowed in this dialect")]
     Types,
+    #[error("`while` is not allowed in this dialect")]
+    While,
+    #[error("set literals are not allowed in this dialect")]
+    SetLiterals,
+    #[error("f-strings are not allowed in this dialect")]
+    FStrings,
 }
 
 /// Starlark language dialect.
@@ -54,6 +60,17 @@ pub struct Dialect {
     pub enable_load_reexport: bool,
     /// Are `for`, `if` and other statements allowed at the top-level
     pub enable_top_level_stmt: bool,
+    /// Are `while` loops permitted
+    pub enable_while: bool,
+    /// Are set literals `{a, b}` and set comprehensions permitted
+    pub enable_set_literals: bool,
+    /// Are f-strings `f"{x}"` permitted
+    pub enable_fstrings: bool,
+    /// May a function be called while it is already running, directly or
+    /// through other functions. The spec forbids it, with an error when the
+    /// call is made, and this applies to functions defined in modules using
+    /// the dialect, wherever they are called from.
+    pub enable_recursion: bool,
 }
 
 // These are morally enumerations, so give them enumeration-like names
@@ -70,6 +87,10 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true, // But they plan to change it
         enable_top_level_stmt: false,
+        enable_while: false,
+        enable_set_literals: false,
+        enable_fstrings: false,
+        enable_recursion: false,
     };
 
     /// Starlark plus `lambda`, nested `def` and other features.
@@ -82,6 +103,10 @@ impl Dialect {
         enable_tabs: true,
         enable_load_reexport: true,
         enable_top_level_stmt: true,
+        enable_while: true,
+        enable_set_literals: true,
+        enable_fstrings: true,
+        enable_recursion: true,
     };
 }
 
@@ -126,6 +151,42 @@ impl Dialect {
         }
     }
 
+    pub(crate) fn check_while<T>(
+        &self,
+        codemap: &Arc<CodeMap>,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_while {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::While)
+        }
+    }
+
+    pub(crate) fn check_set_literal<T>(
+        &self,
+        codemap: &Arc<CodeMap>,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_set_literals {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::SetLiterals)
+        }
+    }
+
+    pub(crate) fn check_fstring<T>(
+        &self,
+        codemap: &Arc<CodeMap>,
+        x: Spanned<T>,
+    ) -> anyhow::Result<Spanned<T>> {
+        if self.enable_fstrings {
+            Ok(x)
+        } else {
+            err(codemap, x.span, DialectError::FStrings)
+        }
+    }
+
     pub(crate) fn check_keyword_only_arguments<T>(
         &self,
         codemap: &Arc<CodeMap>,
@@ -0,0 +1,752 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Print a parsed module back out in a canonical style, in the spirit of
+//! [buildifier](https://github.com/bazelbuild/buildtools).
+//!
+//! The AST doesn't record parentheses or the original spelling of literals,
+//! so we recover those from the source text using the spans. Comments come
+//! from the module trivia, lexing the source again if it wasn't recorded.
+
+use crate::syntax::ast::{
+    Argument, AstArgument, AstClause, AstExpr, AstModule, AstParameter, AstStmt, BinOp, Expr,
+    Parameter, Stmt, Trivia,
+};
+use codemap::{Pos, Span};
+
+const INDENT: &str = "    ";
+
+// Precedence of expressions, from loosest to tightest binding
+const PREC_LAMBDA: u8 = 0;
+const PREC_IF: u8 = 1;
+const PREC_OR: u8 = 2;
+const PREC_AND: u8 = 3;
+const PREC_NOT: u8 = 4;
+const PREC_COMPARE: u8 = 5;
+const PREC_PIPE: u8 = 6;
+const PREC_XOR: u8 = 7;
+const PREC_BIT_AND: u8 = 8;
+const PREC_SHIFT: u8 = 9;
+const PREC_ADD: u8 = 10;
+const PREC_MUL: u8 = 11;
+const PREC_UNARY: u8 = 12;
+const PREC_ATOM: u8 = 13;
+
+fn binop_precedence(op: BinOp) -> u8 {
+    match op {
+        BinOp::Or => PREC_OR,
+        BinOp::And => PREC_AND,
+        BinOp::EqualsTo
+        | BinOp::Different
+        | BinOp::LessThan
+        | BinOp::GreaterThan
+        | BinOp::LessOrEqual
+        | BinOp::GreaterOrEqual
+        | BinOp::In
+        | BinOp::NotIn => PREC_COMPARE,
+        BinOp::Pipe => PREC_PIPE,
+        BinOp::BitXor => PREC_XOR,
+        BinOp::BitAnd => PREC_BIT_AND,
+        BinOp::LeftShift | BinOp::RightShift => PREC_SHIFT,
+        BinOp::Addition | BinOp::Subtraction => PREC_ADD,
+        BinOp::Multiplication | BinOp::Division | BinOp::FloorDivision | BinOp::Percent => PREC_MUL,
+    }
+}
+
+fn precedence(x: &Expr) -> u8 {
+    match x {
+        Expr::Lambda(..) => PREC_LAMBDA,
+        Expr::If(..) => PREC_IF,
+        Expr::Op(_, op, _) => binop_precedence(*op),
+        Expr::Not(..) => PREC_NOT,
+        Expr::Minus(..) | Expr::Plus(..) | Expr::BitNot(..) => PREC_UNARY,
+        _ => PREC_ATOM,
+    }
+}
+
+/// Rewrite a single-quoted string literal (including bytes, raw and f-strings)
+/// to use double quotes. Returns `None` if the literal should be kept as is,
+/// either because it isn't single-quoted, or because it contains a `"`.
+fn double_quoted(x: &str) -> Option<String> {
+    let quote = x.find(&['\'', '"'][..])?;
+    let (prefix, rest) = x.split_at(quote);
+    if !rest.starts_with('\'') {
+        return None;
+    }
+    let quotes = if rest.starts_with("'''") {
+        "\"\"\""
+    } else {
+        "\""
+    };
+    let body = &rest[quotes.len()..rest.len() - quotes.len()];
+    let raw = prefix.contains('r');
+
+    let mut res = String::with_capacity(x.len());
+    res.push_str(prefix);
+    res.push_str(quotes);
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return None,
+            '\\' => match chars.next() {
+                Some('\'') if !raw => res.push('\''),
+                Some(c) => {
+                    res.push('\\');
+                    res.push(c);
+                }
+                None => res.push('\\'),
+            },
+            c => res.push(c),
+        }
+    }
+    res.push_str(quotes);
+    Some(res)
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    file_start: Pos,
+    line_starts: Vec<usize>,
+    /// The span of every comment, in order, as offsets into the source
+    comments: Vec<(usize, usize)>,
+    /// The first comment we haven't printed yet
+    next_comment: usize,
+    /// The source line of the last thing printed, so we can preserve blank lines
+    last_line: Option<usize>,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(module: &AstModule, source: &'a str, file_span: Span) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        Self {
+            source,
+            file_start: file_span.low(),
+            line_starts,
+            comments: Self::find_comments(module, file_span),
+            next_comment: 0,
+            last_line: None,
+            out: String::with_capacity(source.len()),
+        }
+    }
+
+    // The comments, as offsets into the source
+    fn find_comments(module: &AstModule, file_span: Span) -> Vec<(usize, usize)> {
+        module
+            .trivia_or_lex()
+            .iter()
+            .filter(|x| matches!(x.node, Trivia::Comment(_)))
+            .map(|x| {
+                (
+                    (x.span.low() - file_span.low()) as usize,
+                    (x.span.high() - file_span.low()) as usize,
+                )
+            })
+            .collect()
+    }
+
+    fn lo(&self, x: Span) -> usize {
+        (x.low() - self.file_start) as usize
+    }
+
+    fn hi(&self, x: Span) -> usize {
+        (x.high() - self.file_start) as usize
+    }
+
+    fn line(&self, pos: usize) -> usize {
+        match self.line_starts.binary_search(&pos) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        }
+    }
+
+    fn column(&self, pos: usize) -> usize {
+        pos - self.line_starts[self.line(pos)]
+    }
+
+    fn byte(&self, pos: usize) -> Option<u8> {
+        self.source.as_bytes().get(pos).copied()
+    }
+
+    fn comment(&self, i: usize) -> &'a str {
+        let (lo, hi) = self.comments[i];
+        self.source[lo..hi].trim_end()
+    }
+
+    fn peek_comment(&self) -> Option<usize> {
+        self.comments.get(self.next_comment).map(|x| x.0)
+    }
+
+    fn has_comments(&self, lo: usize, hi: usize) -> bool {
+        self.comments.iter().any(|x| x.0 >= lo && x.0 < hi)
+    }
+
+    /// The position of the next token at or after `pos`.
+    fn skip_trivia(&self, mut pos: usize) -> usize {
+        loop {
+            match self.byte(pos) {
+                Some(b'#') => {
+                    while self.byte(pos).map_or(false, |c| c != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() || c == b'\\' => pos += 1,
+                _ => return pos,
+            }
+        }
+    }
+
+    /// The position just after the previous token before `pos`.
+    fn skip_trivia_back(&self, mut pos: usize) -> usize {
+        loop {
+            while pos > 0 && self.byte(pos - 1).unwrap().is_ascii_whitespace() {
+                pos -= 1;
+            }
+            let i = match self.comments.binary_search_by_key(&pos, |x| x.0) {
+                Ok(i) => i,
+                Err(i) => i,
+            };
+            match i.checked_sub(1).map(|i| self.comments[i]) {
+                Some((lo, hi)) if pos <= hi && pos > lo => pos = lo,
+                _ => return pos,
+            }
+        }
+    }
+
+    /// The position of the closing bracket of a list whose last element ends at `pos`.
+    fn close_pos(&self, pos: usize) -> usize {
+        let pos = self.skip_trivia(pos);
+        if self.byte(pos) == Some(b',') {
+            self.skip_trivia(pos + 1)
+        } else {
+            pos
+        }
+    }
+
+    fn indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // Keep (at most) one blank line, if there was one in the source before `line`
+    fn blank_lines(&mut self, line: usize) {
+        if let Some(last) = self.last_line {
+            if line > last + 1 {
+                self.out.push('\n');
+            }
+        }
+        self.last_line = Some(line);
+    }
+
+    fn own_line_comment(&mut self, indent: usize) {
+        let line = self.line(self.comments[self.next_comment].0);
+        self.blank_lines(line);
+        self.indent(indent);
+        self.out.push_str(self.comment(self.next_comment));
+        self.out.push('\n');
+        self.next_comment += 1;
+    }
+
+    /// Print all the comments before `pos` on their own lines.
+    fn comments_before(&mut self, pos: usize, indent: usize) {
+        while self.peek_comment().map_or(false, |x| x < pos) {
+            self.own_line_comment(indent);
+        }
+    }
+
+    /// Finish a line whose source ended at `pos`, keeping a comment that followed
+    /// it on the same line. Comments we couldn't place inside the line (e.g. in the
+    /// middle of a long expression) are put after it.
+    fn end_line(&mut self, pos: usize, indent: usize) {
+        let line = self.line(pos);
+        let mut stuck = Vec::new();
+        while let Some(lo) = self.peek_comment() {
+            if lo < pos {
+                stuck.push(self.next_comment);
+            } else if self.line(lo) == line {
+                self.out.push_str("  ");
+                self.out.push_str(self.comment(self.next_comment));
+                self.next_comment += 1;
+                break;
+            } else {
+                break;
+            }
+            self.next_comment += 1;
+        }
+        self.out.push('\n');
+        for i in stuck {
+            self.indent(indent);
+            self.out.push_str(self.comment(i));
+            self.out.push('\n');
+        }
+        self.last_line = Some(line);
+    }
+
+    fn is_multiline(&self, open: usize, close: usize, len: usize) -> bool {
+        self.line(open) != self.line(close) && (len != 0 || self.has_comments(open, close))
+    }
+
+    /// Print a bracketed sequence, either on one line, or with one item per line
+    /// and a trailing comma if it was originally written over several lines.
+    /// `open` and `close` are the positions of the brackets in the source.
+    fn seq<T>(
+        &mut self,
+        brackets: (&str, &str),
+        (open, close): (usize, usize),
+        items: &[T],
+        indent: usize,
+        span: impl Fn(&T) -> Span,
+        mut item: impl FnMut(&mut Self, &T, usize),
+    ) {
+        self.out.push_str(brackets.0);
+        if !self.is_multiline(open, close, items.len()) {
+            for (i, x) in items.iter().enumerate() {
+                if i != 0 {
+                    self.out.push_str(", ");
+                }
+                item(self, x, indent);
+            }
+        } else {
+            self.out.push('\n');
+            // We don't keep blank lines between items, since they might have been sorted
+            for x in items {
+                let (lo, hi) = (self.lo(span(x)), self.hi(span(x)));
+                self.last_line = None;
+                self.comments_before(lo, indent + 1);
+                self.indent(indent + 1);
+                item(self, x, indent + 1);
+                self.out.push(',');
+                self.end_line(hi, indent + 1);
+            }
+            self.last_line = None;
+            self.comments_before(close, indent + 1);
+            self.indent(indent);
+        }
+        self.out.push_str(brackets.1);
+    }
+
+    fn stmt(&mut self, x: &AstStmt, indent: usize) {
+        if let Stmt::Statements(xs) = &x.node {
+            for x in xs {
+                self.stmt(x, indent);
+            }
+            return;
+        }
+        let lo = self.lo(x.span);
+        self.comments_before(lo, indent);
+        self.blank_lines(self.line(lo));
+        self.indent(indent);
+        match &x.node {
+            Stmt::Statements(_) => unreachable!(),
+            Stmt::Break => self.out.push_str("break"),
+            Stmt::Continue => self.out.push_str("continue"),
+            Stmt::Pass => self.out.push_str("pass"),
+            Stmt::Return(e) => {
+                self.out.push_str("return");
+                if let Some(e) = e {
+                    self.out.push(' ');
+                    self.expr(e, PREC_LAMBDA, indent);
+                }
+            }
+            Stmt::Expression(e) => self.expr(e, PREC_LAMBDA, indent),
+            Stmt::Assign(l, op, r) => {
+                self.expr(l, PREC_LAMBDA, indent);
+                self.out.push_str(&op.to_string());
+                self.expr(r, PREC_LAMBDA, indent);
+            }
+            Stmt::If(cond, box then) => return self.if_stmt("if ", cond, then, None, indent),
+            Stmt::IfElse(cond, box (then, els)) => {
+                return self.if_stmt("if ", cond, then, Some(els), indent);
+            }
+            Stmt::For(box (var, over, body)) => {
+                self.out.push_str("for ");
+                self.expr(var, PREC_PIPE, indent);
+                self.out.push_str(" in ");
+                self.expr(over, PREC_LAMBDA, indent);
+                self.out.push(':');
+                self.end_line(self.hi(over.span), indent + 1);
+                return self.suite(body, indent + 1);
+            }
+            Stmt::While(cond, box body) => {
+                self.out.push_str("while ");
+                self.expr(cond, PREC_LAMBDA, indent);
+                self.out.push(':');
+                self.end_line(self.hi(cond.span), indent + 1);
+                return self.suite(body, indent + 1);
+            }
+            Stmt::Def(name, params, ret, box body) => {
+                self.out.push_str("def ");
+                self.out.push_str(&name.node);
+                let open = self.skip_trivia(self.hi(name.span));
+                let close = params
+                    .last()
+                    .map_or(open + 1, |x| self.close_pos(self.hi(x.span)));
+                self.seq(
+                    ("(", ")"),
+                    (open, close),
+                    params,
+                    indent,
+                    |x| x.span,
+                    |s, x, indent| s.parameter(x, indent),
+                );
+                let mut end = close + 1;
+                if let Some(ret) = ret {
+                    self.out.push_str(" -> ");
+                    self.expr(ret, PREC_LAMBDA, indent);
+                    end = self.hi(ret.span);
+                }
+                self.out.push(':');
+                self.end_line(end, indent + 1);
+                return self.suite(body, indent + 1);
+            }
+            Stmt::Load(module, symbols, _) => {
+                let mut items = vec![(module.span, self.literal(module.span))];
+                let mut symbols = symbols.iter().collect::<Vec<_>>();
+                if !self.has_comments(lo, self.hi(x.span)) {
+                    symbols.sort_by(|a, b| a.0.node.cmp(&b.0.node));
+                }
+                for (local, their) in symbols {
+                    let their_text = self.literal(their.span);
+                    let text = if local.node == their.node {
+                        their_text
+                    } else {
+                        format!("{} = {}", local.node, their_text)
+                    };
+                    items.push((local.span.merge(their.span), text));
+                }
+                self.out.push_str("load");
+                let open = self.skip_trivia(lo + "load".len());
+                self.seq(
+                    ("(", ")"),
+                    (open, self.hi(x.span) - 1),
+                    &items,
+                    indent,
+                    |x| x.0,
+                    |s, x, _| s.out.push_str(&x.1),
+                );
+            }
+        }
+        self.end_line(self.hi(x.span), indent);
+    }
+
+    fn if_stmt(
+        &mut self,
+        keyword: &str,
+        cond: &AstExpr,
+        then: &AstStmt,
+        els: Option<&AstStmt>,
+        indent: usize,
+    ) {
+        self.out.push_str(keyword);
+        self.expr(cond, PREC_LAMBDA, indent);
+        self.out.push(':');
+        self.end_line(self.hi(cond.span), indent + 1);
+        self.suite(then, indent + 1);
+        if let Some(els) = els {
+            // The position of the `else` or `elif` keyword
+            let pos = self.skip_trivia(self.hi(then.span));
+            self.comments_before(pos, indent);
+            self.indent(indent);
+            match &els.node {
+                // An `else` block is always a list of statements, so a bare `if` came from `elif`
+                Stmt::If(cond, box then) => self.if_stmt("elif ", cond, then, None, indent),
+                Stmt::IfElse(cond, box (then, els)) => {
+                    self.if_stmt("elif ", cond, then, Some(els), indent)
+                }
+                _ => {
+                    self.out.push_str("else:");
+                    self.end_line(pos + "else".len(), indent + 1);
+                    self.suite(els, indent + 1);
+                }
+            }
+        }
+    }
+
+    fn suite(&mut self, x: &AstStmt, indent: usize) {
+        self.last_line = None;
+        self.stmt(x, indent);
+        // Comments at the end of a block stay in it if they are indented like it
+        let column = self.column(self.lo(x.span));
+        let next = self.skip_trivia(self.hi(x.span));
+        while let Some(lo) = self.peek_comment() {
+            if lo >= next || self.column(lo) < column {
+                break;
+            }
+            self.own_line_comment(indent);
+        }
+    }
+
+    fn parameter(&mut self, x: &AstParameter, indent: usize) {
+        let (prefix, name, typ, default) = match &x.node {
+            Parameter::Normal(name, typ) => ("", name, typ, None),
+            Parameter::WithDefaultValue(name, typ, default) => ("", name, typ, Some(default)),
+            Parameter::NoArgs => return self.out.push('*'),
+            Parameter::Args(name, typ) => ("*", name, typ, None),
+            Parameter::KWArgs(name, typ) => ("**", name, typ, None),
+        };
+        self.out.push_str(prefix);
+        self.out.push_str(&name.node);
+        if let Some(typ) = typ {
+            self.out.push_str(": ");
+            self.expr(typ, PREC_LAMBDA, indent);
+        }
+        if let Some(default) = default {
+            self.out.push_str(" = ");
+            self.expr(default, PREC_LAMBDA, indent);
+        }
+    }
+
+    fn argument(&mut self, x: &AstArgument, indent: usize) {
+        let (prefix, e) = match &x.node {
+            Argument::Positional(e) => ("", e),
+            Argument::Named(name, e) => {
+                self.out.push_str(&name.node);
+                (" = ", e)
+            }
+            Argument::ArgsArray(e) => ("*", e),
+            Argument::KWArgsDict(e) => ("**", e),
+        };
+        self.out.push_str(prefix);
+        self.expr(e, PREC_LAMBDA, indent);
+    }
+
+    /// The source of a literal, switched to double quotes where possible.
+    fn literal(&self, x: Span) -> String {
+        let text = &self.source[self.lo(x)..self.hi(x)];
+        double_quoted(text).unwrap_or_else(|| text.to_owned())
+    }
+
+    /// Print an expression, adding brackets if it binds less tightly than `prec`.
+    fn expr(&mut self, x: &AstExpr, prec: u8, indent: usize) {
+        if precedence(&x.node) < prec {
+            self.out.push('(');
+            self.expr_inner(x, prec, indent);
+            self.out.push(')');
+        } else {
+            self.expr_inner(x, prec, indent);
+        }
+    }
+
+    fn expr_inner(&mut self, x: &AstExpr, prec: u8, indent: usize) {
+        match &x.node {
+            Expr::Tuple(xs) if xs.is_empty() => self.out.push_str("()"),
+            Expr::Tuple(xs) => {
+                // The span of a tuple doesn't include the brackets, so look for them
+                let start = self.skip_trivia_back(self.lo(x.span));
+                if start > 0 && self.byte(start - 1) == Some(b'(') {
+                    let (open, close) = (start - 1, self.close_pos(self.hi(x.span)));
+                    // A one element tuple needs a trailing comma, even on one line
+                    let brackets = if xs.len() == 1 && !self.is_multiline(open, close, xs.len()) {
+                        ("(", ",)")
+                    } else {
+                        ("(", ")")
+                    };
+                    self.seq(
+                        brackets,
+                        (open, close),
+                        xs,
+                        indent,
+                        |x| x.span,
+                        |s, x, indent| s.expr(x, PREC_LAMBDA, indent),
+                    );
+                } else {
+                    for (i, x) in xs.iter().enumerate() {
+                        if i != 0 {
+                            self.out.push_str(", ");
+                        }
+                        self.expr(x, prec, indent);
+                    }
+                    if xs.len() == 1 {
+                        self.out.push(',');
+                    }
+                }
+            }
+            Expr::Dot(e, name) => {
+                self.expr(e, PREC_ATOM, indent);
+                self.out.push('.');
+                self.out.push_str(&name.node);
+            }
+            Expr::Call(f, args) => {
+                self.expr(f, PREC_ATOM, indent);
+                let open = self.skip_trivia(self.hi(f.span));
+                self.seq(
+                    ("(", ")"),
+                    (open, self.hi(x.span) - 1),
+                    args,
+                    indent,
+                    |x| x.span,
+                    |s, x, indent| s.argument(x, indent),
+                );
+            }
+            Expr::ArrayIndirection(box (e, i)) => {
+                self.expr(e, PREC_ATOM, indent);
+                self.out.push('[');
+                self.expr(i, PREC_LAMBDA, indent);
+                self.out.push(']');
+            }
+            Expr::Slice(e, start, stop, stride) => {
+                self.expr(e, PREC_ATOM, indent);
+                self.out.push('[');
+                if let Some(start) = start {
+                    self.expr(start, PREC_LAMBDA, indent);
+                }
+                self.out.push(':');
+                if let Some(stop) = stop {
+                    self.expr(stop, PREC_LAMBDA, indent);
+                }
+                if let Some(stride) = stride {
+                    self.out.push(':');
+                    self.expr(stride, PREC_LAMBDA, indent);
+                }
+                self.out.push(']');
+            }
+            Expr::Identifier(name) => self.out.push_str(&name.node),
+            Expr::Lambda(params, body) => {
+                self.out.push_str("lambda");
+                for (i, x) in params.iter().enumerate() {
+                    self.out.push_str(if i == 0 { " " } else { ", " });
+                    self.parameter(x, indent);
+                }
+                self.out.push_str(": ");
+                self.expr(body, PREC_LAMBDA, indent);
+            }
+            Expr::Literal(_) | Expr::FString(..) => {
+                let text = self.literal(x.span);
+                self.out.push_str(&text);
+            }
+            Expr::Not(e) => {
+                self.out.push_str("not ");
+                self.expr(e, PREC_NOT, indent);
+            }
+            Expr::Minus(e) => {
+                self.out.push('-');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            Expr::Plus(e) => {
+                self.out.push('+');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            Expr::BitNot(e) => {
+                self.out.push('~');
+                self.expr(e, PREC_UNARY, indent);
+            }
+            Expr::Op(l, op, r) => {
+                let prec = binop_precedence(*op);
+                // `and`, `or` and comparisons nest to the right, everything else to the left
+                let (l_prec, r_prec) = if matches!(prec, PREC_OR | PREC_AND | PREC_COMPARE) {
+                    (prec + 1, prec)
+                } else {
+                    (prec, prec + 1)
+                };
+                self.expr(l, l_prec, indent);
+                self.out.push_str(&op.to_string());
+                self.expr(r, r_prec, indent);
+            }
+            Expr::If(box (cond, then, els)) => {
+                self.expr(then, PREC_OR, indent);
+                self.out.push_str(" if ");
+                self.expr(cond, PREC_OR, indent);
+                self.out.push_str(" else ");
+                self.expr(els, PREC_LAMBDA, indent);
+            }
+            Expr::List(xs) | Expr::Set(xs) => {
+                let brackets = match &x.node {
+                    Expr::List(_) => ("[", "]"),
+                    _ => ("{", "}"),
+                };
+                self.seq(
+                    brackets,
+                    (self.lo(x.span), self.hi(x.span) - 1),
+                    xs,
+                    indent,
+                    |x| x.span,
+                    |s, x, indent| s.expr(x, PREC_LAMBDA, indent),
+                );
+            }
+            Expr::Dict(xs) => self.seq(
+                ("{", "}"),
+                (self.lo(x.span), self.hi(x.span) - 1),
+                xs,
+                indent,
+                |(k, v)| k.span.merge(v.span),
+                |s, (k, v), indent| {
+                    s.expr(k, PREC_LAMBDA, indent);
+                    s.out.push_str(": ");
+                    s.expr(v, PREC_LAMBDA, indent);
+                },
+            ),
+            Expr::ListComprehension(e, clauses) => {
+                self.out.push('[');
+                self.expr(e, PREC_LAMBDA, indent);
+                self.clauses(clauses, indent);
+                self.out.push(']');
+            }
+            Expr::SetComprehension(e, clauses) => {
+                self.out.push('{');
+                self.expr(e, PREC_LAMBDA, indent);
+                self.clauses(clauses, indent);
+                self.out.push('}');
+            }
+            Expr::DictComprehension(box (k, v), clauses) => {
+                self.out.push('{');
+                self.expr(k, PREC_LAMBDA, indent);
+                self.out.push_str(": ");
+                self.expr(v, PREC_LAMBDA, indent);
+                self.clauses(clauses, indent);
+                self.out.push('}');
+            }
+        }
+    }
+
+    fn clauses(&mut self, xs: &[AstClause], indent: usize) {
+        for x in xs {
+            self.out.push_str(" for ");
+            self.expr(&x.var, PREC_PIPE, indent);
+            self.out.push_str(" in ");
+            self.expr(&x.over, PREC_OR, indent);
+            for x in &x.ifs {
+                self.out.push_str(" if ");
+                self.expr(x, PREC_OR, indent);
+            }
+        }
+    }
+}
+
+impl AstModule {
+    /// Print the module in a canonical style, keeping comments and single blank lines.
+    ///
+    /// Indentation is four spaces and string literals use double quotes where that
+    /// doesn't need extra escapes. Brackets that were written over several lines are
+    /// printed one element per line with a trailing comma, and others on a single line.
+    /// The symbols of each `load` are sorted. Nothing else is reordered, since dictionaries
+    /// keep their insertion order.
+    ///
+    /// ```
+    /// use starlark::syntax::{parse, Dialect};
+    /// let module = parse("x.star", "load('a', 'z', 'b')\nx=[1,2]\n".to_owned(), &Dialect::Standard).unwrap();
+    /// assert_eq!(module.format(), "load(\"a\", \"b\", \"z\")\nx = [1, 2]\n");
+    /// ```
+    pub fn format(&self) -> String {
+        let file = self.codemap.find_file(self.statement.span.low());
+        let mut printer = Printer::new(self, file.source(), file.span);
+        printer.stmt(&self.statement, 0);
+        printer.comments_before(usize::MAX, 0);
+        printer.out
+    }
+}
@@ -0,0 +1,182 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::syntax::{parse, Dialect};
+
+fn format(program: &str) -> String {
+    parse("format.star", program.to_owned(), &Dialect::Extended)
+        .unwrap()
+        .format()
+}
+
+// Check the formatting, and that formatting again doesn't change anything
+fn assert_format(program: &str, expected: &str) {
+    let actual = format(program);
+    assert_eq!(actual, expected);
+    assert_eq!(format(&actual), expected);
+}
+
+#[test]
+fn test_format_empty() {
+    assert_format("", "");
+    assert_format("\n\n", "");
+    assert_format("# Just a comment", "# Just a comment\n");
+}
+
+#[test]
+fn test_format_statements() {
+    assert_format(
+        r#"
+
+x=1
+y = 'a'  # trailing
+def f(a,b=2,*args,**kwargs):
+  # leading
+  return a+b*2
+
+
+
+z = [1,2]
+z -= [1]
+"#,
+        r#"x = 1
+y = "a"  # trailing
+def f(a, b = 2, *args, **kwargs):
+    # leading
+    return a + b * 2
+
+z = [1, 2]
+z -= [1]
+"#,
+    );
+}
+
+#[test]
+fn test_format_blocks() {
+    assert_format(
+        r#"
+if x:
+    pass
+elif y :
+  a = (1,)
+  b = 1,
+else:
+    # in else
+    for k, v in d.items(): print(k)
+    # end of else
+c = (a + b) * -(c)
+d = not (a or b) and c
+e = x if y else lambda: z
+"#,
+        r#"if x:
+    pass
+elif y:
+    a = (1,)
+    b = 1,
+else:
+    # in else
+    for k, v in d.items():
+        print(k)
+    # end of else
+c = (a + b) * -c
+d = not (a or b) and c
+e = x if y else lambda: z
+"#,
+    );
+}
+
+#[test]
+fn test_format_multiline() {
+    assert_format(
+        r#"
+foo(
+    'a', # first
+    # before b
+    b=[1,
+       2],
+)
+bar(1,
+  2)
+baz(
+)
+"#,
+        r#"foo(
+    "a",  # first
+    # before b
+    b = [
+        1,
+        2,
+    ],
+)
+bar(
+    1,
+    2,
+)
+baz()
+"#,
+    );
+}
+
+#[test]
+fn test_format_sorting() {
+    assert_format(
+        r#"
+load(':a.bzl', 'z', y = 'x', 'b')
+load(
+    ':b.bzl',
+    # Keep c first
+    'c',
+    'a',
+)
+rule(name = 'x', deps = {'b': 1, 'a': [2]}, other = {'b': f(), 'a': 1})
+x = {'b': 1, 'a': 2}
+"#,
+        r#"load(":a.bzl", "b", y = "x", "z")
+load(
+    ":b.bzl",
+    # Keep c first
+    "c",
+    "a",
+)
+rule(name = "x", deps = {"b": 1, "a": [2]}, other = {"b": f(), "a": 1})
+x = {"b": 1, "a": 2}
+"#,
+    );
+}
+
+#[test]
+fn test_format_literals() {
+    assert_format(
+        r#"
+def f():
+    '''Doc "quoted".'''
+    return r'\d', b'\x00', 'it\'s', "a'b", f'{x}', 0x1F, 1.5e3
+"#,
+        r#"def f():
+    '''Doc "quoted".'''
+    return r"\d", b"\x00", "it's", "a'b", f"{x}", 0x1F, 1.5e3
+"#,
+    );
+}
+
+#[test]
+fn test_format_precedence() {
+    assert_format(
+        "x = (a - b) - (c - d)\ny = (a == b) == c\nz = (lambda: 1)()\nw = -(1 + 2) * ~x[1:2]\n",
+        "x = a - b - (c - d)\ny = (a == b) == c\nz = (lambda: 1)()\nw = -(1 + 2) * ~x[1:2]\n",
+    );
+}
@@ -59,6 +59,59 @@ fn test_top_level_assignation() {
     );
 }
 
+#[test]
+fn test_float_and_division() {
+    assert_eq!(assert::parse("x = 1.5 / 2\n"), "x = (1.5 / 2)\n");
+    assert_eq!(assert::parse("x /= 1e20\n"), "x /= 1e+20\n");
+}
+
+#[test]
+fn test_bitwise_operators() {
+    assert_eq!(
+        assert::parse("x = 1 | 2 ^ 3 & 4 << 5 + 6\n"),
+        "x = (1 | (2 ^ (3 & (4 << (5 + 6)))))\n"
+    );
+    assert_eq!(assert::parse("x = 1 & 2 | 3\n"), "x = ((1 & 2) | 3)\n");
+    assert_eq!(assert::parse("x = ~1 == -2\n"), "x = (~1 == -2)\n");
+    assert_eq!(assert::parse("x <<= 2\n"), "x <<= 2\n");
+    assert_eq!(assert::parse("x ^= 2\n"), "x ^= 2\n");
+}
+
+#[test]
+fn test_set_literals() {
+    assert_eq!(assert::parse("x = {1, 2,}\n"), "x = {1, 2}\n");
+    assert_eq!(assert::parse("x = {1}\n"), "x = {1}\n");
+    assert_eq!(assert::parse("x = {}\n"), "x = {}\n");
+    assert_eq!(
+        assert::parse("x = {y for y in z}\n"),
+        "x = {y for y in z}\n"
+    );
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_set_literals = false);
+    a.parse_fail("x = !{1, 2}!\n");
+    a.parse_fail("x = !{y for y in z}!\n");
+    assert_eq!(a.parse("x = {1: 2}\n"), "x = {1: 2}\n");
+}
+
+#[test]
+fn test_fstrings() {
+    assert_eq!(
+        assert::parse("x = f'a{y}b{z!r:.2f}{{c}}'\n"),
+        "x = f\"a{y}b{z!r:.2f}{{c}}\"\n"
+    );
+    assert_eq!(
+        assert::parse("x = f'{ {1: 2}[1] }\\n'\n"),
+        "x = f\"{ {1: 2}[1]}\\n\"\n"
+    );
+    assert::parse_fail("x = f'a{y}!}!'\n");
+    assert::parse_fail("x = f'!{y!'\n");
+    assert::parse_fail("x = f'!{ }!'\n");
+    assert::parse_fail("x = f'{y + !$!}'\n");
+    let mut a = Assert::new();
+    a.dialect_set(|x| x.enable_fstrings = false);
+    a.parse_fail("x = !f'{y}'!\n");
+}
+
 #[test]
 fn test_top_level_docstring() {
     assert_eq!(
@@ -98,6 +151,7 @@ fn test_top_level_statements() {
     a.dialect_set(|x| x.enable_top_level_stmt = false);
     a.parse_fail("x = 1\n!if x == 1:\n  x = 2\n!x = 3");
     a.parse_fail("x = 1\n!for x in []:\n   pass\n!");
+    a.parse_fail("x = 1\n!while x:\n   pass\n!");
     assert_eq!(a.parse("pass"), "pass\n");
 
     assert_eq!(
@@ -108,6 +162,10 @@ fn test_top_level_statements() {
         assert::parse("for x in []:\n   pass\n"),
         "for x in []:\n  pass\n"
     );
+    assert_eq!(
+        assert::parse("while x < 1:\n   x += 1\n"),
+        "while (x < 1):\n  x += 1\n"
+    );
     assert_eq!(assert::parse("pass"), "pass\n");
 }
 
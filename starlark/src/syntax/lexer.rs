@@ -18,14 +18,17 @@
 use crate::{
     errors::Diagnostic,
     syntax::{
+        ast::{AstTrivia, Trivia},
         cursors::{CursorBytes, CursorChars},
         dialect::Dialect,
     },
 };
-use codemap::{CodeMap, Span};
+use codemap::{CodeMap, Span, Spanned};
 use gazebo::dupe::Dupe;
 use logos::Logos;
-use std::{char, collections::VecDeque, fmt, fmt::Display, sync::Arc};
+use num_bigint::BigInt;
+use num_traits::Num;
+use std::{char, collections::VecDeque, convert::TryFrom, fmt, fmt::Display, sync::Arc};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -40,6 +43,8 @@ pub enum LexemeError {
     UnfinishedStringLiteral,
     #[error("Parse error: invalid string escape sequence")]
     InvalidEscapeSequence,
+    #[error("Parse error: invalid f-string, {0}")]
+    InvalidFString(&'static str),
 }
 
 type Lexeme = anyhow::Result<(usize, Token, usize)>;
@@ -56,10 +61,35 @@ pub(crate) struct Lexer<'a> {
     lexer: logos::Lexer<'a, Token>,
     done: bool,
     dialect_allow_tabs: bool,
+    /// Comments and blank lines, if we are recording them
+    trivia: Option<Vec<AstTrivia>>,
+    /// The position of the start of the current line
+    line_start: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str, dialect: &Dialect, codemap: Arc<CodeMap>, filespan: Span) -> Self {
+        Self::create(input, dialect, codemap, filespan, false)
+    }
+
+    /// Like `new`, but also record the comments and blank lines, which can be
+    /// retrieved with `take_trivia` once lexing is finished.
+    pub fn new_with_trivia(
+        input: &'a str,
+        dialect: &Dialect,
+        codemap: Arc<CodeMap>,
+        filespan: Span,
+    ) -> Self {
+        Self::create(input, dialect, codemap, filespan, true)
+    }
+
+    fn create(
+        input: &'a str,
+        dialect: &Dialect,
+        codemap: Arc<CodeMap>,
+        filespan: Span,
+        trivia: bool,
+    ) -> Self {
         let lexer = Token::lexer(input);
         let mut lexer2 = Self {
             codemap,
@@ -71,6 +101,8 @@ impl<'a> Lexer<'a> {
             parens: 0,
             done: false,
             dialect_allow_tabs: dialect.enable_tabs,
+            trivia: if trivia { Some(Vec::new()) } else { None },
+            line_start: 0,
         };
         if let Err(e) = lexer2.calculate_indent() {
             lexer2.buffer.push_back(Err(e));
@@ -78,6 +110,36 @@ impl<'a> Lexer<'a> {
         lexer2
     }
 
+    /// The comments and blank lines seen so far, in order, if we are recording them.
+    pub fn take_trivia(&mut self) -> Option<Vec<AstTrivia>> {
+        self.trivia.take()
+    }
+
+    fn add_trivia(&mut self, node: Trivia, start: usize, end: usize) {
+        if let Some(trivia) = &mut self.trivia {
+            trivia.push(Spanned {
+                span: self.filespan.subspan(start as u64, end as u64),
+                node,
+            })
+        }
+    }
+
+    fn comment(&mut self, start: usize, end: usize) {
+        if self.trivia.is_some() {
+            let text = self.lexer.source()[start..end].trim_end();
+            self.add_trivia(Trivia::Comment(text.to_owned()), start, start + text.len());
+        }
+    }
+
+    // We have seen a newline between `start` and `end`, record the line it ends if it was blank
+    fn newline(&mut self, start: usize, end: usize) {
+        let line = &self.lexer.source()[self.line_start..start];
+        if self.trivia.is_some() && line.trim().is_empty() {
+            self.add_trivia(Trivia::BlankLine, self.line_start, start);
+        }
+        self.line_start = end;
+    }
+
     fn err_pos<T>(&self, msg: LexemeError, pos: usize) -> anyhow::Result<T> {
         self.err_span(msg, pos, pos)
     }
@@ -95,6 +157,7 @@ impl<'a> Lexer<'a> {
     fn calculate_indent(&mut self) -> anyhow::Result<()> {
         // consume tabs and spaces, output the indentation levels
         let mut it = CursorBytes::new(self.lexer.remainder());
+        let base = self.lexer.span().end;
         let mut spaces = 0;
         let mut tabs = 0;
         let mut indent_start = self.lexer.span().start;
@@ -125,9 +188,11 @@ impl<'a> Lexer<'a> {
                     // Remove skip now, so we can freely add it on later
                     spaces = 0;
                     tabs = 0;
+                    let start = base + it.pos() - 1;
                     loop {
                         match it.next_char() {
                             None => {
+                                self.comment(start, base + it.pos());
                                 self.lexer.bump(it.pos());
                                 return Ok(());
                             }
@@ -135,6 +200,8 @@ impl<'a> Lexer<'a> {
                             Some(_) => {}
                         }
                     }
+                    self.comment(start, base + it.pos() - 1);
+                    self.line_start = base + it.pos();
                     indent_start = self.lexer.span().start + it.pos();
                 }
                 _ => break,
@@ -214,16 +281,31 @@ impl<'a> Lexer<'a> {
         char::from_u32(value).ok_or(())
     }
 
+    fn push_char(res: &mut Vec<u8>, c: char) {
+        res.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    // In a bytes literal, `\x` and octal escapes denote a single byte,
+    // rather than a Unicode code point.
+    fn push_escaped(res: &mut Vec<u8>, c: char, bytes: bool) -> Result<(), ()> {
+        if bytes {
+            res.push(u8::try_from(c as u32).map_err(|_| ())?);
+        } else {
+            Self::push_char(res, c);
+        }
+        Ok(())
+    }
+
     // We have seen a '\' character, now parse what comes next
-    fn escape(it: &mut CursorChars, res: &mut String) -> Result<(), ()> {
+    fn escape(it: &mut CursorChars, res: &mut Vec<u8>, bytes: bool) -> Result<(), ()> {
         match it.next() {
-            Some('n') => res.push('\n'),
-            Some('r') => res.push('\r'),
-            Some('t') => res.push('\t'),
-            Some('a') => res.push('\x07'),
-            Some('b') => res.push('\x08'),
-            Some('f') => res.push('\x0C'),
-            Some('v') => res.push('\x0B'),
+            Some('n') => res.push(b'\n'),
+            Some('r') => res.push(b'\r'),
+            Some('t') => res.push(b'\t'),
+            Some('a') => res.push(0x07),
+            Some('b') => res.push(0x08),
+            Some('f') => res.push(0x0C),
+            Some('v') => res.push(0x0B),
             Some('\n') => {}
             Some('\r') => {
                 // Windows newline incoming, we expect a \n next, which we can ignore
@@ -232,18 +314,18 @@ impl<'a> Lexer<'a> {
                     return Err(());
                 }
             }
-            Some('x') => res.push(Self::escape_char(it, 2, 2, 16)?),
-            Some('u') => res.push(Self::escape_char(it, 4, 4, 16)?),
-            Some('U') => res.push(Self::escape_char(it, 8, 8, 16)?),
+            Some('x') => Self::push_escaped(res, Self::escape_char(it, 2, 2, 16)?, bytes)?,
+            Some('u') => Self::push_char(res, Self::escape_char(it, 4, 4, 16)?),
+            Some('U') => Self::push_char(res, Self::escape_char(it, 8, 8, 16)?),
             Some(c) => match c {
                 '0'..='7' => {
                     it.unnext(c);
-                    res.push(Self::escape_char(it, 1, 3, 8)?)
+                    Self::push_escaped(res, Self::escape_char(it, 1, 3, 8)?, bytes)?
                 }
-                '"' | '\'' | '\\' => res.push(c),
+                '"' | '\'' | '\\' => Self::push_char(res, c),
                 _ => {
-                    res.push('\\');
-                    res.push(c);
+                    res.push(b'\\');
+                    Self::push_char(res, c);
                 }
             },
             None => {
@@ -253,12 +335,28 @@ impl<'a> Lexer<'a> {
         Ok(())
     }
 
+    fn string_token(contents: Vec<u8>, bytes: bool) -> Token {
+        if bytes {
+            Token::BytesLiteral(contents)
+        } else {
+            // We only ever push whole characters, so the contents are valid UTF-8
+            Token::StringLiteral(String::from_utf8(contents).unwrap())
+        }
+    }
+
     // String parsing is a hot-spot, so parameterise by a `stop` function which gets
     // specialised for each variant
-    fn string(&mut self, triple: bool, raw: bool, mut stop: impl FnMut(char) -> bool) -> Lexeme {
+    fn string(
+        &mut self,
+        triple: bool,
+        raw: bool,
+        bytes: bool,
+        mut stop: impl FnMut(char) -> bool,
+    ) -> Lexeme {
         // We have seen an openning quote, which is either ' or "
         // If triple is true, it was a triple quote
         // stop lets us know when a string ends.
+        // If bytes is true, the literal was prefixed with `b`.
 
         // Before the first quote character
         let string_start = self.lexer.span().start;
@@ -289,15 +387,18 @@ impl<'a> Lexer<'a> {
                     if stop(c) {
                         let contents_end = it.pos() - if triple { 3 } else { 1 };
                         let contents = &self.lexer.remainder()[contents_start..contents_end];
+                        let token = if bytes {
+                            Token::BytesLiteral(contents.as_bytes().to_vec())
+                        } else {
+                            Token::StringLiteral(contents.to_owned())
+                        };
                         self.lexer.bump(it.pos());
-                        return Ok((
-                            string_start,
-                            Token::StringLiteral(contents.to_owned()),
-                            string_end + it.pos(),
-                        ));
+                        return Ok((string_start, token, string_end + it.pos()));
                     } else if c == '\\' || c == '\r' || (c == '\n' && !triple) {
-                        res = String::with_capacity(it.pos() + 10);
-                        res.push_str(&self.lexer.remainder()[contents_start..it.pos() - 1]);
+                        res = Vec::with_capacity(it.pos() + 10);
+                        res.extend_from_slice(
+                            self.lexer.remainder()[contents_start..it.pos() - 1].as_bytes(),
+                        );
                         it2 = CursorChars::new_offset(self.lexer.remainder(), it.pos() - 1);
                         break;
                     }
@@ -316,7 +417,7 @@ impl<'a> Lexer<'a> {
                 }
                 return Ok((
                     string_start,
-                    Token::StringLiteral(res),
+                    Self::string_token(res, bytes),
                     string_end + it.pos(),
                 ));
             }
@@ -335,17 +436,17 @@ impl<'a> Lexer<'a> {
                         match it.next() {
                             Some(c) => {
                                 if c == '\'' || c == '"' {
-                                    res.push(c);
+                                    Self::push_char(&mut res, c);
                                 } else {
-                                    res.push('\\');
-                                    res.push(c);
+                                    res.push(b'\\');
+                                    Self::push_char(&mut res, c);
                                 }
                             }
                             _ => break, // Out of chars
                         }
                     } else {
                         let pos = it.pos();
-                        if Self::escape(&mut it, &mut res).is_err() {
+                        if Self::escape(&mut it, &mut res, bytes).is_err() {
                             return self.err_span(
                                 LexemeError::InvalidEscapeSequence,
                                 string_end + pos - 1,
@@ -354,7 +455,7 @@ impl<'a> Lexer<'a> {
                         }
                     }
                 }
-                c => res.push(c),
+                c => Self::push_char(&mut res, c),
             }
         }
 
@@ -366,6 +467,160 @@ impl<'a> Lexer<'a> {
         )
     }
 
+    // Given an opening quote, possibly with `r`, `b` or `f` prefixes,
+    // is it (raw, bytes, fstring)
+    fn string_prefix(&self) -> (bool, bool, bool) {
+        let slice = self.lexer.slice();
+        let prefix = &slice[..slice.len() - 1];
+        (
+            prefix.contains('r'),
+            prefix.contains('b'),
+            prefix.contains('f'),
+        )
+    }
+
+    // Move the literal text of an f-string into the format string,
+    // doubling any braces so `str.format` treats them as text.
+    fn fstring_literal(format: &mut String, literal: &mut Vec<u8>) {
+        // We only ever push whole characters, so the contents are valid UTF-8
+        for c in std::str::from_utf8(literal).unwrap().chars() {
+            if c == '{' || c == '}' {
+                format.push(c);
+            }
+            format.push(c);
+        }
+        literal.clear();
+    }
+
+    // We have lexed the f-string `string`, now split the body into the format
+    // string and the source of each expression between braces.
+    fn fstring(&self, string: Lexeme, raw: bool, triple: bool) -> Lexeme {
+        let (start, _, end) = string?;
+        let quotes = if triple { 3 } else { 1 };
+        let source = self.lexer.source();
+        let body_start = start + source[start..].find(&['\'', '"'][..]).unwrap() + quotes;
+        let body = &source[body_start..end - quotes];
+        let err = |msg, start, end| self.err_span(LexemeError::InvalidFString(msg), start, end);
+
+        let mut res = TokenFString {
+            format: String::new(),
+            expressions: Vec::new(),
+        };
+        let mut literal = Vec::new();
+        let mut it = CursorChars::new_offset(body, 0);
+        while let Some(c) = it.next() {
+            match c {
+                '{' | '}' if it.peek() == Some(c) => {
+                    it.next();
+                    literal.push(c as u8);
+                }
+                '}' => {
+                    let pos = body_start + it.pos();
+                    return err("single `}` is not allowed", pos - 1, pos);
+                }
+                '{' => {
+                    Self::fstring_literal(&mut res.format, &mut literal);
+                    let field_start = it.pos();
+                    let unclosed = |pos| err("expected `}`", body_start + field_start - 1, pos);
+                    // The expression ends at a `}`, `!` or `:` outside of brackets and strings
+                    let mut depth = 0;
+                    let mut quote = None;
+                    let mut c = loop {
+                        let c = match it.next() {
+                            None => return unclosed(end - quotes),
+                            Some(c) => c,
+                        };
+                        if let Some(q) = quote {
+                            if c == q {
+                                quote = None;
+                            }
+                        } else if c == '\\' {
+                            let pos = body_start + it.pos();
+                            return err("backslash in an expression", pos - 1, pos);
+                        } else if c == '\'' || c == '"' {
+                            quote = Some(c);
+                        } else if c == '(' || c == '[' || c == '{' {
+                            depth += 1;
+                        } else if depth > 0 && (c == ')' || c == ']' || c == '}') {
+                            depth -= 1;
+                        } else if depth == 0
+                            && (c == '}' || c == ':' || (c == '!' && it.peek() != Some('=')))
+                        {
+                            break c;
+                        }
+                    };
+                    let expression = &body[field_start..it.pos() - 1];
+                    if expression.trim().is_empty() {
+                        let pos = body_start + it.pos();
+                        return err("empty expression", body_start + field_start - 1, pos);
+                    }
+                    res.expressions
+                        .push((body_start + field_start, expression.to_owned()));
+                    res.format.push('{');
+                    if c == '!' {
+                        match it.next() {
+                            Some(conv) if conv == 'r' || conv == 's' => {
+                                res.format.push('!');
+                                res.format.push(conv);
+                            }
+                            _ => {
+                                let pos = body_start + it.pos();
+                                return err("conversion must be `!r` or `!s`", pos - 2, pos);
+                            }
+                        }
+                        c = match it.next() {
+                            Some(c) if c == ':' || c == '}' => c,
+                            _ => return unclosed(body_start + it.pos()),
+                        };
+                    }
+                    if c == ':' {
+                        res.format.push(':');
+                        loop {
+                            match it.next() {
+                                None => return unclosed(end - quotes),
+                                Some('}') => break,
+                                Some('{') => {
+                                    let pos = body_start + it.pos();
+                                    return err(
+                                        "nested format fields are not supported",
+                                        pos - 1,
+                                        pos,
+                                    );
+                                }
+                                Some(c) => res.format.push(c),
+                            }
+                        }
+                    }
+                    res.format.push('}');
+                }
+                '\\' if raw => match it.next() {
+                    Some(c) if c == '\'' || c == '"' => Self::push_char(&mut literal, c),
+                    Some(c) => {
+                        literal.push(b'\\');
+                        Self::push_char(&mut literal, c);
+                    }
+                    None => literal.push(b'\\'),
+                },
+                '\\' => {
+                    let pos = it.pos();
+                    if Self::escape(&mut it, &mut literal, false).is_err() {
+                        return self.err_span(
+                            LexemeError::InvalidEscapeSequence,
+                            body_start + pos - 1,
+                            body_start + it.pos(),
+                        );
+                    }
+                }
+                '\r' => {
+                    // We just ignore these in all modes
+                }
+                c => Self::push_char(&mut literal, c),
+            }
+        }
+        Self::fstring_literal(&mut res.format, &mut literal);
+        Ok((start, Token::FStringLiteral(res), end))
+    }
+
     pub fn next(&mut self) -> Option<Lexeme> {
         loop {
             // Note that this function doesn't always return - a few branches use `continue`
@@ -394,9 +649,15 @@ impl<'a> Lexer<'a> {
                             }
                             continue;
                         }
+                        Token::Comment => {
+                            let span = self.lexer.span();
+                            self.comment(span.start, span.end);
+                            continue;
+                        }
                         Token::Newline => {
+                            let span = self.lexer.span();
+                            self.newline(span.start, span.end);
                             if self.parens == 0 {
-                                let span = self.lexer.span();
                                 if let Err(e) = self.calculate_indent() {
                                     return Some(Err(e));
                                 }
@@ -411,10 +672,11 @@ impl<'a> Lexer<'a> {
                             self.lexer.span().end,
                         )),
                         Token::RawDoubleQuote => {
-                            let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("\"\"") {
+                            let (raw, bytes, fstring) = self.string_prefix();
+                            let triple = self.lexer.remainder().starts_with("\"\"");
+                            let res = if triple {
                                 let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
+                                self.string(true, raw, bytes, |c| {
                                     if c == '\"' {
                                         qs += 1;
                                         qs == 3
@@ -422,16 +684,22 @@ impl<'a> Lexer<'a> {
                                         qs = 0;
                                         false
                                     }
-                                }))
+                                })
                             } else {
-                                Some(self.string(false, raw, |c| c == '\"'))
-                            }
+                                self.string(false, raw, bytes, |c| c == '\"')
+                            };
+                            Some(if fstring {
+                                self.fstring(res, raw, triple)
+                            } else {
+                                res
+                            })
                         }
                         Token::RawSingleQuote => {
-                            let raw = self.lexer.span().len() == 2;
-                            if self.lexer.remainder().starts_with("''") {
+                            let (raw, bytes, fstring) = self.string_prefix();
+                            let triple = self.lexer.remainder().starts_with("''");
+                            let res = if triple {
                                 let mut qs = 0;
-                                Some(self.string(true, raw, |c| {
+                                self.string(true, raw, bytes, |c| {
                                     if c == '\'' {
                                         qs += 1;
                                         qs == 3
@@ -439,10 +707,15 @@ impl<'a> Lexer<'a> {
                                         qs = 0;
                                         false
                                     }
-                                }))
+                                })
                             } else {
-                                Some(self.string(false, raw, |c| c == '\''))
-                            }
+                                self.string(false, raw, bytes, |c| c == '\'')
+                            };
+                            Some(if fstring {
+                                self.fstring(res, raw, triple)
+                            } else {
+                                res
+                            })
                         }
                         Token::OpeningCurly | Token::OpeningRound | Token::OpeningSquare => {
                             self.parens += 1;
@@ -460,18 +733,58 @@ impl<'a> Lexer<'a> {
     }
 }
 
+/// The value of an integer literal. Literals which don't fit into `i32`
+/// are stored boxed, to keep the size of the AST down.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TokenInt {
+    I32(i32),
+    BigInt(Box<BigInt>),
+}
+
+impl TokenInt {
+    pub fn from_str_radix(s: &str, base: u32) -> Option<TokenInt> {
+        if let Ok(i) = i32::from_str_radix(s, base) {
+            Some(TokenInt::I32(i))
+        } else {
+            BigInt::from_str_radix(s, base)
+                .ok()
+                .map(|i| TokenInt::BigInt(box i))
+        }
+    }
+}
+
+impl Display for TokenInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenInt::I32(i) => write!(f, "{}", i),
+            TokenInt::BigInt(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+/// An f-string literal, split into a format string with an empty field
+/// for each expression, and the expressions. For `f"a{x!r}"` the format
+/// string is `"a{!r}"` and the single expression is `x`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenFString {
+    pub format: String,
+    /// The source of each expression, with its position in the file
+    pub expressions: Vec<(usize, String)>,
+}
+
 /// All token that can be generated by the lexer
 #[derive(Logos, Debug, Clone, PartialEq)]
 pub enum Token {
     #[regex(" +", logos::skip)] // Whitespace
     #[token("\\\n", logos::skip)] // Escaped newline
     #[token("\\\r\n", logos::skip)] // Escaped newline (Windows line ending)
-    #[regex(r#"#[^\n]*"#, logos::skip)] // Comments
     #[error]
     Error,
 
     #[regex("\t+")] // Tabs (might be an error)
     Tabs,
+    #[regex(r#"#[^\n]*"#)] // Comments (only recorded as trivia)
+    Comment,
 
     // Indentation block & meaningfull spaces
     Indent, // New indentation block
@@ -483,13 +796,25 @@ pub enum Token {
     // things ourselves
     #[token("'")]
     #[token("r'")]
+    #[token("b'")]
+    #[token("br'")]
+    #[token("rb'")]
+    #[token("f'")]
+    #[token("fr'")]
+    #[token("rf'")]
     RawSingleQuote,
     #[token("\"")]
     #[token("r\"")]
+    #[token("b\"")]
+    #[token("br\"")]
+    #[token("rb\"")]
+    #[token("f\"")]
+    #[token("fr\"")]
+    #[token("rf\"")]
     RawDoubleQuote,
 
     #[regex(
-        "as|import|is|class|nonlocal|del|raise|except|try|finally|while|from|with|global|yield"
+        "as|import|is|class|nonlocal|del|raise|except|try|finally|from|with|global|yield"
     , |lex| lex.slice().to_owned())]
     Reserved(String), // One of the reserved keywords
 
@@ -502,24 +827,31 @@ pub enum Token {
         "[0-9]+"
     , |lex|
         if lex.slice().len() > 1 && &lex.slice()[0..1] == "0" {
-            // Hack to make it return an error
-            "".parse()
+            // Leading zeros are not allowed, return an error
+            None
         } else {
-            lex.slice().parse()
+            TokenInt::from_str_radix(lex.slice(), 10)
         }
     )]
     #[regex(
         "0[xX][A-Fa-f0-9]+"
-    , |lex| i32::from_str_radix(&lex.slice()[2..], 16))]
+    , |lex| TokenInt::from_str_radix(&lex.slice()[2..], 16))]
     #[regex(
         "0[bB][01]+"
-    , |lex| i32::from_str_radix(&lex.slice()[2..], 2))]
+    , |lex| TokenInt::from_str_radix(&lex.slice()[2..], 2))]
     #[regex(
         "0[oO][0-7]+"
-    , |lex| i32::from_str_radix(&lex.slice()[2..], 8))]
-    IntegerLiteral(i32), // An integer literal (123, 0x1, 0b1011, 0o755, ...)
+    , |lex| TokenInt::from_str_radix(&lex.slice()[2..], 8))]
+    IntegerLiteral(TokenInt), // An integer literal (123, 0x1, 0b1011, 0o755, ...)
+
+    #[regex("[0-9]+\\.[0-9]*([eE][-+]?[0-9]+)?", |lex| lex.slice().parse())]
+    #[regex("[0-9]+[eE][-+]?[0-9]+", |lex| lex.slice().parse())]
+    #[regex("\\.[0-9]+([eE][-+]?[0-9]+)?", |lex| lex.slice().parse())]
+    FloatLiteral(f64), // A float literal (3.14, .3, 1e6, 0.)
 
-    StringLiteral(String), // A string literal
+    StringLiteral(String),        // A string literal
+    BytesLiteral(Vec<u8>),        // A bytes literal
+    FStringLiteral(TokenFString), // An f-string literal
 
     // Keywords
     #[token("and")]
@@ -552,6 +884,8 @@ pub enum Token {
     Return,
     #[token("lambda")]
     Lambda,
+    #[token("while")]
+    While,
     // Symbols
     #[token(",")]
     Comma,
@@ -571,6 +905,16 @@ pub enum Token {
     SlashSlashEqual,
     #[token("%=")]
     PercentEqual,
+    #[token("|=")]
+    PipeEqual,
+    #[token("&=")]
+    AmpersandEqual,
+    #[token("^=")]
+    CaretEqual,
+    #[token("<<=")]
+    LessLessEqual,
+    #[token(">>=")]
+    GreaterGreaterEqual,
     #[token("==")]
     EqualEqual,
     #[token("!=")]
@@ -605,6 +949,16 @@ pub enum Token {
     Dot,
     #[token("|")]
     Pipe,
+    #[token("&")]
+    Ampersand,
+    #[token("^")]
+    Caret,
+    #[token("~")]
+    Tilde,
+    #[token("<<")]
+    LessLess,
+    #[token(">>")]
+    GreaterGreater,
 
     // Brackets
     #[token("[")]
@@ -629,6 +983,14 @@ impl Token {
             Token::Newline => "\n".to_owned(),
             Token::Dedent => "#dedent".to_owned(),
             Token::StringLiteral(x) => format!("{:?}", x),
+            Token::BytesLiteral(x) => {
+                let mut s = "b\"".to_owned();
+                for b in x {
+                    s.push_str(&format!("\\x{:02x}", b));
+                }
+                s.push('"');
+                s
+            }
             _ => {
                 let s = self.to_string();
                 let first = s.find('\'');
@@ -665,6 +1027,7 @@ impl Display for Token {
             Token::Elif => write!(f, "keyword 'elif'"),
             Token::Return => write!(f, "keyword 'return'"),
             Token::Lambda => write!(f, "keyword 'lambda'"),
+            Token::While => write!(f, "keyword 'while'"),
             Token::Comma => write!(f, "symbol ','"),
             Token::Semicolon => write!(f, "symbol ';'"),
             Token::Colon => write!(f, "symbol ':'"),
@@ -674,6 +1037,11 @@ impl Display for Token {
             Token::SlashEqual => write!(f, "symbol '/='"),
             Token::SlashSlashEqual => write!(f, "symbol '//='"),
             Token::PercentEqual => write!(f, "symbol '%='"),
+            Token::PipeEqual => write!(f, "symbol '|='"),
+            Token::AmpersandEqual => write!(f, "symbol '&='"),
+            Token::CaretEqual => write!(f, "symbol '^='"),
+            Token::LessLessEqual => write!(f, "symbol '<<='"),
+            Token::GreaterGreaterEqual => write!(f, "symbol '>>='"),
             Token::EqualEqual => write!(f, "symbol '=='"),
             Token::BangEqual => write!(f, "symbol '!='"),
             Token::LessEqual => write!(f, "symbol '<='"),
@@ -691,6 +1059,11 @@ impl Display for Token {
             Token::SlashSlash => write!(f, "symbol '//'"),
             Token::Dot => write!(f, "symbol '.'"),
             Token::Pipe => write!(f, "symbol '|'"),
+            Token::Ampersand => write!(f, "symbol '&'"),
+            Token::Caret => write!(f, "symbol '^'"),
+            Token::Tilde => write!(f, "symbol '~'"),
+            Token::LessLess => write!(f, "symbol '<<'"),
+            Token::GreaterGreater => write!(f, "symbol '>>'"),
             Token::OpeningSquare => write!(f, "symbol '['"),
             Token::OpeningCurly => write!(f, "symbol '{{'"),
             Token::OpeningRound => write!(f, "symbol '('"),
@@ -700,10 +1073,16 @@ impl Display for Token {
             Token::Reserved(s) => write!(f, "reserved keyword '{}'", s),
             Token::Identifier(s) => write!(f, "identifier '{}'", s),
             Token::IntegerLiteral(i) => write!(f, "integer literal '{}'", i),
+            Token::FloatLiteral(x) => write!(f, "float literal '{}'", x),
             Token::StringLiteral(s) => write!(f, "string literal '{}'", s),
+            Token::BytesLiteral(s) => {
+                write!(f, "bytes literal '{}'", String::from_utf8_lossy(s))
+            }
+            Token::FStringLiteral(s) => write!(f, "f-string literal '{}'", s.format),
             Token::RawSingleQuote => write!(f, "starting '"),
             Token::RawDoubleQuote => write!(f, "starting \""),
             Token::Tabs => Ok(()),
+            Token::Comment => Ok(()),
         }
     }
 }
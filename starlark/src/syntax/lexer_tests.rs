@@ -15,7 +15,13 @@
  * limitations under the License.
  */
 
-use crate::{assert, syntax::lexer::Token::*};
+use crate::{
+    assert,
+    syntax::{
+        lexer::{Token::*, TokenFString},
+        parse_with_trivia, Dialect, Trivia,
+    },
+};
 
 #[test]
 fn test_int_lit() {
@@ -23,10 +29,23 @@ fn test_int_lit() {
     assert_eq!(assert::lex("0x7F 0x7d"), "127 125 \n");
     assert_eq!(assert::lex("0B1011 0b1010"), "11 10 \n");
     assert_eq!(assert::lex("0o755 0O753"), "493 491 \n");
+    assert_eq!(
+        assert::lex("4294967296 0x100000000"),
+        "4294967296 4294967296 \n"
+    );
     // Starlark requires us to ban leading zeros (confusion with implicit octal)
     assert::parse_fail("x = !01!");
 }
 
+#[test]
+fn test_float_lit() {
+    assert_eq!(
+        assert::lex("0.5 1. .25 1.5e3 2e-2"),
+        "0.5 1 0.25 1500 0.02 \n"
+    );
+    assert_eq!(assert::lex("x[1.5]"), "x [ 1.5 ] \n");
+}
+
 #[test]
 fn test_indentation() {
     assert_eq!(
@@ -55,13 +74,22 @@ fn test_symbols() {
     assert_eq!(assert::lex(",;:{}[]()|"), ", ; : { } [ ] ( ) | \n",);
 }
 
+#[test]
+fn test_bitwise_symbols() {
+    assert_eq!(
+        assert::lex("& ^ ~ << >> |= &= ^= <<= >>="),
+        "& ^ ~ << >> |= &= ^= <<= >>= \n",
+    );
+    assert_eq!(assert::lex("a<<=b>>c"), "a <<= b >> c \n");
+}
+
 #[test]
 fn test_keywords() {
     assert_eq!(
         assert::lex(
-            "and else load break for not not  in continue if or def in pass elif return lambda"
+            "and else load break for not not  in continue if or def in pass elif return lambda while"
         ),
-        "and else load break for not not in continue if or def in pass elif return lambda \n"
+        "and else load break for not not in continue if or def in pass elif return lambda while \n"
     );
 }
 
@@ -79,9 +107,9 @@ fn test_reserved() {
     assert_eq!(
         assert::lex(
             "as import is class nonlocal del raise except try finally \
-             while from with global yield",
+             from with global yield",
         ),
-        "as import is class nonlocal del raise except try finally while from with global yield \n"
+        "as import is class nonlocal del raise except try finally from with global yield \n"
     );
 }
 
@@ -95,6 +123,37 @@ fn test_comment() {
     assert_eq!(assert::lex("[\n# a comment\n]"), "[ ] \n");
 }
 
+#[test]
+fn test_trivia() {
+    let program = "# a\nx = 1  # b\n\n  \ny = [\n  # c\n\n]\n";
+    let module = parse_with_trivia("trivia.star", program.to_owned(), &Dialect::Standard).unwrap();
+    let start = module
+        .codemap
+        .find_file(module.statement.span.low())
+        .span
+        .low();
+    let actual = module
+        .trivia()
+        .unwrap()
+        .iter()
+        .map(|x| (x.node.clone(), x.span.low() - start, x.span.high() - start))
+        .collect::<Vec<_>>();
+    let comment = |x: &str| Trivia::Comment(x.to_owned());
+    assert_eq!(
+        actual,
+        vec![
+            (comment("# a"), 0, 3),
+            (comment("# b"), 11, 14),
+            (Trivia::BlankLine, 15, 15),
+            (Trivia::BlankLine, 16, 18),
+            (comment("# c"), 27, 30),
+            (Trivia::BlankLine, 31, 31),
+        ]
+    );
+    // Trivia is only recorded on request
+    assert!(assert::parse_ast("# a\n").trivia().is_none());
+}
+
 #[test]
 fn test_identifier() {
     assert_eq!(
@@ -144,6 +203,50 @@ fn test_string_escape() {
     assert::parse_fail("test 'more !\\x0!");
 }
 
+#[test]
+fn test_bytes_lit() {
+    let lex = |x| {
+        assert::lex_tokens(x)
+            .into_iter()
+            .map(|x| x.1)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        lex(r#"b"ab" b'\xff\x00' br"\x" rb'\'' b"é" b'\377'"#),
+        vec![
+            BytesLiteral(b"ab".to_vec()),
+            BytesLiteral(vec![0xff, 0]),
+            BytesLiteral(b"\\x".to_vec()),
+            BytesLiteral(b"'".to_vec()),
+            BytesLiteral("é".as_bytes().to_vec()),
+            BytesLiteral(vec![0xff]),
+            Newline,
+        ]
+    );
+    // A `\x` escape in a string is still a code point
+    assert_eq!(assert::lex("'\\xff'"), "\"ÿ\" \n");
+    assert::parse_fail("b'!\\400!'");
+}
+
+#[test]
+fn test_fstring_lit() {
+    let actual = assert::lex_tokens(r#"f"a{x}{{b}}{y[0]!r:.2f}\x41""#);
+    assert_eq!(
+        actual[0],
+        (
+            0,
+            FStringLiteral(TokenFString {
+                format: "a{}{{b}}{!r:.2f}A".to_owned(),
+                expressions: vec![(4, "x".to_owned()), (12, "y[0]".to_owned())],
+            }),
+            28
+        )
+    );
+    assert::parse_fail("f'{x}!}!'");
+    assert::parse_fail("f'{x:!{!y}}'");
+    assert::parse_fail("f'{x + !\\!n}'");
+}
+
 #[test]
 fn test_simple_example() {
     assert_eq!(
@@ -17,10 +17,12 @@
 
 //! The syntax module that handle lexing and parsing
 
-pub use ast::AstModule;
+pub use ast::{AstModule, AstTrivia, Trivia};
 pub use dialect::Dialect;
-pub use parser::{parse, parse_file};
+pub use parser::{parse, parse_file, parse_with_trivia};
 
+#[cfg(test)]
+mod format_tests;
 #[cfg(test)]
 mod grammar_tests;
 #[cfg(test)]
@@ -31,6 +33,7 @@ mod testcases;
 pub(crate) mod ast;
 pub(crate) mod cursors;
 mod dialect;
+mod format;
 pub(crate) mod lexer;
 pub(crate) mod validate;
 
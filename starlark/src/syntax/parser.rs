@@ -18,7 +18,7 @@
 use crate::{
     errors::Diagnostic,
     syntax::{
-        ast::{AstModule, AstStmt, Stmt},
+        ast::{AstExpr, AstModule, AstStmt, AstTrivia, Stmt},
         dialect::Dialect,
         grammar::StarlarkParser,
         lexer::{Lexer, Token},
@@ -27,7 +27,14 @@ use crate::{
 use codemap::{CodeMap, Span, SpanLoc};
 use gazebo::prelude::*;
 use lalrpop_util as lu;
-use std::{fs, path::Path, sync::Arc};
+use std::{borrow::Cow, fs, path::Path, sync::Arc};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+enum ParserError {
+    #[error("Parse error: expected an expression inside the f-string")]
+    FStringExpression,
+}
 
 fn one_of(expected: &[String]) -> String {
     let mut result = String::new();
@@ -101,24 +108,117 @@ pub(crate) fn parse_error_add_span(
 
 /// Parse a Starlark file.
 pub fn parse(filename: &str, content: String, dialect: &Dialect) -> anyhow::Result<AstModule> {
+    parse_module(filename, content, dialect, false)
+}
+
+/// Parse a Starlark file, also recording the comments and blank lines,
+/// available from [`AstModule::trivia`].
+pub fn parse_with_trivia(
+    filename: &str,
+    content: String,
+    dialect: &Dialect,
+) -> anyhow::Result<AstModule> {
+    parse_module(filename, content, dialect, true)
+}
+
+fn parse_module(
+    filename: &str,
+    content: String,
+    dialect: &Dialect,
+    trivia: bool,
+) -> anyhow::Result<AstModule> {
     let mut codemap = CodeMap::new();
     let file = codemap.add_file(filename.to_string(), content);
     let codemap = Arc::new(codemap);
-    let lexer = Lexer::new(file.source(), dialect, codemap.dupe(), file.span);
-    match StarlarkParser::new().parse(&codemap, file.span, dialect, lexer) {
-        Ok(v) => Ok(AstModule::create(codemap, v, dialect)?),
+    let mut lexer = if trivia {
+        Lexer::new_with_trivia(file.source(), dialect, codemap.dupe(), file.span)
+    } else {
+        Lexer::new(file.source(), dialect, codemap.dupe(), file.span)
+    };
+    match StarlarkParser::new().parse(&codemap, file.span, dialect, &mut lexer) {
+        Ok(v) => Ok(AstModule::create(codemap, v, dialect, lexer.take_trivia())?),
         Err(p) => Err(parse_error_add_span(p, file.span, codemap)),
     }
 }
 
+/// Parse the expressions between the braces of an f-string, given the
+/// position of each expression in the file.
+pub(crate) fn parse_fstring_expressions(
+    codemap: &Arc<CodeMap>,
+    file_span: Span,
+    dialect: &Dialect,
+    expressions: Vec<(usize, String)>,
+) -> anyhow::Result<Vec<AstExpr>> {
+    let mut res = Vec::with_capacity(expressions.len());
+    for (pos, source) in expressions {
+        // Leading whitespace would otherwise be lexed as an indent
+        let trimmed = source.trim_start();
+        let pos = pos + source.len() - trimmed.len();
+        let span = file_span.subspan(pos as u64, (pos + trimmed.len()) as u64);
+        let lexer = Lexer::new(trimmed, dialect, codemap.dupe(), span)
+            .map(|x| x.map(|(l, t, r)| (l + pos, t, r + pos)));
+        let stmt = match StarlarkParser::new().parse(codemap, file_span, dialect, lexer) {
+            Ok(stmt) => stmt,
+            Err(p) => return Err(parse_error_add_span(p, file_span, codemap.dupe())),
+        };
+        match stmt.node {
+            Stmt::Statements(mut xs) if xs.len() == 1 => match xs.pop().unwrap().node {
+                Stmt::Expression(x) => res.push(x),
+                _ => {
+                    return Err(Diagnostic::add_span(
+                        ParserError::FStringExpression,
+                        span,
+                        codemap.dupe(),
+                    ));
+                }
+            },
+            _ => {
+                return Err(Diagnostic::add_span(
+                    ParserError::FStringExpression,
+                    span,
+                    codemap.dupe(),
+                ));
+            }
+        }
+    }
+    Ok(res)
+}
+
 impl AstModule {
     fn create(
         codemap: Arc<CodeMap>,
         statement: AstStmt,
         dialect: &Dialect,
+        trivia: Option<Vec<AstTrivia>>,
     ) -> anyhow::Result<AstModule> {
         Stmt::validate(&codemap, &statement, dialect)?;
-        Ok(AstModule { codemap, statement })
+        Ok(AstModule {
+            codemap,
+            statement,
+            trivia,
+            enable_recursion: dialect.enable_recursion,
+        })
+    }
+
+    /// The recorded trivia, or if there is none, the trivia from lexing the source again.
+    pub(crate) fn trivia_or_lex(&self) -> Cow<[AstTrivia]> {
+        if let Some(trivia) = self.trivia() {
+            return Cow::Borrowed(trivia);
+        }
+        let file = self.codemap.find_file(self.statement.span.low());
+        // The module already parsed, so the dialect just needs to be permissive enough
+        let mut lexer = Lexer::new_with_trivia(
+            file.source(),
+            &Dialect::Extended,
+            self.codemap.dupe(),
+            file.span,
+        );
+        for token in &mut lexer {
+            if token.is_err() {
+                break;
+            }
+        }
+        Cow::Owned(lexer.take_trivia().unwrap_or_default())
     }
 
     pub fn collect_loads(&self) -> Vec<&str> {
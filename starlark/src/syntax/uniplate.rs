@@ -46,6 +46,10 @@ impl Stmt {
                 f(Either::Right(over));
                 f(Either::Left(body));
             }
+            Stmt::While(condition, box body) => {
+                f(Either::Right(condition));
+                f(Either::Left(body));
+            }
             // Nothing else contains nested statements
             Stmt::Break => {}
             Stmt::Continue => {}
@@ -143,6 +147,7 @@ impl Expr {
             Expr::Not(x) => f(x),
             Expr::Minus(x) => f(x),
             Expr::Plus(x) => f(x),
+            Expr::BitNot(x) => f(x),
             Expr::Op(x, _, y) => {
                 f(x);
                 f(y);
@@ -152,12 +157,12 @@ impl Expr {
                 f(b);
                 f(c);
             }
-            Expr::List(x) => x.iter().for_each(|x| f(x)),
+            Expr::List(x) | Expr::Set(x) | Expr::FString(_, x) => x.iter().for_each(|x| f(x)),
             Expr::Dict(x) => x.iter().for_each(|(x, y)| {
                 f(x);
                 f(y);
             }),
-            Expr::ListComprehension(x, y) => {
+            Expr::ListComprehension(x, y) | Expr::SetComprehension(x, y) => {
                 y.iter().for_each(|x| x.visit_expr(|x| f(x)));
                 f(x);
             }
@@ -45,6 +45,8 @@ enum ValidateError {
     NoTopLevelIf,
     #[error("`for` cannot be used outside `def` in this dialect")]
     NoTopLevelFor,
+    #[error("`while` cannot be used outside `def` in this dialect")]
+    NoTopLevelWhile,
 }
 
 #[derive(Eq, PartialEq, Ord, PartialOrd)]
@@ -233,10 +235,10 @@ impl Stmt {
         stmt: &AstStmt,
         dialect: &Dialect,
     ) -> anyhow::Result<()> {
-        // Inside a for, we allow continue/break, unless we go beneath a def.
+        // Inside a for or while, we allow continue/break, unless we go beneath a def.
         // Inside a def, we allow return.
         // All load's must occur at the top-level.
-        // At the top-level we only allow for/if when the dialect permits it.
+        // At the top-level we only allow for/while/if when the dialect permits it.
         fn f(
             codemap: &Arc<CodeMap>,
             dialect: &Dialect,
@@ -256,6 +258,13 @@ impl Stmt {
                         f(codemap, dialect, body, false, true, inside_def)
                     }
                 }
+                Stmt::While(_, box body) => {
+                    if top_level && !dialect.enable_top_level_stmt {
+                        err(ValidateError::NoTopLevelWhile)
+                    } else {
+                        f(codemap, dialect, body, false, true, inside_def)
+                    }
+                }
                 Stmt::If(..) | Stmt::IfElse(..) => {
                     if top_level && !dialect.enable_top_level_stmt {
                         err(ValidateError::NoTopLevelIf)
@@ -39,6 +39,12 @@ pub enum ValueError {
     /// Arithmetic operation results in integer overflow.
     #[error("Integer overflow")]
     IntegerOverflow,
+    /// Shifting an int by a negative number of bits.
+    #[error("Negative shift count")]
+    NegativeShiftCount,
+    /// Shifting an int left by too many bits.
+    #[error("Shift count too large")]
+    ShiftCountTooLarge,
     /// Trying to modify an immutable value.
     #[error("Immutable")]
     CannotMutateImmutableValue,
@@ -51,6 +57,9 @@ pub enum ValueError {
     /// Trying to access an index outside of the value range,
     #[error("Index `{0}` is out of bound")]
     IndexOutOfBound(i32),
+    /// The value has no JSON representation, stores the repr of the value.
+    #[error("Value `{0}` can't be represented in JSON")]
+    NotJson(String),
     /// The value is not hashable but was requested for a hash structure (e.g.
     /// dictionary).
     #[error("Value of type `{0}` is not hashable")]
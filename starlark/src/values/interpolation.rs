@@ -18,12 +18,22 @@
 //! String interpolation-related code.
 //! Based on https://docs.python.org/3/library/stdtypes.html#printf-style-string-formatting
 
-use crate::values::{tuple::Tuple, Heap, Value, ValueLike};
+use crate::values::{
+    bigint::StarlarkBigInt,
+    float::{write_compact, write_decimal, write_scientific, StarlarkFloat},
+    tuple::Tuple,
+    Heap, Value, ValueLike,
+};
 use gazebo::prelude::*;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, Signed};
 use std::{fmt::Write, iter};
 use thiserror::Error;
 
-const AFTER_PERCENT: &str = "'%' must be followed by an optional name and a specifier ('s', 'r', 'd', 'i', 'o', 'x', 'X', 'c') or '%'";
+const AFTER_PERCENT: &str = "'%' must be followed by an optional name, an optional precision and a specifier ('s', 'r', 'd', 'i', 'o', 'x', 'X', 'c', 'f', 'e', 'E', 'g', 'G') or '%'";
+
+/// Precision used by `%f`, `%e` and `%g` when none is given.
+const DEFAULT_PRECISION: usize = 6;
 
 /// Operator `%` format or evaluation errors
 #[derive(Clone, Dupe, Debug, Error)]
@@ -47,6 +57,12 @@ enum StringInterpolationError {
     NotEnoughParameters,
     #[error("'%c' formatter requires a single-character string")]
     ValueNotChar,
+    #[error("'%{}' formatter requires a number", .0)]
+    ValueNotNumber(char),
+    #[error("'%{}' formatter requires a finite number", .0)]
+    ValueNotFinite(char),
+    #[error("Precision is only supported for float formatters, not '%{}'", .0)]
+    PrecisionNotSupported(char),
 }
 
 /// Format char
@@ -65,6 +81,16 @@ enum ArgFormat {
     HexUpper,
     // x for string, chr(x) for int
     Char,
+    // float, decimal notation
+    Float(usize),
+    // float, exponential notation, lowercase
+    ExpLower(usize),
+    // float, exponential notation, uppercase
+    ExpUpper(usize),
+    // float, shortest of decimal and exponential notation, lowercase
+    GeneralLower(usize),
+    // float, shortest of decimal and exponential notation, uppercase
+    GeneralUpper(usize),
     // `%` sign
     Percent,
 }
@@ -79,34 +105,34 @@ impl ArgFormat {
                 Some(v) => out.push_str(v),
             },
             ArgFormat::Repr => arg.collect_repr(out),
-            ArgFormat::Dec => write!(out, "{}", arg.to_int()?).unwrap(),
+            ArgFormat::Dec => write!(out, "{}", Self::unpack_dec(arg)?).unwrap(),
             ArgFormat::Oct => {
-                let v = arg.to_int()?;
+                let v = StarlarkBigInt::to_bigint(arg)?;
                 write!(
                     out,
                     "{}{:o}",
-                    if v < 0 { "-" } else { "" },
-                    v.wrapping_abs() as u64
+                    if v.is_negative() { "-" } else { "" },
+                    v.magnitude()
                 )
                 .unwrap();
             }
             ArgFormat::HexLower => {
-                let v = arg.to_int()?;
+                let v = StarlarkBigInt::to_bigint(arg)?;
                 write!(
                     out,
                     "{}{:x}",
-                    if v < 0 { "-" } else { "" },
-                    v.wrapping_abs() as u64
+                    if v.is_negative() { "-" } else { "" },
+                    v.magnitude()
                 )
                 .unwrap();
             }
             ArgFormat::HexUpper => {
-                let v = arg.to_int()?;
+                let v = StarlarkBigInt::to_bigint(arg)?;
                 write!(
                     out,
                     "{}{:X}",
-                    if v < 0 { "-" } else { "" },
-                    v.wrapping_abs() as u64
+                    if v.is_negative() { "-" } else { "" },
+                    v.magnitude()
                 )
                 .unwrap();
             }
@@ -129,12 +155,41 @@ impl ArgFormat {
                     }
                 }
             },
+            ArgFormat::Float(precision) => {
+                write_decimal(out, Self::unpack_number(arg, 'f')?, *precision, false)
+            }
+            ArgFormat::ExpLower(precision) => {
+                write_scientific(out, Self::unpack_number(arg, 'e')?, *precision, false)
+            }
+            ArgFormat::ExpUpper(precision) => {
+                write_scientific(out, Self::unpack_number(arg, 'E')?, *precision, true)
+            }
+            ArgFormat::GeneralLower(precision) => {
+                write_compact(out, Self::unpack_number(arg, 'g')?, *precision, false)
+            }
+            ArgFormat::GeneralUpper(precision) => {
+                write_compact(out, Self::unpack_number(arg, 'G')?, *precision, true)
+            }
             ArgFormat::Percent => {
                 out.push('%');
             }
         }
         Ok(())
     }
+
+    // Like Python, `%d` also takes a float, truncating it
+    fn unpack_dec(arg: Value) -> anyhow::Result<BigInt> {
+        match arg.downcast_ref::<StarlarkFloat>() {
+            Some(f) => BigInt::from_f64(f.0.trunc())
+                .ok_or_else(|| StringInterpolationError::ValueNotFinite('d').into()),
+            None => StarlarkBigInt::to_bigint(arg),
+        }
+    }
+
+    fn unpack_number(arg: Value, format_char: char) -> anyhow::Result<f64> {
+        StarlarkFloat::unpack_number(arg)
+            .ok_or_else(|| StringInterpolationError::ValueNotNumber(format_char).into())
+    }
 }
 
 // %(name)s or %s
@@ -204,7 +259,30 @@ impl Interpolation {
                 } else {
                     (NamedOrPositional::Positional, next)
                 };
+                let (precision, format_char) = if format_char == '.' {
+                    let mut digits = String::new();
+                    loop {
+                        match chars.next() {
+                            None => {
+                                return Err(StringInterpolationError::UnexpectedEOFPercent.into())
+                            }
+                            Some(c) if c.is_ascii_digit() => digits.push(c),
+                            Some(c) => break (Some(digits.parse().unwrap_or(0)), c),
+                        }
+                    }
+                } else {
+                    (None, format_char)
+                };
+                let float_precision = precision.unwrap_or(DEFAULT_PRECISION);
                 let format = match format_char {
+                    'f' => ArgFormat::Float(float_precision),
+                    'e' => ArgFormat::ExpLower(float_precision),
+                    'E' => ArgFormat::ExpUpper(float_precision),
+                    'g' => ArgFormat::GeneralLower(float_precision),
+                    'G' => ArgFormat::GeneralUpper(float_precision),
+                    c if precision.is_some() => {
+                        return Err(StringInterpolationError::PrecisionNotSupported(c).into());
+                    }
                     's' => ArgFormat::Str,
                     'r' => ArgFormat::Repr,
                     'd' | 'i' => ArgFormat::Dec,
@@ -36,15 +36,60 @@ use std::{
     fmt,
     fmt::{Debug, Formatter},
     hash::{Hash, Hasher},
+    mem,
     ops::Deref,
     ptr,
     sync::Arc,
     time::Instant,
 };
+use thiserror::Error;
 
 pub struct Heap {
     // Should really be ValueMem<'v>, where &'v self
     arena: RefCell<Arena<ValueMem<'static>>>,
+    // The values in the arena, and the memory they own outside it, as counted
+    // when they were allocated, or when they last survived a GC
+    objects: Cell<usize>,
+    extra_bytes: Cell<usize>,
+    // The usage before the last GC that reduced it
+    peak: Cell<HeapUsage>,
+    limit: Cell<HeapLimit>,
+}
+
+/// Limits on what a [`Heap`] may hold, see [`Heap::set_limit`].
+#[derive(Debug, Clone, Copy, Dupe, Default, PartialEq, Eq)]
+pub struct HeapLimit {
+    pub bytes: Option<usize>,
+    pub objects: Option<usize>,
+}
+
+/// What a [`Heap`] holds, see [`Heap::usage`].
+#[derive(Debug, Clone, Copy, Dupe, Default, PartialEq, Eq)]
+pub struct HeapUsage {
+    /// The memory of the heap itself, and an estimate of that owned by its
+    /// values, such as the characters of a string or the elements of a list.
+    pub bytes: usize,
+    /// The number of values, not counting those like `None` and small integers
+    /// which are not allocated.
+    pub objects: usize,
+}
+
+impl HeapUsage {
+    fn max(self, other: Self) -> Self {
+        Self {
+            bytes: self.bytes.max(other.bytes),
+            objects: self.objects.max(other.objects),
+        }
+    }
+}
+
+/// Errors from a [`Heap`] which is over its [`HeapLimit`].
+#[derive(Debug, Error)]
+pub enum HeapError {
+    #[error("Heap limit of {0} bytes exceeded")]
+    BytesExceeded(usize),
+    #[error("Heap limit of {0} values exceeded")]
+    ObjectsExceeded(usize),
 }
 
 impl Debug for Heap {
@@ -198,6 +243,62 @@ impl Heap {
     pub fn new() -> Self {
         Self {
             arena: RefCell::new(Arena::new()),
+            objects: Cell::new(0),
+            extra_bytes: Cell::new(0),
+            peak: Cell::new(HeapUsage::default()),
+            limit: Cell::new(HeapLimit::default()),
+        }
+    }
+
+    /// Limit what the heap may hold. Allocation can't fail, so instead
+    /// evaluation fails with a [`HeapError`] at the next statement or loop
+    /// iteration once the heap is over its limit. Operations likely to create
+    /// a large value, such as `[0] * 1000000000`, check they will fit first.
+    pub fn set_limit(&self, limit: HeapLimit) {
+        self.limit.set(limit)
+    }
+
+    /// The limit set by [`set_limit`](Heap::set_limit), if any.
+    pub fn limit(&self) -> HeapLimit {
+        self.limit.get()
+    }
+
+    /// What the heap holds now.
+    pub fn usage(&self) -> HeapUsage {
+        HeapUsage {
+            bytes: self.allocated_bytes() + self.extra_bytes.get(),
+            objects: self.objects.get(),
+        }
+    }
+
+    /// The most the heap has held at once. Usage only goes down when garbage
+    /// is collected, so this is the usage now, or before a GC.
+    pub fn peak_usage(&self) -> HeapUsage {
+        self.peak.get().max(self.usage())
+    }
+
+    /// Fail if the heap is over its limit.
+    pub fn check_limit(&self) -> anyhow::Result<()> {
+        self.check_reserve(0)
+    }
+
+    /// Fail if allocating a value owning roughly `bytes` of memory would put
+    /// the heap over its limit, so we can fail before doing it.
+    pub fn check_reserve(&self, bytes: usize) -> anyhow::Result<()> {
+        let limit = self.limit.get();
+        if limit == HeapLimit::default() {
+            return Ok(());
+        }
+        let usage = self.usage();
+        match limit.bytes {
+            Some(x) if usage.bytes.saturating_add(bytes) > x => {
+                return Err(HeapError::BytesExceeded(x).into());
+            }
+            _ => {}
+        }
+        match limit.objects {
+            Some(x) if usage.objects > x => Err(HeapError::ObjectsExceeded(x).into()),
+            _ => Ok(()),
         }
     }
 
@@ -226,14 +327,44 @@ impl Heap {
         // so we can make the `arena` available longer
         let arena = unsafe { transmute!(&Arena<ValueMem<'v>>, &'v Arena<ValueMem<'v>>, arena) };
 
+        self.objects.set(self.objects.get() + 1);
         Value(Pointer::new_ptr2(arena.alloc(v)))
     }
 
+    /// Fail if `count` more items of type `T`, being collected outside the
+    /// heap, would put the heap over its limit. Checked as a collection grows,
+    /// so building one from a huge iterable fails before exhausting memory.
+    pub(crate) fn check_reserve_items<T>(&self, count: usize) -> anyhow::Result<()> {
+        self.check_reserve(count.saturating_mul(mem::size_of::<T>()))
+    }
+
+    /// Collect items into a `Vec`, failing if it would put the heap over its
+    /// limit, see [`check_reserve_items`](Heap::check_reserve_items).
+    pub(crate) fn collect_limited<T>(
+        &self,
+        xs: impl IntoIterator<Item = T>,
+    ) -> anyhow::Result<Vec<T>> {
+        let mut res = Vec::new();
+        for x in xs {
+            self.check_reserve_items::<T>(res.len() + 1)?;
+            res.push(x);
+        }
+        Ok(res)
+    }
+
+    /// Count memory a value has taken since it was allocated, such as by
+    /// appending to a list, which would otherwise only be counted by the next GC.
+    pub(crate) fn add_extra_bytes(&self, bytes: usize) {
+        self.extra_bytes.set(self.extra_bytes.get() + bytes)
+    }
+
     pub(crate) fn alloc_str(&self, x: Box<str>) -> Value {
+        self.add_extra_bytes(x.len());
         self.alloc_raw(ValueMem::Str(x))
     }
 
     pub fn alloc_immutable<'v>(&'v self, x: impl ImmutableValue<'v> + 'v) -> Value<'v> {
+        self.add_extra_bytes(x.extra_memory());
         self.alloc_raw(ValueMem::Immutable(box x))
     }
 
@@ -250,6 +381,7 @@ impl Heap {
     }
 
     pub(crate) fn alloc_mutable_box<'v>(&'v self, x: Box<dyn MutableValue<'v> + 'v>) -> Value<'v> {
+        self.add_extra_bytes(x.extra_memory());
         if x.naturally_mutable() {
             self.alloc_raw(ValueMem::Mutable(RefCell::new(x)))
         } else {
@@ -293,18 +425,26 @@ impl Heap {
 
     fn garbage_collect_internal<'v>(&'v self, f: impl FnOnce(&Walker<'v>)) {
         // Must rewrite all Value's so they point at the new heap
+        self.peak.set(self.peak_usage());
         let mut arena = self.arena().borrow_mut();
 
         let walker = Walker::<'v> {
             arena: Arena::new(),
+            objects: Cell::new(0),
+            extra_bytes: Cell::new(0),
         };
         f(&walker);
         *arena = walker.arena;
+        self.objects.set(walker.objects.get());
+        self.extra_bytes.set(walker.extra_bytes.get());
     }
 }
 
 pub struct Walker<'v> {
     arena: Arena<ValueMem<'v>>,
+    // What survives, to recount the usage of the heap
+    objects: Cell<usize>,
+    extra_bytes: Cell<usize>,
 }
 
 impl<'v> Walker<'v> {
@@ -361,6 +501,15 @@ impl<'v> Walker<'v> {
         let new_val: Value<'v> = Value(Pointer::new_ptr2(new_mem));
         let mut old_mem = unsafe { ptr::replace(old_mem, ValueMem::Copied(new_val)) };
 
+        let extra_bytes = match &old_mem {
+            ValueMem::Str(x) => x.len(),
+            ValueMem::Immutable(x) => x.extra_memory(),
+            ValueMem::Mutable(x) => x.borrow().extra_memory(),
+            ValueMem::Pseudo(x) => x.extra_memory(),
+            _ => 0,
+        };
+        self.objects.set(self.objects.get() + 1);
+        self.extra_bytes.set(self.extra_bytes.get() + extra_bytes);
         match &mut old_mem {
             ValueMem::Ref(x) => self.walk_cell(x),
             ValueMem::Mutable(x) => x.borrow_mut().walk(self),
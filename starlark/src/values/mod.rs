@@ -174,9 +174,14 @@ pub trait ValueLike<'v>: Eq + Copy + Debug {
     }
 
     fn collect_repr(self, collector: &mut String) {
-        self.get_aref().collect_repr(collector);
+        // Shares the depth limit with `equals`, a cyclic or very deeply nested
+        // value is cut off with `...` rather than overflowing the stack.
+        match crate::eval::call_stack::try_inc() {
+            Ok(_guard) => self.get_aref().collect_repr(collector),
+            Err(_) => collector.push_str("..."),
+        }
     }
-    fn to_json(self) -> String {
+    fn to_json(self) -> anyhow::Result<String> {
         self.get_aref().to_json()
     }
 
@@ -330,12 +335,36 @@ impl<'v> Value<'v> {
         self.get_aref().percent(other, heap)
     }
 
+    pub fn div(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().div(other, heap)
+    }
+
     pub fn floor_div(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         self.get_aref().floor_div(other, heap)
     }
 
-    pub fn pipe(self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
-        self.get_aref().pipe(other)
+    pub fn bit_not(self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().bit_not(heap)
+    }
+
+    pub fn pipe(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().pipe(other, heap)
+    }
+
+    pub fn bit_and(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().bit_and(other, heap)
+    }
+
+    pub fn bit_xor(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().bit_xor(other, heap)
+    }
+
+    pub fn left_shift(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().left_shift(other, heap)
+    }
+
+    pub fn right_shift(self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.get_aref().right_shift(other, heap)
     }
 
     pub fn freeze(self, freezer: &Freezer) -> FrozenValue {
@@ -374,7 +403,12 @@ impl<'v> Value<'v> {
         alloc_value: Value<'v>,
         heap: &'v Heap,
     ) -> anyhow::Result<()> {
-        self.get_ref_mut(heap)?.set_at(index, alloc_value)
+        let mut me = self.get_ref_mut(heap)?;
+        // Assigning to a new key grows a dict outside the heap, so count it
+        let before = me.extra_memory();
+        me.set_at(index, alloc_value)?;
+        heap.add_extra_bytes(me.extra_memory().saturating_sub(before));
+        Ok(())
     }
 
     pub fn iterate(self, heap: &'v Heap) -> anyhow::Result<RefIterable<'v>> {
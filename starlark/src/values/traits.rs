@@ -130,6 +130,13 @@ pub trait TypedValue<'v>: 'v + AsTypedValue<'v> + Debug {
         false
     }
 
+    /// An estimate of the memory this value owns outside the heap, such as the
+    /// elements of a list, so it counts towards the limit set by
+    /// [`Heap::set_limit`](crate::values::Heap::set_limit).
+    fn extra_memory(&self) -> usize {
+        0
+    }
+
     // Do not implement this function, it's just syntax sugar over collect_repr
     fn to_repr(&self) -> String {
         let mut s = String::new();
@@ -147,8 +154,10 @@ pub trait TypedValue<'v>: 'v + AsTypedValue<'v> + Debug {
         write!(collector, "{:?}", self).unwrap()
     }
 
-    fn to_json(&self) -> String {
-        panic!("unsupported for type {}", self.get_type())
+    /// Convert self to JSON, failing if it is, or contains, a value JSON can't
+    /// represent.
+    fn to_json(&self) -> anyhow::Result<String> {
+        unsupported(self, "to_json()")
     }
 
     /// Convert self to a Boolean truth value, as returned by the bool()
@@ -361,6 +370,17 @@ pub trait TypedValue<'v>: 'v + AsTypedValue<'v> + Debug {
         unsupported(self, "-")
     }
 
+    /// Apply the `~` unary operator to the current value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("~1", "-2");
+    /// ```
+    fn bit_not(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported(self, "~")
+    }
+
     /// Add with the arguments the other way around. Should return None
     /// to fall through to normal add.
     fn radd(
@@ -429,6 +449,17 @@ pub trait TypedValue<'v>: 'v + AsTypedValue<'v> + Debug {
         unsupported_with(self, "%", other)
     }
 
+    /// Divide the current value by `other`. Always results in a float value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("7 / 2", "3.5");
+    /// ```
+    fn div(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported_with(self, "/", other)
+    }
+
     /// Floor division between the current value and `other`.
     ///
     /// # Examples
@@ -442,8 +473,62 @@ pub trait TypedValue<'v>: 'v + AsTypedValue<'v> + Debug {
 
     /// Apply the operator pipe to the current value and `other`.
     ///
-    /// This is usually the union on set.
-    fn pipe(&self, other: Value<'v>) -> anyhow::Result<Value<'v>> {
+    /// This is the bitwise or on int, and usually the union on set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("5 | 3", "7");
+    /// ```
+    fn pipe(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         unsupported_with(self, "|", other)
     }
+
+    /// Apply the operator `&` to the current value and `other`.
+    ///
+    /// This is the bitwise and on int, and usually the intersection on set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("5 & 3", "1");
+    /// ```
+    fn bit_and(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported_with(self, "&", other)
+    }
+
+    /// Apply the operator `^` to the current value and `other`.
+    ///
+    /// This is the bitwise xor on int, and usually the symmetric difference on set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("5 ^ 3", "6");
+    /// ```
+    fn bit_xor(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported_with(self, "^", other)
+    }
+
+    /// Shift the current value left by `other` bits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("1 << 3", "8");
+    /// ```
+    fn left_shift(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported_with(self, "<<", other)
+    }
+
+    /// Shift the current value right by `other` bits.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// starlark::assert::eq("8 >> 3", "1");
+    /// ```
+    fn right_shift(&self, other: Value<'v>, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        unsupported_with(self, ">>", other)
+    }
 }
@@ -0,0 +1,395 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Arbitrary-precision integers, used for `int` values which don't fit into `i32`.
+//!
+//! Small ints are stored tagged inside the pointer (see `PointerI32`), and are
+//! only promoted to a heap-allocated `StarlarkBigInt` when an operation overflows.
+//! A `StarlarkBigInt` never holds a value in the `i32` range, so equal ints
+//! always share the same representation.
+
+use crate::values::{
+    error::ValueError,
+    float::{
+        bigint_to_f64, compare_bigint_float, float_div, float_floor_div, float_percent,
+        StarlarkFloat,
+    },
+    int::INT_VALUE_TYPE_NAME,
+    unsupported_owned, unsupported_with, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue,
+    Heap, ImmutableValue, TypedValue, Value,
+};
+use gazebo::any::AnyLifetime;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_traits::{Signed, ToPrimitive, Zero};
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// An `int` outside of the `i32` range.
+#[derive(Clone, Debug, AnyLifetime)]
+pub struct StarlarkBigInt {
+    value: BigInt,
+}
+
+impl StarlarkBigInt {
+    pub fn get(&self) -> &BigInt {
+        &self.value
+    }
+
+    /// Unpack an `int` of any size, returns `None` if the value is not an `int`.
+    pub fn unpack_bigint(value: Value) -> Option<BigInt> {
+        match value.unpack_int() {
+            Some(i) => Some(BigInt::from(i)),
+            None => value
+                .downcast_ref::<StarlarkBigInt>()
+                .map(|x| x.value.clone()),
+        }
+    }
+
+    /// Like `Value::to_int`, but doesn't fail for ints outside of the `i32` range.
+    pub fn to_bigint(value: Value) -> anyhow::Result<BigInt> {
+        match Self::unpack_bigint(value) {
+            Some(x) => Ok(x),
+            None => Ok(BigInt::from(value.to_int()?)),
+        }
+    }
+
+    /// Is the value an `int`, either small or big.
+    pub fn is_int(value: Value) -> bool {
+        value.unpack_int().is_some() || value.downcast_ref::<StarlarkBigInt>().is_some()
+    }
+}
+
+impl<'v> AllocValue<'v> for BigInt {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        match self.to_i32() {
+            Some(i) => Value::new_int(i),
+            None => heap.alloc_immutable(StarlarkBigInt { value: self }),
+        }
+    }
+}
+
+impl<'v> AllocFrozenValue<'v> for BigInt {
+    fn alloc_frozen_value(self, heap: &'v FrozenHeap) -> FrozenValue {
+        match self.to_i32() {
+            Some(i) => FrozenValue::new_int(i),
+            None => heap.alloc_immutable(StarlarkBigInt { value: self }),
+        }
+    }
+}
+
+/// Apply a binary operation to a `BigInt` and a Starlark `int` of any size.
+/// If the right operand is a `float`, the `float` operation is used instead.
+pub(crate) fn bigint_bin_op<'v>(
+    left: &BigInt,
+    right: Value,
+    heap: &'v Heap,
+    op: &'static str,
+    f: impl FnOnce(&BigInt, &BigInt) -> anyhow::Result<BigInt>,
+    float: impl FnOnce(f64, f64) -> anyhow::Result<f64>,
+) -> anyhow::Result<Value<'v>> {
+    if let Some(right) = right.downcast_ref::<StarlarkFloat>() {
+        return Ok(heap.alloc(float(bigint_to_f64(left), right.0)?));
+    }
+    match StarlarkBigInt::unpack_bigint(right) {
+        Some(right) => Ok(heap.alloc(f(left, &right)?)),
+        None => unsupported_owned(INT_VALUE_TYPE_NAME, op, Some(right.get_type())),
+    }
+}
+
+/// Apply a bitwise operation to a `BigInt` and a Starlark `int` of any size.
+pub(crate) fn bigint_bitwise<'v>(
+    left: &BigInt,
+    right: Value,
+    heap: &'v Heap,
+    op: &'static str,
+    f: impl FnOnce(&BigInt, &BigInt) -> BigInt,
+) -> anyhow::Result<Value<'v>> {
+    match StarlarkBigInt::unpack_bigint(right) {
+        Some(right) => Ok(heap.alloc(f(left, &right))),
+        None => unsupported_owned(INT_VALUE_TYPE_NAME, op, Some(right.get_type())),
+    }
+}
+
+/// Left shifts are limited to this many bits, to avoid accidentally
+/// allocating huge numbers.
+const MAX_SHIFT: usize = 512;
+
+/// Get the shift count from the right operand of `<<` or `>>`.
+fn shift_count(left: &BigInt, right: Value, op: &'static str) -> anyhow::Result<Option<usize>> {
+    match StarlarkBigInt::unpack_bigint(right) {
+        None => unsupported_owned(INT_VALUE_TYPE_NAME, op, Some(right.get_type())),
+        Some(count) if count.is_negative() => Err(ValueError::NegativeShiftCount.into()),
+        // Shifting zero by any amount is still zero.
+        Some(_) if left.is_zero() => Ok(Some(0)),
+        Some(count) => Ok(count.to_usize()),
+    }
+}
+
+/// Starlark `<<`, fails if the shift count is negative or too large.
+pub(crate) fn bigint_left_shift<'v>(
+    left: &BigInt,
+    right: Value,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    match shift_count(left, right, "<<")? {
+        Some(count) if count <= MAX_SHIFT => Ok(heap.alloc(left << count)),
+        _ => Err(ValueError::ShiftCountTooLarge.into()),
+    }
+}
+
+/// Starlark `>>`, an arithmetic shift which rounds towards negative infinity.
+pub(crate) fn bigint_right_shift<'v>(
+    left: &BigInt,
+    right: Value,
+    heap: &'v Heap,
+) -> anyhow::Result<Value<'v>> {
+    match shift_count(left, right, ">>")? {
+        Some(count) if count < left.bits() as usize => Ok(heap.alloc(left >> count)),
+        // Everything has been shifted out, only the sign remains.
+        _ if left.is_negative() => Ok(Value::new_int(-1)),
+        _ => Ok(Value::new_int(0)),
+    }
+}
+
+/// Hash an int, consistent with the hash of an equal `float`.
+pub(crate) fn hash_bigint(x: &BigInt) -> u64 {
+    let mut s = DefaultHasher::new();
+    x.hash(&mut s);
+    s.finish()
+}
+
+/// Starlark `%`, the result takes the sign of the divisor.
+pub(crate) fn bigint_percent(a: &BigInt, b: &BigInt) -> anyhow::Result<BigInt> {
+    if b.is_zero() {
+        Err(ValueError::DivisionByZero.into())
+    } else {
+        Ok(a.mod_floor(b))
+    }
+}
+
+/// Starlark `//`, rounds towards negative infinity.
+pub(crate) fn bigint_floor_div(a: &BigInt, b: &BigInt) -> anyhow::Result<BigInt> {
+    if b.is_zero() {
+        Err(ValueError::DivisionByZero.into())
+    } else {
+        Ok(a.div_floor(b))
+    }
+}
+
+impl<'v> ImmutableValue<'v> for StarlarkBigInt {}
+
+impl<'v> TypedValue<'v> for StarlarkBigInt {
+    starlark_type!(INT_VALUE_TYPE_NAME);
+
+    fn equals(&self, other: Value) -> anyhow::Result<bool> {
+        // Small ints are never equal to a big int, since we always normalise.
+        if let Some(other) = other.downcast_ref::<StarlarkBigInt>() {
+            Ok(self.value == other.value)
+        } else if let Some(other) = other.downcast_ref::<StarlarkFloat>() {
+            Ok(compare_bigint_float(&self.value, other.0) == Ordering::Equal)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn collect_repr(&self, s: &mut String) {
+        s.push_str(&self.value.to_string());
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(self.value.to_string())
+    }
+    fn to_int(&self) -> anyhow::Result<i32> {
+        Err(ValueError::IntegerOverflow.into())
+    }
+    fn to_bool(&self) -> bool {
+        // Zero always fits into an `i32`
+        true
+    }
+    fn get_hash(&self) -> anyhow::Result<u64> {
+        Ok(hash_bigint(&self.value))
+    }
+    fn plus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(self.value.clone()))
+    }
+    fn minus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(-&self.value))
+    }
+    fn add(&self, _original: Value, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bin_op(
+            &self.value,
+            other,
+            heap,
+            "+",
+            |a, b| Ok(a + b),
+            |a, b| Ok(a + b),
+        )
+    }
+    fn sub(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bin_op(
+            &self.value,
+            other,
+            heap,
+            "-",
+            |a, b| Ok(a - b),
+            |a, b| Ok(a - b),
+        )
+    }
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bin_op(
+            &self.value,
+            other,
+            heap,
+            "*",
+            |a, b| Ok(a * b),
+            |a, b| Ok(a * b),
+        )
+    }
+    fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkFloat::unpack_number(other) {
+            Some(other) => Ok(heap.alloc(float_div(bigint_to_f64(&self.value), other)?)),
+            None => unsupported_with(self, "/", other),
+        }
+    }
+    fn percent(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bin_op(&self.value, other, heap, "%", bigint_percent, float_percent)
+    }
+    fn floor_div(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bin_op(
+            &self.value,
+            other,
+            heap,
+            "//",
+            bigint_floor_div,
+            float_floor_div,
+        )
+    }
+
+    fn bit_not(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(!&self.value))
+    }
+    fn pipe(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bitwise(&self.value, other, heap, "|", |a, b| a | b)
+    }
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bitwise(&self.value, other, heap, "&", |a, b| a & b)
+    }
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_bitwise(&self.value, other, heap, "^", |a, b| a ^ b)
+    }
+    fn left_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_left_shift(&self.value, other, heap)
+    }
+    fn right_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        bigint_right_shift(&self.value, other, heap)
+    }
+
+    fn compare(&self, _ptr_eq: bool, other: Value) -> anyhow::Result<Ordering> {
+        if let Some(other) = other.downcast_ref::<StarlarkFloat>() {
+            return Ok(compare_bigint_float(&self.value, other.0));
+        }
+        match StarlarkBigInt::unpack_bigint(other) {
+            Some(other) => Ok(self.value.cmp(&other)),
+            None => unsupported_with(self, "==", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_bigint_arithmetic() {
+        assert::all_true(
+            r#"
+2147483647 + 1 == 2147483648
+-2147483648 - 1 == -2147483649
+2147483648 - 1 == 2147483647
+type(2147483647 + 1) == "int"
+65536 * 65536 == 4294967296
+4294967296 // 65536 == 65536
+-4294967297 // 65536 == -65537
+4294967297 % 65536 == 1
+-4294967297 % 65536 == 65535
+4294967297 % -65536 == -65535
+-(-2147483648) == 2147483648
+-2147483648 // -1 == 2147483648
+100000000000000000000 * 100000000000000000000 == 10000000000000000000000000000000000000000
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bigint_compare() {
+        assert::all_true(
+            r#"
+2147483648 > 2147483647
+-2147483649 < -2147483648
+4294967296 == 65536 * 65536
+4294967296 != 4294967297
+sorted([4294967296, 1, -4294967296]) == [-4294967296, 1, 4294967296]
+{4294967296: 1}[65536 * 65536] == 1
+hash(4294967296) == hash(65536 * 65536)
+(4294967296 - 4294967295) == 1
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bigint_conversions() {
+        assert::all_true(
+            r#"
+int("4294967296") == 4294967296
+int("-4294967296") == -4294967296
+int("0x100000000", 0) == 4294967296
+int(4294967296) == 4294967296
+str(4294967296) == "4294967296"
+repr(-4294967296) == "-4294967296"
+"%d" % 4294967296 == "4294967296"
+"%x" % 4294967296 == "100000000"
+"%o" % -4294967296 == "-40000000000"
+struct(x = 4294967296).to_json() == "{x: 4294967296}"
+"#,
+        );
+        assert::fail("1 // (4294967296 - 4294967296)", "Cannot divide by zero");
+        assert::fail("4294967296 % 0", "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_bigint_bitwise() {
+        assert::all_true(
+            r#"
+~4294967296 == -4294967297
+4294967296 | 1 == 4294967297
+4294967297 & 4294967296 == 4294967296
+4294967297 ^ 1 == 4294967296
+1 << 40 == 1099511627776
+1099511627776 >> 40 == 1
+-1099511627776 >> 100 == -1
+1099511627776 >> 100 == 0
+0 << 100000 == 0
+"#,
+        );
+        assert::fail("1 << -1", "Negative shift count");
+        assert::fail("1 << 1000", "Shift count too large");
+    }
+}
@@ -49,8 +49,8 @@ impl TypedValue<'_> for bool {
             s.push_str("False")
         }
     }
-    fn to_json(&self) -> String {
-        self.to_repr()
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(self.to_repr())
     }
     fn to_int(&self) -> anyhow::Result<i32> {
         Ok(if *self { 1 } else { 0 })
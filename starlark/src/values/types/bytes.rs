@@ -0,0 +1,256 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The `bytes` type, an immutable sequence of bytes (`b"abc"`).
+
+use crate::{
+    environment::{Globals, GlobalsStatic},
+    stdlib::UnpackValue,
+    values::{
+        index::{convert_index, convert_slice_indices},
+        unsupported_with, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap,
+        ImmutableValue, TypedValue, Value, ValueError,
+    },
+};
+use gazebo::{any::AnyLifetime, cell::ARef};
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    fmt::Write,
+    hash::{Hash, Hasher},
+};
+
+/// Representation of the `bytes` type.
+#[derive(Clone, Debug, PartialEq, Eq, AnyLifetime)]
+pub struct StarlarkBytes(pub Vec<u8>);
+
+impl StarlarkBytes {
+    pub const TYPE: &'static str = "bytes";
+
+    pub fn from_value<'v>(x: Value<'v>) -> Option<ARef<'v, StarlarkBytes>> {
+        x.downcast_ref::<StarlarkBytes>()
+    }
+
+    /// Decode the bytes as UTF-8, replacing invalid sequences with `U+FFFD`.
+    pub fn decode(&self) -> String {
+        String::from_utf8_lossy(&self.0).into_owned()
+    }
+}
+
+/// Write `x` as a bytes literal. Printable ASCII is shown as text, any other
+/// byte as a `\x` escape, so the literal reads back as the same bytes.
+pub(crate) fn collect_bytes_repr(x: &[u8], r: &mut String) {
+    r.push_str("b\"");
+    for &b in x {
+        match b {
+            b'\n' => r.push_str("\\n"),
+            b'\r' => r.push_str("\\r"),
+            b'\t' => r.push_str("\\t"),
+            b'"' | b'\\' => {
+                r.push('\\');
+                r.push(b as char);
+            }
+            0x20..=0x7e => r.push(b as char),
+            _ => write!(r, "\\x{:02x}", b).unwrap(),
+        }
+    }
+    r.push('"');
+}
+
+impl<'v> AllocValue<'v> for StarlarkBytes {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_immutable(self)
+    }
+}
+
+impl<'v> AllocFrozenValue<'v> for StarlarkBytes {
+    fn alloc_frozen_value(self, heap: &'v FrozenHeap) -> FrozenValue {
+        heap.alloc_immutable(self)
+    }
+}
+
+impl<'v> UnpackValue<'v> for ARef<'v, StarlarkBytes> {
+    fn unpack_value(value: Value<'v>, _heap: &'v Heap) -> Option<Self> {
+        StarlarkBytes::from_value(value)
+    }
+}
+
+impl<'v> ImmutableValue<'v> for StarlarkBytes {}
+
+/// Define the bytes type
+impl<'v> TypedValue<'v> for StarlarkBytes {
+    starlark_type!(StarlarkBytes::TYPE);
+
+    fn extra_memory(&self) -> usize {
+        self.0.len()
+    }
+
+    fn get_members(&self) -> Option<&'static Globals> {
+        static RES: GlobalsStatic = GlobalsStatic::new();
+        RES.members(crate::stdlib::bytes::bytes_members)
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        collect_bytes_repr(&self.0, r)
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(format!(
+            "[{}]",
+            self.0
+                .iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    fn get_hash(&self) -> anyhow::Result<u64> {
+        let mut s = DefaultHasher::new();
+        self.0.hash(&mut s);
+        Ok(s.finish())
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self.0 == other.0),
+            None => Ok(false),
+        }
+    }
+
+    fn compare(&self, _ptr_eq: bool, other: Value<'v>) -> anyhow::Result<Ordering> {
+        match StarlarkBytes::from_value(other) {
+            Some(other) => Ok(self.0.cmp(&other.0)),
+            None => unsupported_with(self, "cmp()", other),
+        }
+    }
+
+    fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = convert_index(index, self.0.len() as i32)?;
+        Ok(Value::new_int(self.0[i as usize] as i32))
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.0.len() as i32)
+    }
+
+    /// `x in b` accepts either a byte value as an `int`, or a `bytes` substring.
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        if let Some(i) = other.unpack_int() {
+            if !(0..256).contains(&i) {
+                return Err(ValueError::IncorrectParameterType.into());
+            }
+            Ok(self.0.contains(&(i as u8)))
+        } else if let Some(needle) = StarlarkBytes::from_value(other) {
+            Ok(needle.0.is_empty() || self.0.windows(needle.0.len()).any(|w| w == &*needle.0))
+        } else {
+            Err(ValueError::IncorrectParameterType.into())
+        }
+    }
+
+    fn slice(
+        &self,
+        start: Option<Value>,
+        stop: Option<Value>,
+        stride: Option<Value>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        let (start, stop, stride) =
+            convert_slice_indices(self.0.len() as i32, start, stop, stride)?;
+        let mut res = Vec::new();
+        let mut i = start;
+        while (stride > 0 && i < stop) || (stride < 0 && i > stop) {
+            res.push(self.0[i as usize]);
+            i += stride;
+        }
+        Ok(heap.alloc(StarlarkBytes(res)))
+    }
+
+    fn add(
+        &self,
+        original: Value<'v>,
+        other: Value<'v>,
+        heap: &'v Heap,
+    ) -> anyhow::Result<Value<'v>> {
+        match StarlarkBytes::from_value(other) {
+            Some(x) if x.0.is_empty() => Ok(original),
+            Some(x) => {
+                let mut res = Vec::with_capacity(self.0.len() + x.0.len());
+                res.extend_from_slice(&self.0);
+                res.extend_from_slice(&x.0);
+                Ok(heap.alloc(StarlarkBytes(res)))
+            }
+            None => unsupported_with(self, "+", other),
+        }
+    }
+
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match other.unpack_int() {
+            Some(n) => {
+                heap.check_reserve(self.0.len().saturating_mul(n.max(0) as usize))?;
+                Ok(heap.alloc(StarlarkBytes(self.0.repeat(n.max(0) as usize))))
+            }
+            None => unsupported_with(self, "*", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::collect_bytes_repr;
+    use crate::assert;
+
+    #[test]
+    fn test_bytes_operators() {
+        assert::all_true(
+            r#"
+len(b"abc") == 3
+b"abc"[0] == 97
+b"abc"[-1] == 99
+b"abcdef"[1:4] == b"bcd"
+b"abcdef"[::-2] == b"fdb"
+b"ab" + b"cd" == b"abcd"
+b"ab" * 2 == b"abab"
+b"ab" < b"b"
+b"ab" != "ab"
+98 in b"abc"
+b"bc" in b"abc"
+b"" in b"abc"
+not b""
+type(b"") == "bytes"
+repr(b"a\x00\xff\"") == 'b"a\\x00\\xff\\""'
+repr(b"\xe4\xb8\x96") == 'b"\\xe4\\xb8\\x96"'
+repr(b"\x7f\n\\") == 'b"\\x7f\\n\\\\"'
+"#,
+        );
+        assert::fail("b'abc'[3]", "out of bound");
+        assert::fail("b'abc' + 'def'", "not supported");
+        assert::fail("256 in b'abc'", "Type of parameters mismatch");
+    }
+
+    #[test]
+    fn test_bytes_repr_round_trip() {
+        let all: Vec<u8> = (0..=255).collect();
+        let mut literal = String::new();
+        collect_bytes_repr(&all, &mut literal);
+        assert::is_true(&format!("{} == bytes(range(256))", literal));
+    }
+}
@@ -27,7 +27,10 @@ use crate::{
 };
 use gazebo::{any::AnyLifetime, cell::ARef, prelude::*};
 use indexmap::Equivalent;
-use std::hash::{Hash, Hasher};
+use std::{
+    hash::{Hash, Hasher},
+    mem,
+};
 
 /// Define the Dict type
 #[derive(Clone, Default_, Debug)]
@@ -169,6 +172,23 @@ impl<'v> ImmutableValue<'v> for FrozenDict {
     }
 }
 
+impl<'v, T: ValueLike<'v>> DictGen<T> {
+    /// Merge `other` into a copy of this dictionary, or `None` if `other` is not a dictionary.
+    fn union(&self, other: Value<'v>, heap: &'v Heap) -> Option<Value<'v>> {
+        let other = Dict::from_value(other)?;
+        let mut result = Dict::default();
+        for (k, v) in self.content.iter_hashed() {
+            result
+                .content
+                .insert_hashed(k.unborrow_copy().to_hashed_value(), v.to_value());
+        }
+        for (k, v) in other.iter_hashed() {
+            result.content.insert_hashed(k, v);
+        }
+        Some(heap.alloc(result))
+    }
+}
+
 impl<'v, T: ValueLike<'v>> TypedValue<'v> for DictGen<T>
 where
     Value<'v>: Equivalent<T>,
@@ -181,6 +201,10 @@ where
         true
     }
 
+    fn extra_memory(&self) -> usize {
+        self.content.len() * mem::size_of::<(T, T)>()
+    }
+
     fn get_members(&self) -> Option<&'static Globals> {
         static RES: GlobalsStatic = GlobalsStatic::new();
         RES.members(crate::stdlib::dict::dict_members)
@@ -199,19 +223,15 @@ where
         r.push('}');
     }
 
-    fn to_json(&self) -> String {
-        format!(
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(format!(
             "{{{}}}",
             self.content
                 .iter()
-                .map(|(k, v)| format!("{}: {}", k.to_json(), v.to_json()))
-                .enumerate()
-                .fold("".to_string(), |accum, s| if s.0 == 0 {
-                    accum + &s.1
-                } else {
-                    accum + ", " + &s.1
-                })
-        )
+                .map(|(k, v)| Ok(format!("{}: {}", k.to_json()?, v.to_json()?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ))
     }
 
     fn to_bool(&self) -> bool {
@@ -264,19 +284,17 @@ where
         other: Value<'v>,
         heap: &'v Heap,
     ) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = Dict::from_value(other) {
-            let mut result = Dict::default();
-            for (k, v) in self.content.iter_hashed() {
-                result
-                    .content
-                    .insert_hashed(k.unborrow_copy().to_hashed_value(), v.to_value());
-            }
-            for (k, v) in other.iter_hashed() {
-                result.content.insert_hashed(k, v);
-            }
-            return Ok(heap.alloc(result));
+        match self.union(other, heap) {
+            Some(result) => Ok(result),
+            None => unsupported_with(self, "+", other),
+        }
+    }
+
+    fn pipe(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self.union(other, heap) {
+            Some(result) => Ok(result),
+            None => unsupported_with(self, "|", other),
         }
-        unsupported_with(self, "+", other)
     }
 }
 
@@ -309,6 +327,24 @@ b1 and b2 and b3
         );
     }
 
+    #[test]
+    fn test_dict_union() {
+        assert::all_true(
+            r#"
+{1: 2, 3: 4} | {3: 5, 6: 7} == {1: 2, 3: 5, 6: 7}
+{} | {} == {}
+"#,
+        );
+        assert::is_true(
+            r#"
+x = {1: 2}
+x |= {3: 4}
+x == {1: 2, 3: 4}
+"#,
+        );
+        assert::fail("{1: 2} | [3]", "not supported");
+    }
+
     #[test]
     fn test_get_str() -> anyhow::Result<()> {
         let heap = Heap::new();
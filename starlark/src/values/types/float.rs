@@ -0,0 +1,434 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The floating point number type (`3.14`, `1e9`).
+
+use crate::values::{
+    bigint::{hash_bigint, StarlarkBigInt},
+    unsupported_with, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, ImmutableValue,
+    TypedValue, Value, ValueError,
+};
+use gazebo::any::AnyLifetime;
+use num_bigint::BigInt;
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{cmp::Ordering, fmt::Write};
+
+/// Representation of the `float` type.
+#[derive(Clone, Copy, Debug, AnyLifetime)]
+pub struct StarlarkFloat(pub f64);
+
+impl StarlarkFloat {
+    pub const TYPE: &'static str = "float";
+
+    /// Unpack a `float` or an `int` of any size as `f64`.
+    pub fn unpack_number(value: Value) -> Option<f64> {
+        if let Some(f) = value.downcast_ref::<StarlarkFloat>() {
+            Some(f.0)
+        } else if let Some(i) = value.unpack_int() {
+            Some(i as f64)
+        } else {
+            value
+                .downcast_ref::<StarlarkBigInt>()
+                .map(|x| bigint_to_f64(x.get()))
+        }
+    }
+
+    /// Compare with an `int` or a `float`, returns `None` if `other` is not a number.
+    fn compare_number(self, other: Value) -> Option<Ordering> {
+        if let Some(other) = other.downcast_ref::<StarlarkFloat>() {
+            Some(float_cmp(self.0, other.0))
+        } else if let Some(other) = other.unpack_int() {
+            Some(float_cmp(self.0, other as f64))
+        } else {
+            other
+                .downcast_ref::<StarlarkBigInt>()
+                .map(|other| compare_bigint_float(other.get(), self.0).reverse())
+        }
+    }
+
+    fn bin_op<'v>(
+        self,
+        other: Value,
+        heap: &'v Heap,
+        op: &'static str,
+        f: impl FnOnce(f64, f64) -> anyhow::Result<f64>,
+    ) -> anyhow::Result<Value<'v>> {
+        match Self::unpack_number(other) {
+            Some(other) => Ok(heap.alloc(f(self.0, other)?)),
+            None => unsupported_with(&self, op, other),
+        }
+    }
+}
+
+pub(crate) fn bigint_to_f64(x: &BigInt) -> f64 {
+    // Conversion from `BigInt` saturates to infinity, so never fails
+    x.to_f64().unwrap()
+}
+
+/// Floats are totally ordered, with `NaN` greater than all other values
+/// (including `+inf`) and equal to itself.
+pub(crate) fn float_cmp(a: f64, b: f64) -> Ordering {
+    match a.partial_cmp(&b) {
+        Some(x) => x,
+        None => a.is_nan().cmp(&b.is_nan()),
+    }
+}
+
+/// Compare an int and a float exactly, without losing precision on the int.
+pub(crate) fn compare_bigint_float(a: &BigInt, b: f64) -> Ordering {
+    if b.is_nan() {
+        Ordering::Less
+    } else if b.is_infinite() {
+        if b > 0.0 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        }
+    } else {
+        let floor = b.floor();
+        match a.cmp(&BigInt::from_f64(floor).unwrap()) {
+            Ordering::Equal if b > floor => Ordering::Less,
+            x => x,
+        }
+    }
+}
+
+/// Starlark `/`.
+pub(crate) fn float_div(a: f64, b: f64) -> anyhow::Result<f64> {
+    if b == 0.0 {
+        Err(ValueError::DivisionByZero.into())
+    } else {
+        Ok(a / b)
+    }
+}
+
+/// Starlark `%`, the result takes the sign of the divisor.
+pub(crate) fn float_percent(a: f64, b: f64) -> anyhow::Result<f64> {
+    if b == 0.0 {
+        return Err(ValueError::DivisionByZero.into());
+    }
+    let r = a % b;
+    if r != 0.0 && (r < 0.0) != (b < 0.0) {
+        Ok(r + b)
+    } else {
+        Ok(r)
+    }
+}
+
+/// Starlark `//`, rounds towards negative infinity.
+pub(crate) fn float_floor_div(a: f64, b: f64) -> anyhow::Result<f64> {
+    if b == 0.0 {
+        Err(ValueError::DivisionByZero.into())
+    } else {
+        Ok((a / b).floor())
+    }
+}
+
+fn write_non_finite(out: &mut String, f: f64, uppercase: bool) {
+    let s = if f.is_nan() {
+        "nan"
+    } else if f > 0.0 {
+        "inf"
+    } else {
+        "-inf"
+    };
+    if uppercase {
+        out.push_str(&s.to_uppercase())
+    } else {
+        out.push_str(s)
+    }
+}
+
+/// Append the exponent of Rust `{:e}` output in the `e+XX` form used by Python.
+fn write_exponent(out: &mut String, s: &str, uppercase: bool) {
+    let e = s.find('e').unwrap();
+    let exp: i32 = s[e + 1..].parse().unwrap();
+    out.push_str(&s[..e]);
+    out.push(if uppercase { 'E' } else { 'e' });
+    write!(out, "{}{:02}", if exp < 0 { '-' } else { '+' }, exp.abs()).unwrap();
+}
+
+/// Format a float in `%f` notation, e.g. `1.500000`.
+pub fn write_decimal(out: &mut String, f: f64, precision: usize, uppercase: bool) {
+    if !f.is_finite() {
+        write_non_finite(out, f, uppercase)
+    } else {
+        write!(out, "{:.*}", precision, f).unwrap()
+    }
+}
+
+/// Format a float in `%e` notation, e.g. `1.500000e+00`.
+pub fn write_scientific(out: &mut String, f: f64, precision: usize, uppercase: bool) {
+    if !f.is_finite() {
+        write_non_finite(out, f, uppercase)
+    } else {
+        write_exponent(out, &format!("{:.*e}", precision, f), uppercase)
+    }
+}
+
+/// Format a float in `%g` notation: the shorter of `%f` and `%e`,
+/// without trailing zeros.
+pub fn write_compact(out: &mut String, f: f64, precision: usize, uppercase: bool) {
+    if !f.is_finite() {
+        return write_non_finite(out, f, uppercase);
+    }
+    fn trim_zeros(s: &str) -> &str {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.')
+        } else {
+            s
+        }
+    }
+
+    let precision = precision.max(1);
+    let sci = format!("{:.*e}", precision - 1, f);
+    let exp: i32 = sci[sci.find('e').unwrap() + 1..].parse().unwrap();
+    if exp >= -4 && exp < precision as i32 {
+        let s = format!("{:.*}", (precision as i32 - 1 - exp) as usize, f);
+        out.push_str(trim_zeros(&s));
+    } else {
+        let e = sci.find('e').unwrap();
+        let s = format!("{}{}", trim_zeros(&sci[..e]), &sci[e..]);
+        write_exponent(out, &s, uppercase);
+    }
+}
+
+impl<'v> AllocValue<'v> for f64 {
+    fn alloc_value(self, heap: &'v Heap) -> Value<'v> {
+        heap.alloc_immutable(StarlarkFloat(self))
+    }
+}
+
+impl<'v> AllocFrozenValue<'v> for f64 {
+    fn alloc_frozen_value(self, heap: &'v FrozenHeap) -> FrozenValue {
+        heap.alloc_immutable(StarlarkFloat(self))
+    }
+}
+
+impl<'v> ImmutableValue<'v> for StarlarkFloat {}
+
+/// Define the float type
+impl<'v> TypedValue<'v> for StarlarkFloat {
+    starlark_type!(StarlarkFloat::TYPE);
+
+    fn equals(&self, other: Value) -> anyhow::Result<bool> {
+        Ok(self.compare_number(other) == Some(Ordering::Equal))
+    }
+
+    fn collect_repr(&self, s: &mut String) {
+        let f = self.0;
+        if !f.is_finite() {
+            if f > 0.0 {
+                s.push('+');
+            }
+            return write_non_finite(s, f, false);
+        }
+        // Same as Python, use exponential notation outside of `1e-4 <= abs(x) < 1e16`.
+        let sci = format!("{:e}", f);
+        let exp: i32 = sci[sci.find('e').unwrap() + 1..].parse().unwrap();
+        if f == 0.0 || (exp >= -4 && exp < 16) {
+            let start = s.len();
+            write!(s, "{}", f).unwrap();
+            if !s[start..].contains('.') {
+                s.push_str(".0");
+            }
+        } else {
+            write_exponent(s, &sci, false);
+        }
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        // JSON has no way to write NaN or the infinities
+        if self.0.is_finite() {
+            Ok(self.to_repr())
+        } else {
+            Err(ValueError::NotJson(self.to_repr()).into())
+        }
+    }
+
+    fn to_bool(&self) -> bool {
+        self.0 != 0.0
+    }
+
+    fn get_hash(&self) -> anyhow::Result<u64> {
+        let f = self.0;
+        // Floats with an integer value must hash the same as the equal int.
+        if f.is_finite() && f.fract() == 0.0 {
+            if f >= i32::MIN as f64 && f <= i32::MAX as f64 {
+                return Ok((f as i32) as u64);
+            }
+            return Ok(hash_bigint(&BigInt::from_f64(f).unwrap()));
+        }
+        if f.is_nan() {
+            Ok(f64::NAN.to_bits())
+        } else {
+            Ok(f.to_bits())
+        }
+    }
+
+    fn plus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(self.0))
+    }
+    fn minus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(heap.alloc(-self.0))
+    }
+    fn add(&self, _original: Value, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "+", |a, b| Ok(a + b))
+    }
+    fn sub(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "-", |a, b| Ok(a - b))
+    }
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "*", |a, b| Ok(a * b))
+    }
+    fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "/", float_div)
+    }
+    fn percent(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "%", float_percent)
+    }
+    fn floor_div(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        self.bin_op(other, heap, "//", float_floor_div)
+    }
+
+    fn compare(&self, _ptr_eq: bool, other: Value) -> anyhow::Result<Ordering> {
+        match self.compare_number(other) {
+            Some(x) => Ok(x),
+            None => unsupported_with(self, "==", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert;
+
+    fn compact(f: f64) -> String {
+        let mut s = String::new();
+        write_compact(&mut s, f, 6, false);
+        s
+    }
+
+    #[test]
+    fn test_write_compact() {
+        assert_eq!(compact(0.0), "0");
+        assert_eq!(compact(1.5), "1.5");
+        assert_eq!(compact(100000.0), "100000");
+        assert_eq!(compact(1000000.0), "1e+06");
+        assert_eq!(compact(0.0001), "0.0001");
+        assert_eq!(compact(0.00001), "1e-05");
+        assert_eq!(compact(123456789.0), "1.23457e+08");
+    }
+
+    #[test]
+    fn test_float_arithmetic() {
+        assert::all_true(
+            r#"
+1.5 + 1.5 == 3.0
+1 + 0.5 == 1.5
+0.5 + 1 == 1.5
+3 - 0.5 == 2.5
+2 * 0.25 == 0.5
+7 / 2 == 3.5
+6 / 2 == 3.0
+type(6 / 2) == "float"
+7.0 // 2 == 3.0
+-7.0 // 2 == -4.0
+7.5 % 2 == 1.5
+-7.5 % 2 == 0.5
+7.5 % -2 == -0.5
+-(1.5) == -1.5
+4294967296 * 0.5 == 2147483648
+"#,
+        );
+        assert::fail("1 / 0", "Cannot divide by zero");
+        assert::fail("1.0 // 0.0", "Cannot divide by zero");
+        assert::fail("1.0 % 0", "Cannot divide by zero");
+    }
+
+    #[test]
+    fn test_float_compare() {
+        assert::all_true(
+            r#"
+1 == 1.0
+1.0 == 1
+1.5 != 1
+1 < 1.5
+2 > 1.5
+4294967296 == 4294967296.0
+9007199254740993 > 9007199254740992.0
+sorted([3, 1.5, -1, float("inf"), float("-inf")]) == [float("-inf"), -1, 1.5, 3, float("inf")]
+float("nan") > float("inf")
+{1: "a"}[1.0] == "a"
+hash(1.0) == hash(1)
+hash(4294967296.0) == hash(4294967296)
+len(dict([(1, 1), (1.0, 2), (2.5, 3)])) == 2
+"#,
+        );
+    }
+
+    #[test]
+    fn test_float_repr() {
+        assert::all_true(
+            r#"
+repr(1.0) == "1.0"
+str(0.5) == "0.5"
+str(-1.5) == "-1.5"
+str(1e20) == "1e+20"
+str(1.5e-7) == "1.5e-07"
+str(1e15) == "1000000000000000.0"
+str(123.456) == "123.456"
+str(float("inf")) == "+inf"
+str(float("-inf")) == "-inf"
+str(float("nan")) == "nan"
+struct(x = 0.5).to_json() == "{x: 0.5}"
+"#,
+        );
+    }
+
+    #[test]
+    fn test_float_json() {
+        assert::all_true(
+            r#"
+struct(x = 1e20, y = [-0.5]).to_json() == "{x: 1e+20, y: [-0.5]}"
+"#,
+        );
+        assert::fail(
+            "struct(x = float('nan')).to_json()",
+            "Value `nan` can't be represented in JSON",
+        );
+        assert::fail(
+            "struct(x = {'a': float('-inf')}).to_json()",
+            "Value `-inf` can't be represented in JSON",
+        );
+    }
+
+    #[test]
+    fn test_float_interpolation() {
+        assert::all_true(
+            r#"
+"%d" % 3.9 == "3"
+"%i" % -3.9 == "-3"
+"%d" % 1e20 == "100000000000000000000"
+"%d" % -0.5 == "0"
+"#,
+        );
+        assert::fail("'%d' % float('inf')", "requires a finite number");
+        assert::fail("'%x' % 1.5", "not supported");
+    }
+}
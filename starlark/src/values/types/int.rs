@@ -18,10 +18,17 @@
 //! Define the int type for Starlark.
 
 use crate::values::{
-    error::ValueError, layout::PointerI32, unsupported_owned, unsupported_with, AllocFrozenValue,
-    AllocValue, FrozenHeap, FrozenValue, Heap, TypedValue, Value,
+    bigint::{
+        bigint_bin_op, bigint_bitwise, bigint_floor_div, bigint_left_shift, bigint_percent,
+        bigint_right_shift, StarlarkBigInt,
+    },
+    float::{float_cmp, float_div, float_floor_div, float_percent, StarlarkFloat},
+    layout::PointerI32,
+    unsupported_with, AllocFrozenValue, AllocValue, FrozenHeap, FrozenValue, Heap, TypedValue,
+    Value, ValueError,
 };
-use std::cmp::Ordering;
+use num_bigint::BigInt;
+use std::{cmp::Ordering, convert::TryFrom};
 
 // We'd love to put this on a type, but we use i32 directly
 pub const INT_VALUE_TYPE_NAME: &str = "int";
@@ -37,18 +44,41 @@ impl<'v> AllocFrozenValue<'v> for i32 {
     }
 }
 
-fn i64_arith_bin_op<'v, F>(
+/// Apply a binary operation to a small int and a number.
+/// The `small` operation returns `None` on overflow, in which case
+/// we fall back to the `big` operation. If the right operand is a `float`,
+/// the `float` operation is used.
+fn int_bin_op<'v>(
     left: i32,
     right: Value,
+    heap: &'v Heap,
     op: &'static str,
-    f: F,
-) -> anyhow::Result<Value<'v>>
-where
-    F: FnOnce(i32, i32) -> anyhow::Result<i32>,
-{
+    small: impl FnOnce(i32, i32) -> anyhow::Result<Option<i32>>,
+    big: impl FnOnce(&BigInt, &BigInt) -> anyhow::Result<BigInt>,
+    float: impl FnOnce(f64, f64) -> anyhow::Result<f64>,
+) -> anyhow::Result<Value<'v>> {
+    if let Some(right) = right.unpack_int() {
+        if let Some(res) = small(left, right)? {
+            return Ok(Value::new_int(res));
+        }
+    } else if let Some(right) = right.downcast_ref::<StarlarkFloat>() {
+        return Ok(heap.alloc(float(left as f64, right.0)?));
+    }
+    bigint_bin_op(&BigInt::from(left), right, heap, op, big, float)
+}
+
+/// Apply a bitwise operation to a small int and an `int` of any size.
+fn int_bitwise<'v>(
+    left: i32,
+    right: Value,
+    heap: &'v Heap,
+    op: &'static str,
+    small: impl FnOnce(i32, i32) -> i32,
+    big: impl FnOnce(&BigInt, &BigInt) -> BigInt,
+) -> anyhow::Result<Value<'v>> {
     match right.unpack_int() {
-        Some(right) => Ok(Value::new_int(f(left, right)?)),
-        None => unsupported_owned(INT_VALUE_TYPE_NAME, op, Some(INT_VALUE_TYPE_NAME)),
+        Some(right) => Ok(Value::new_int(small(left, right))),
+        None => bigint_bitwise(&BigInt::from(left), right, heap, op, big),
     }
 }
 
@@ -59,6 +89,8 @@ impl<'v> TypedValue<'v> for PointerI32 {
     fn equals(&self, other: Value) -> anyhow::Result<bool> {
         if let Some(other) = other.unpack_int() {
             Ok(self.get() == other)
+        } else if let Some(other) = other.downcast_ref::<StarlarkFloat>() {
+            Ok(self.get() as f64 == other.0)
         } else {
             Ok(false)
         }
@@ -68,8 +100,8 @@ impl<'v> TypedValue<'v> for PointerI32 {
         s.push_str(&self.get().to_string());
     }
 
-    fn to_json(&self) -> String {
-        self.get().to_string()
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(self.get().to_string())
     }
     fn to_int(&self) -> anyhow::Result<i32> {
         Ok(self.get())
@@ -83,76 +115,135 @@ impl<'v> TypedValue<'v> for PointerI32 {
     fn plus(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         Ok(Value::new_int(self.get()))
     }
-    fn minus(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        self.get()
-            .checked_neg()
-            .map(Value::new_int)
-            .ok_or_else(|| ValueError::IntegerOverflow.into())
-    }
-    fn add(&self, _original: Value, other: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            self.get()
-                .checked_add(other)
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
-        } else {
-            unsupported_with(self, "+", other)
+    fn minus(&self, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match self.get().checked_neg() {
+            Some(x) => Ok(Value::new_int(x)),
+            None => Ok(heap.alloc(-BigInt::from(self.get()))),
         }
     }
-    fn sub(&self, other: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        if let Some(other) = other.unpack_int() {
-            self.get()
-                .checked_sub(other)
-                .map(Value::new_int)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
+    fn add(&self, _original: Value, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bin_op(
+            self.get(),
+            other,
+            heap,
+            "+",
+            |a, b| Ok(a.checked_add(b)),
+            |a, b| Ok(a + b),
+            |a, b| Ok(a + b),
+        )
+    }
+    fn sub(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bin_op(
+            self.get(),
+            other,
+            heap,
+            "-",
+            |a, b| Ok(a.checked_sub(b)),
+            |a, b| Ok(a - b),
+            |a, b| Ok(a - b),
+        )
+    }
+    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        if StarlarkFloat::unpack_number(other).is_some() {
+            int_bin_op(
+                self.get(),
+                other,
+                heap,
+                "*",
+                |a, b| Ok(a.checked_mul(b)),
+                |a, b| Ok(a * b),
+                |a, b| Ok(a * b),
+            )
         } else {
-            unsupported_with(self, "-", other)
+            other.mul(Value::new_int(self.get()), heap)
         }
     }
-    fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        match other.unpack_int() {
-            Some(other) => self
-                .get()
-                .checked_mul(other)
-                .ok_or_else(|| ValueError::IntegerOverflow.into())
-                .map(Value::new_int),
-            None => other.mul(Value::new_int(self.get()), heap),
+    fn div(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match StarlarkFloat::unpack_number(other) {
+            Some(other) => Ok(heap.alloc(float_div(self.get() as f64, other)?)),
+            None => unsupported_with(self, "/", other),
         }
     }
-    fn percent(&self, other: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        i64_arith_bin_op(self.get(), other, "%", |a, b| {
-            if b == 0 {
-                return Err(ValueError::DivisionByZero.into());
-            }
-            // In Rust `i32::min_value() % -1` is overflow, but we should eval it to zero.
-            if self.get() == i32::min_value() && b == -1 {
-                return Ok(0);
-            }
-            let r = a % b;
-            if r == 0 {
-                Ok(0)
-            } else {
-                Ok(if b.signum() != r.signum() { r + b } else { r })
-            }
-        })
+    fn percent(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bin_op(
+            self.get(),
+            other,
+            heap,
+            "%",
+            |a, b| {
+                if b == 0 {
+                    return Err(ValueError::DivisionByZero.into());
+                }
+                // In Rust `i32::min_value() % -1` is overflow, which we handle as a `BigInt`.
+                Ok(a.checked_rem(b).map(|r| {
+                    if r != 0 && b.signum() != r.signum() {
+                        r + b
+                    } else {
+                        r
+                    }
+                }))
+            },
+            bigint_percent,
+            float_percent,
+        )
     }
-    fn floor_div(&self, other: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        i64_arith_bin_op(self.get(), other, "//", |a, b| {
-            if b == 0 {
-                return Err(ValueError::DivisionByZero.into());
-            }
-            let sig = b.signum() * a.signum();
-            let offset = if sig < 0 && a % b != 0 { 1 } else { 0 };
-            match a.checked_div(b) {
-                Some(div) => Ok(div - offset),
-                None => Err(ValueError::IntegerOverflow.into()),
+    fn floor_div(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bin_op(
+            self.get(),
+            other,
+            heap,
+            "//",
+            |a, b| {
+                if b == 0 {
+                    return Err(ValueError::DivisionByZero.into());
+                }
+                let sig = b.signum() * a.signum();
+                let offset = if sig < 0 && a % b != 0 { 1 } else { 0 };
+                // `i32::min_value() // -1` overflows, which we handle as a `BigInt`.
+                Ok(a.checked_div(b).map(|div| div - offset))
+            },
+            bigint_floor_div,
+            float_floor_div,
+        )
+    }
+
+    fn bit_not(&self, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        Ok(Value::new_int(!self.get()))
+    }
+    fn pipe(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bitwise(self.get(), other, heap, "|", |a, b| a | b, |a, b| a | b)
+    }
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bitwise(self.get(), other, heap, "&", |a, b| a & b, |a, b| a & b)
+    }
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        int_bitwise(self.get(), other, heap, "^", |a, b| a ^ b, |a, b| a ^ b)
+    }
+    fn left_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        if let Some(b) = other.unpack_int() {
+            if (0..32).contains(&b) {
+                let res = (self.get() as i64) << b;
+                if let Ok(res) = i32::try_from(res) {
+                    return Ok(Value::new_int(res));
+                }
             }
-        })
+        }
+        bigint_left_shift(&BigInt::from(self.get()), other, heap)
+    }
+    fn right_shift(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match other.unpack_int() {
+            Some(b) if b >= 0 => Ok(Value::new_int(self.get() >> b.min(31))),
+            _ => bigint_right_shift(&BigInt::from(self.get()), other, heap),
+        }
     }
 
     fn compare(&self, _ptr_eq: bool, other: Value) -> anyhow::Result<Ordering> {
         if let Some(other) = other.unpack_int() {
             Ok(self.get().cmp(&other))
+        } else if let Some(other) = other.downcast_ref::<StarlarkFloat>() {
+            Ok(float_cmp(self.get() as f64, other.0))
+        } else if let Some(other) = other.downcast_ref::<StarlarkBigInt>() {
+            Ok(BigInt::from(self.get()).cmp(other.get()))
         } else {
             unsupported_with(self, "==", other)
         }
@@ -173,7 +264,35 @@ mod tests {
 1 - 2 == -1
 2 * 3 == 6
 5 % 3 == 2
+-5 % 3 == 1
+5 // -3 == -2
+-2147483648 % -1 == 0
+2147483647 + 1 == 2147483648
+-(-2147483647 - 1) == 2147483648
+"#,
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert::all_true(
+            r#"
+~5 == -6
+~-1 == 0
+6 | 3 == 7
+6 & 3 == 2
+6 ^ 3 == 5
+-6 & 0xff == 250
+1 << 4 == 16
+-1 << 4 == -16
+1 << 31 == 2147483648
+256 >> 4 == 16
+-256 >> 4 == -16
+-1 >> 100 == -1
+5 >> 100 == 0
 "#,
         );
+        assert::fail("1 >> -1", "Negative shift count");
+        assert::fail("1 | 1.0", "not supported");
     }
 }
@@ -28,7 +28,7 @@ use crate::{
     },
 };
 use gazebo::{any::AnyLifetime, cell::ARef, prelude::*};
-use std::cmp::Ordering;
+use std::{cmp::Ordering, mem};
 
 #[derive(Clone, Default_, Debug)]
 pub struct ListGen<T> {
@@ -146,6 +146,10 @@ where
         true
     }
 
+    fn extra_memory(&self) -> usize {
+        self.content.capacity() * mem::size_of::<T>()
+    }
+
     fn get_members(&self) -> Option<&'static Globals> {
         static RES: GlobalsStatic = GlobalsStatic::new();
         RES.members(crate::stdlib::list::list_members)
@@ -174,18 +178,15 @@ where
         s.push(']');
     }
 
-    fn to_json(&self) -> String {
-        format!(
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(format!(
             "[{}]",
-            self.content.iter().map(|e| e.to_json()).enumerate().fold(
-                "".to_string(),
-                |accum, s| if s.0 == 0 {
-                    accum + &s.1
-                } else {
-                    accum + ", " + &s.1
-                },
-            )
-        )
+            self.content
+                .iter()
+                .map(|e| e.to_json())
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ))
     }
 
     fn to_bool(&self) -> bool {
@@ -289,6 +290,7 @@ where
             // Important we have finished using other, so that we can have exclusive access for content_mut
             let items = other.iter().collect::<Vec<_>>();
             Ok(box move |heap| {
+                heap.add_extra_bytes(items.len() * mem::size_of::<Value>());
                 List::from_value_mut(lhs, heap)?.unwrap().extend(items);
                 Ok(())
             })
@@ -309,6 +311,9 @@ where
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         match other.unpack_int() {
             Some(l) => {
+                let bytes = (self.content.len() * mem::size_of::<Value>())
+                    .saturating_mul(l.max(0) as usize);
+                heap.check_reserve(bytes)?;
                 let mut result = List {
                     content: Vec::new(),
                 };
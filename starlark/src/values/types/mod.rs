@@ -16,15 +16,19 @@
  */
 
 pub mod any;
+pub mod bigint;
 pub mod bool;
+pub mod bytes;
 pub mod dict;
 pub mod enumeration;
+pub mod float;
 pub mod function;
 pub mod int;
 pub mod list;
 pub mod none;
 pub mod range;
 pub mod record;
+pub mod set;
 pub mod string;
 pub mod structs;
 pub mod tuple;
@@ -46,8 +46,8 @@ impl<'v> TypedValue<'v> for NoneType {
         s.push_str("None");
     }
 
-    fn to_json(&self) -> String {
-        "None".to_string()
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok("None".to_string())
     }
     fn to_bool(&self) -> bool {
         false
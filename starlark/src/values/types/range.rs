@@ -18,31 +18,57 @@
 //! `range()` builtin implementation
 
 use crate::values::{
-    index::{convert_index, convert_slice_indices},
-    iter::TypedIterable,
-    AllocValue, Heap, ImmutableValue, TypedValue, Value, ValueError,
+    bigint::StarlarkBigInt, float::StarlarkFloat, index::convert_slice_indices,
+    iter::TypedIterable, AllocValue, Heap, ImmutableValue, TypedValue, Value, ValueError,
 };
 use gazebo::any::AnyLifetime;
-use std::{marker::PhantomData, num::NonZeroI32};
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+use std::{convert::TryFrom, num::NonZeroI64};
 
 /// Representation of `range()` type.
+///
+/// The bounds are `i64`, so a range may contain ints which don't fit into `i32`,
+/// these are allocated as big ints when produced.
 #[derive(Clone, Copy, Debug, AnyLifetime)]
 pub struct Range {
-    start: i32,
-    stop: i32,
-    step: NonZeroI32,
+    start: i64,
+    stop: i64,
+    step: NonZeroI64,
 }
 
 impl Range {
     pub const TYPE: &'static str = "range";
 
-    pub fn new(start: i32, stop: i32, step: NonZeroI32) -> Range {
+    pub fn new(start: i64, stop: i64, step: NonZeroI64) -> Range {
         Range { start, stop, step }
     }
 
+    /// Number of elements in the range. Unlike `length`, never overflows.
+    fn len(&self) -> u64 {
+        // If step is into opposite direction of stop, then length is zero.
+        if self.start == self.stop || (self.stop >= self.start) != (self.step.get() > 0) {
+            return 0;
+        }
+
+        // Convert range and step to `u64`
+        let (dist, step) = if self.step.get() >= 0 {
+            (
+                self.stop.wrapping_sub(self.start) as u64,
+                self.step.get() as u64,
+            )
+        } else {
+            (
+                self.start.wrapping_sub(self.stop) as u64,
+                self.step.get().wrapping_neg() as u64,
+            )
+        };
+        (dist - 1) / step + 1
+    }
+
     fn equals_range(&self, other: &Range) -> anyhow::Result<bool> {
-        let self_length = self.length()?;
-        let other_length = other.length()?;
+        let self_length = self.len();
+        let other_length = other.len();
         if self_length == 0 || other_length == 0 {
             return Ok(self_length == other_length);
         }
@@ -62,8 +88,16 @@ impl Range {
     }
 }
 
+/// Allocate an element of a range, only going to the heap outside of the `i32` range.
+fn alloc_int(x: i64, heap: &Heap) -> Value {
+    match i32::try_from(x) {
+        Ok(x) => Value::new_int(x),
+        Err(_) => heap.alloc(BigInt::from(x)),
+    }
+}
+
 /// Implementation of iterator over range.
-struct RangeIterator<'a>(Range, PhantomData<&'a ()>);
+struct RangeIterator<'a>(Range, &'a Heap);
 
 impl<'a> Iterator for RangeIterator<'a> {
     type Item = Value<'a>;
@@ -75,7 +109,7 @@ impl<'a> Iterator for RangeIterator<'a> {
 
         let old_start = self.0.start;
         self.0.start = self.0.start.saturating_add(self.0.step.get());
-        Some(Value::new_int(old_start))
+        Some(alloc_int(old_start, self.1))
     }
 }
 
@@ -109,39 +143,25 @@ impl<'v> TypedValue<'v> for Range {
     }
 
     fn length(&self) -> anyhow::Result<i32> {
-        if self.start == self.stop {
-            return Ok(0);
-        }
-
-        // If step is into opposite direction of stop, then length is zero.
-        if (self.stop >= self.start) != (self.step.get() > 0) {
-            return Ok(0);
-        }
+        i32::try_from(self.len()).map_err(|_| ValueError::IntegerOverflow.into())
+    }
 
-        // Convert range and step to `u64`
-        let (dist, step) = if self.step.get() >= 0 {
-            (
-                self.stop.wrapping_sub(self.start) as u64,
-                self.step.get() as u64,
-            )
+    fn at(&self, index: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        let i = index
+            .to_int()
+            .map_err(|_| ValueError::IncorrectParameterType)?;
+        let len = i128::from(self.len());
+        let index = if i < 0 {
+            len + i128::from(i)
         } else {
-            (
-                self.start.wrapping_sub(self.stop) as u64,
-                self.step.get().wrapping_neg() as u64,
-            )
+            i128::from(i)
         };
-        let i = ((dist - 1) / step + 1) as i32;
-        if i >= 0 {
-            Ok(i)
-        } else {
-            Err(ValueError::IntegerOverflow.into())
+        if index < 0 || index >= len {
+            return Err(ValueError::IndexOutOfBound(i).into());
         }
-    }
-
-    fn at(&self, index: Value, _heap: &'v Heap) -> anyhow::Result<Value<'v>> {
-        let index = convert_index(index, self.length()?)?;
-        // Must not overflow if `length` is computed correctly
-        Ok(Value::new_int(self.start + self.step.get() * index))
+        // Within `start..stop`, so must fit into `i64`
+        let x = i128::from(self.start) + i128::from(self.step.get()) * index;
+        Ok(alloc_int(x as i64, heap))
     }
 
     fn equals(&self, other: Value) -> anyhow::Result<bool> {
@@ -164,7 +184,7 @@ impl<'v> TypedValue<'v> for Range {
             start: self
                 .start
                 .checked_add(
-                    start
+                    i64::from(start)
                         .checked_mul(self.step.get())
                         .ok_or(ValueError::IntegerOverflow)?,
                 )
@@ -172,12 +192,14 @@ impl<'v> TypedValue<'v> for Range {
             stop: self
                 .start
                 .checked_add(
-                    stop.checked_mul(self.step.get())
+                    i64::from(stop)
+                        .checked_mul(self.step.get())
                         .ok_or(ValueError::IntegerOverflow)?,
                 )
                 .ok_or(ValueError::IntegerOverflow)?,
-            step: NonZeroI32::new(
-                step.checked_mul(self.step.get())
+            step: NonZeroI64::new(
+                i64::from(step)
+                    .checked_mul(self.step.get())
                     .ok_or(ValueError::IntegerOverflow)?,
             )
             .unwrap(),
@@ -189,7 +211,15 @@ impl<'v> TypedValue<'v> for Range {
     }
 
     fn is_in(&self, other: Value) -> anyhow::Result<bool> {
-        let other = match other.unpack_int() {
+        // A float with an integer value acts like that int
+        let other = match StarlarkBigInt::unpack_bigint(other)
+            .and_then(|x| x.to_i64())
+            .or_else(|| {
+                other
+                    .downcast_ref::<StarlarkFloat>()
+                    .filter(|x| x.0.fract() == 0.0)
+                    .and_then(|x| x.0.to_i64())
+            }) {
             Some(other) => other,
             None => {
                 // Go implementation errors here,
@@ -231,32 +261,35 @@ impl PartialEq for Range {
 }
 
 impl<'v> TypedIterable<'v> for Range {
-    fn to_iter<'a>(&'a self, _heap: &'v Heap) -> Box<dyn Iterator<Item = Value<'v>> + 'a>
+    fn to_iter<'a>(&'a self, heap: &'v Heap) -> Box<dyn Iterator<Item = Value<'v>> + 'a>
     where
         'v: 'a,
     {
-        box RangeIterator::<'v>(*self, PhantomData::default())
+        box RangeIterator::<'v>(*self, heap)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::values::{range::Range, Heap, TypedValue, Value};
-    use std::num::NonZeroI32;
+    use crate::{
+        assert,
+        values::{range::Range, Heap, TypedValue, Value},
+    };
+    use std::num::NonZeroI64;
 
-    fn range(start: i32, stop: i32, range: i32) -> Range {
+    fn range(start: i64, stop: i64, range: i64) -> Range {
         Range {
             start,
             stop,
-            step: NonZeroI32::new(range).unwrap(),
+            step: NonZeroI64::new(range).unwrap(),
         }
     }
 
-    fn range_start_stop(start: i32, stop: i32) -> Range {
+    fn range_start_stop(start: i64, stop: i64) -> Range {
         range(start, stop, 1)
     }
 
-    fn range_stop(stop: i32) -> Range {
+    fn range_stop(stop: i64) -> Range {
         range_start_stop(0, stop)
     }
 
@@ -272,9 +305,15 @@ mod tests {
         assert_eq!(Some(0), range_start_stop(10, -30).length().ok());
         assert_eq!(
             Some(i32::max_value()),
-            range_start_stop(0, i32::max_value()).length().ok()
+            range_start_stop(0, i32::max_value() as i64).length().ok()
+        );
+        assert!(range_start_stop(-1, i32::max_value() as i64)
+            .length()
+            .is_err());
+        assert_eq!(
+            u64::max_value(),
+            range_start_stop(i64::min_value(), i64::max_value()).len()
         );
-        assert!(range_start_stop(-1, i32::max_value()).length().is_err());
     }
 
     #[test]
@@ -331,4 +370,36 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_range_big() {
+        assert::all_true(
+            r#"
+range(2**31)[-1] == 2**31 - 1
+list(range(2**31 - 2, 2**31 + 1)) == [2**31 - 2, 2**31 - 1, 2**31]
+list(range(0, 2**40, 2**32))[-1] == 255 * 2**32
+len(range(0, 2**40, 2**32)) == 256
+range(0, 2**40, 2**32)[1] == 2**32
+2**33 in range(0, 2**40, 2**32)
+(2**33 + 1) not in range(0, 2**40, 2**32)
+2**64 not in range(2**62)
+repr(range(-2**40, 2**40)) == "range(-1099511627776, 1099511627776)"
+"#,
+        );
+        assert::fail("len(range(2**31))", "Integer overflow");
+        assert::fail("range(2**63)", "Integer overflow");
+    }
+
+    #[test]
+    fn test_range_float() {
+        assert::all_true(
+            r#"
+2.0 in range(3)
+2.5 not in range(3)
+3.0 not in range(3)
+float("nan") not in range(3)
+1e100 not in range(4)
+"#,
+        );
+    }
 }
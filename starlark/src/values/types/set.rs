@@ -0,0 +1,367 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Module define the Starlark type Set
+use crate::{
+    collections::{Hashed, SmallSet},
+    environment::{Globals, GlobalsStatic},
+    values::{
+        iter::TypedIterable, unsupported_with, Freezer, FrozenValue, Heap, ImmutableValue,
+        MutableValue, TypedValue, Value, ValueLike, Walker,
+    },
+};
+use gazebo::{any::AnyLifetime, cell::ARef, prelude::*};
+use indexmap::Equivalent;
+use std::mem;
+
+/// Define the Set type, which preserves insertion order.
+#[derive(Clone, Default_, Debug)]
+pub struct SetGen<T> {
+    pub content: SmallSet<T>,
+}
+
+impl<T> SetGen<T> {
+    pub const TYPE: &'static str = "set";
+}
+
+starlark_value!(pub Set);
+
+impl FrozenSet {
+    // We need a lifetime because FrozenValue doesn't contain the right lifetime
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn from_value(x: &FrozenValue) -> Option<ARef<FrozenSet>> {
+        x.downcast_ref::<FrozenSet>()
+    }
+}
+
+impl<V> SetGen<V> {
+    pub fn new(content: SmallSet<V>) -> Self {
+        Self { content }
+    }
+
+    pub fn get_content(&self) -> &SmallSet<V> {
+        &self.content
+    }
+}
+
+impl<'v, T: ValueLike<'v>> SetGen<T>
+where
+    Value<'v>: Equivalent<T>,
+{
+    pub fn len(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Value<'v>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content.iter().map(|e| e.to_value())
+    }
+
+    pub fn iter_hashed<'a>(&'a self) -> impl Iterator<Item = Hashed<Value<'v>>> + 'a
+    where
+        'v: 'a,
+    {
+        self.content
+            .iter_hashed()
+            .map(|e| e.unborrow_copy().to_hashed_value())
+    }
+
+    pub fn contains(&self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.content.contains_hashed(value.get_hashed()?.borrow()))
+    }
+
+    /// The elements in either set.
+    pub fn union(&self, other: &SmallSet<Value<'v>>) -> Set<'v> {
+        let mut content = SmallSet::with_capacity(self.len() + other.len());
+        for x in self.iter_hashed() {
+            content.insert_hashed(x);
+        }
+        for x in other.iter_hashed() {
+            content.insert_hashed(x.unborrow_copy());
+        }
+        Set::new(content)
+    }
+
+    /// The elements in both sets.
+    pub fn intersection(&self, other: &SmallSet<Value<'v>>) -> Set<'v> {
+        let mut content = SmallSet::new();
+        for x in self.iter_hashed() {
+            if other.contains_hashed(x.borrow()) {
+                content.insert_hashed(x);
+            }
+        }
+        Set::new(content)
+    }
+
+    /// The elements in this set but not in `other`.
+    pub fn difference(&self, other: &SmallSet<Value<'v>>) -> Set<'v> {
+        let mut content = SmallSet::new();
+        for x in self.iter_hashed() {
+            if !other.contains_hashed(x.borrow()) {
+                content.insert_hashed(x);
+            }
+        }
+        Set::new(content)
+    }
+
+    /// The elements in exactly one of the sets.
+    pub fn symmetric_difference(&self, other: &SmallSet<Value<'v>>) -> Set<'v> {
+        let mut res = self.difference(other);
+        for x in other.iter_hashed() {
+            if !self.content.contains_hashed(x) {
+                res.content.insert_hashed(x.unborrow_copy());
+            }
+        }
+        res
+    }
+
+    /// Is every element of this set also in `other`.
+    pub fn is_subset(&self, other: &SmallSet<Value<'v>>) -> bool {
+        self.iter_hashed()
+            .all(|x| other.contains_hashed(x.borrow()))
+    }
+}
+
+impl<'v> Set<'v> {
+    /// Collect the elements of any iterable into a new set.
+    pub fn from_iterable(x: Value<'v>, heap: &'v Heap) -> anyhow::Result<Set<'v>> {
+        if let Some(x) = Set::from_value(x) {
+            return Ok((*x).clone());
+        }
+        let mut content = SmallSet::new();
+        for x in &x.iterate(heap)? {
+            heap.check_reserve_items::<Value>(content.len() + 1)?;
+            content.insert_hashed(x.get_hashed()?);
+        }
+        Ok(Set::new(content))
+    }
+
+    /// Add an element, returning `false` if it was already present.
+    pub fn add(&mut self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.content.insert_hashed(value.get_hashed()?))
+    }
+
+    /// Remove an element, returning `false` if it was not present.
+    pub fn discard(&mut self, value: Value<'v>) -> anyhow::Result<bool> {
+        Ok(self.content.remove_hashed(value.get_hashed()?.borrow()))
+    }
+}
+
+impl<'v> MutableValue<'v> for Set<'v> {
+    fn freeze<'fv>(self: Box<Self>, freezer: &'fv Freezer) -> Box<dyn ImmutableValue<'fv> + 'fv> {
+        let mut content: SmallSet<FrozenValue> = SmallSet::with_capacity(self.content.len());
+        for x in self.content.into_iter_hashed() {
+            content.insert_hashed(x.freeze(freezer));
+        }
+        box FrozenSet { content }
+    }
+
+    fn walk(&mut self, walker: &Walker<'v>) {
+        self.content
+            .iter()
+            .for_each(|x| walker.walk_dictionary_key(x))
+    }
+}
+
+impl<'v> ImmutableValue<'v> for FrozenSet {
+    fn thaw(&self, heap: &'v Heap) -> Box<dyn MutableValue<'v> + 'v> {
+        let mut content = SmallSet::with_capacity(self.content.len());
+        for x in self.content.iter_hashed() {
+            // The hash does not change as you freeze/thaw
+            content.insert_hashed(Hashed::new_unchecked(
+                x.hash(),
+                heap.alloc_thaw_on_write(*x.key()),
+            ));
+        }
+        box Set { content }
+    }
+}
+
+impl<'v, T: ValueLike<'v>> TypedValue<'v> for SetGen<T>
+where
+    Value<'v>: Equivalent<T>,
+    Self: AnyLifetime<'v>,
+{
+    starlark_type!(Set::TYPE);
+
+    fn naturally_mutable(&self) -> bool {
+        true
+    }
+
+    fn extra_memory(&self) -> usize {
+        self.content.len() * mem::size_of::<T>()
+    }
+
+    fn get_members(&self) -> Option<&'static Globals> {
+        static RES: GlobalsStatic = GlobalsStatic::new();
+        RES.members(crate::stdlib::set::set_members)
+    }
+
+    fn collect_repr(&self, r: &mut String) {
+        if self.content.is_empty() {
+            r.push_str("set()");
+            return;
+        }
+        r.push_str("set([");
+        for (i, x) in self.content.iter().enumerate() {
+            if i != 0 {
+                r.push_str(", ");
+            }
+            x.collect_repr(r);
+        }
+        r.push_str("])");
+    }
+
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(format!(
+            "[{}]",
+            self.content
+                .iter()
+                .map(|e| e.to_json())
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ))
+    }
+
+    fn to_bool(&self) -> bool {
+        !self.content.is_empty()
+    }
+
+    fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        match Set::from_value(other) {
+            None => Ok(false),
+            Some(other) => Ok(self.len() == other.len() && self.is_subset(&other.content)),
+        }
+    }
+
+    fn length(&self) -> anyhow::Result<i32> {
+        Ok(self.content.len() as i32)
+    }
+
+    fn is_in(&self, other: Value<'v>) -> anyhow::Result<bool> {
+        self.contains(other)
+    }
+
+    fn iterate(&self) -> anyhow::Result<&(dyn TypedIterable<'v> + 'v)> {
+        Ok(self)
+    }
+
+    fn pipe(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match Set::from_value(other) {
+            Some(x) => Ok(heap.alloc(self.union(&x.content))),
+            None => unsupported_with(self, "|", other),
+        }
+    }
+
+    fn bit_and(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match Set::from_value(other) {
+            Some(x) => Ok(heap.alloc(self.intersection(&x.content))),
+            None => unsupported_with(self, "&", other),
+        }
+    }
+
+    fn bit_xor(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match Set::from_value(other) {
+            Some(x) => Ok(heap.alloc(self.symmetric_difference(&x.content))),
+            None => unsupported_with(self, "^", other),
+        }
+    }
+
+    fn sub(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
+        match Set::from_value(other) {
+            Some(x) => Ok(heap.alloc(self.difference(&x.content))),
+            None => unsupported_with(self, "-", other),
+        }
+    }
+}
+
+impl<'v, T: ValueLike<'v>> TypedIterable<'v> for SetGen<T> {
+    fn to_iter<'a>(&'a self, _heap: &'v Heap) -> Box<dyn Iterator<Item = Value<'v>> + 'a>
+    where
+        'v: 'a,
+    {
+        box self.content.iter().map(|x| x.to_value())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assert;
+
+    #[test]
+    fn test_set_operators() {
+        assert::all_true(
+            r#"
+set([1, 2]) | set([2, 3]) == set([1, 2, 3])
+set([1, 2]) & set([2, 3]) == set([2])
+set([1, 2]) - set([2, 3]) == set([1])
+set([1, 2]) ^ set([2, 3]) == set([1, 3])
+list(set([3, 1, 2, 1])) == [3, 1, 2]
+set([1, 2]) == set([2, 1])
+set([1, 2]) != set([1])
+set([1]) != [1]
+2 in set([1, 2])
+3 not in set([1, 2])
+len(set(["a", "b", "a"])) == 2
+not set()
+repr(set()) == "set()"
+repr(set([1, "a"])) == 'set([1, "a"])'
+"#,
+        );
+        assert::fail("set([1]) | [2]", "not supported");
+        assert::fail("set([[1]])", "not hashable");
+    }
+
+    #[test]
+    fn test_set_literals() {
+        assert::all_true(
+            r#"
+{1, 2, 2} == set([1, 2])
+{x * 2 for x in [1, 2, 3] if x != 2} == set([2, 6])
+type({1}) == "set"
+{} == dict()
+"#,
+        );
+        let mut a = assert::Assert::new();
+        a.dialect_set(|d| d.enable_set_literals = false);
+        a.fail("{1, 2}", "not allowed in this dialect");
+        a.fail("{x for x in []}", "not allowed in this dialect");
+        a.is_true("set([1]) | set([2]) == set([1, 2])");
+    }
+
+    #[test]
+    fn test_set_freeze() {
+        let mut a = assert::Assert::new();
+        a.module("s.star", "s = set([1, 2])");
+        a.is_true(
+            r#"
+load("s.star", "s")
+x = s | set([3])
+x == set([1, 2, 3]) and s == set([1, 2])
+"#,
+        );
+        a.fail(
+            r#"
+load("s.star", "s")
+s.add(3)
+"#,
+            "Immutable",
+        );
+    }
+}
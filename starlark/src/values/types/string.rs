@@ -117,8 +117,8 @@ impl<'v> TypedValue<'v> for Box<str> {
         buffer.push('"');
     }
 
-    fn to_json(&self) -> String {
-        self.to_repr()
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(self.to_repr())
     }
 
     fn to_bool(&self) -> bool {
@@ -281,6 +281,7 @@ impl<'v> TypedValue<'v> for Box<str> {
     fn mul(&self, other: Value<'v>, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         match other.unpack_int() {
             Some(l) => {
+                heap.check_reserve(self.len().saturating_mul(l.max(0) as usize))?;
                 let mut result = String::new();
                 for _i in 0..l {
                     result += self
@@ -303,6 +304,8 @@ impl<'v> TypedValue<'v> for Box<str> {
     /// # starlark::assert::all_true(r#"
     /// "Hello %s, your score is %d" % ("Bob", 75) == "Hello Bob, your score is 75"
     /// "%d %o %x %c" % (65, 65, 65, 65) == "65 101 41 A"
+    /// "%d %i" % (2.7, -2.7) == "2 -2"
+    /// "%f %.2f %e %g" % (1.5, 1.5, 1.5, 1.5) == "1.500000 1.50 1.500000e+00 1.5"
     /// "%(greeting)s, %(audience)s" % {"greeting": "Hello", "audience": "world"} == "Hello, world"
     /// "Hello %s, welcome" % "Bob" == "Hello Bob, welcome"
     /// "%s%(a)%" % {"a": 1} == "{\"a\": 1}%" # Copy Python corner-cases
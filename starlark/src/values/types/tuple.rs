@@ -23,7 +23,7 @@ use crate::values::{
     TypedValue, Value, ValueError, ValueLike, Walker,
 };
 use gazebo::{any::AnyLifetime, prelude::*};
-use std::{cmp::Ordering, collections::hash_map::DefaultHasher, hash::Hasher};
+use std::{cmp::Ordering, collections::hash_map::DefaultHasher, hash::Hasher, mem};
 
 pub(crate) fn slice_vector<'a, 'v, V: ValueLike<'v> + 'a, I: Iterator<Item = &'a V>>(
     start: i32,
@@ -109,6 +109,10 @@ where
 {
     starlark_type!(Tuple::TYPE);
 
+    fn extra_memory(&self) -> usize {
+        self.content.len() * mem::size_of::<T>()
+    }
+
     fn collect_repr(&self, s: &mut String) {
         s.push('(');
         let mut first = true;
@@ -137,18 +141,15 @@ where
         Ok(s.finish())
     }
 
-    fn to_json(&self) -> String {
-        format!(
+    fn to_json(&self) -> anyhow::Result<String> {
+        Ok(format!(
             "[{}]",
-            self.content.iter().map(|e| e.to_json()).enumerate().fold(
-                "".to_string(),
-                |accum, s| if s.0 == 0 {
-                    accum + &s.1
-                } else {
-                    accum + ", " + &s.1
-                },
-            )
-        )
+            self.content
+                .iter()
+                .map(|e| e.to_json())
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ")
+        ))
     }
 
     fn equals(&self, other: Value<'v>) -> anyhow::Result<bool> {
@@ -245,6 +246,9 @@ where
     fn mul(&self, other: Value, heap: &'v Heap) -> anyhow::Result<Value<'v>> {
         match other.unpack_int() {
             Some(l) => {
+                let bytes = (self.content.len() * mem::size_of::<Value>())
+                    .saturating_mul(l.max(0) as usize);
+                heap.check_reserve(bytes)?;
                 let mut result = Tuple {
                     content: Vec::new(),
                 };
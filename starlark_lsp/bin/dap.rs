@@ -0,0 +1,579 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A server for the Debug Adapter Protocol, so an editor can set breakpoints,
+//! step through a file and look at its variables as it is evaluated.
+//! Evaluation happens on its own thread, which, whenever it stops, waits for
+//! the client to tell it what to do next.
+
+use crate::{
+    eval::{dialect, Context},
+    index::canonical,
+};
+use anyhow::anyhow;
+use codemap::{Span, SpanLoc};
+use serde::Deserialize;
+use serde_json::{json, Value as Json};
+use starlark::{
+    debug,
+    eval::{EvaluationContext, PrintHandler},
+    syntax::parse,
+    values::Value,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    convert::TryFrom,
+    fs,
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+// Evaluation is single threaded, so this is the only thread we tell the client about
+const THREAD_ID: i64 = 1;
+
+// The variables reference of the innermost frame's variables, each frame out
+// from there adds one
+const LOCALS: i64 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+struct Request {
+    seq: i64,
+    command: String,
+    #[serde(default)]
+    arguments: Json,
+}
+
+/// What the client can ask of evaluation while it is stopped.
+#[derive(Debug)]
+enum Command {
+    /// Answer a request about the state of evaluation.
+    Inspect(Request),
+    Continue,
+    StepIn,
+    StepOver,
+    StepOut,
+}
+
+/// When to stop next, other than at a breakpoint.
+#[derive(Debug, Clone, Copy)]
+enum Step {
+    Continue,
+    In,
+    /// Stop at a statement at most this deep in the call stack.
+    Over(usize),
+    /// Stop at a statement less deep in the call stack than this.
+    Out(usize),
+}
+
+impl Step {
+    fn stops(self, depth: usize) -> bool {
+        match self {
+            Step::Continue => false,
+            Step::In => true,
+            Step::Over(x) => depth <= x,
+            Step::Out(x) => depth < x,
+        }
+    }
+}
+
+/// Sends messages to the client, numbering them as the protocol requires.
+#[derive(Debug, Default)]
+struct Client {
+    seq: AtomicI64,
+    // Held while writing, so messages from different threads don't interleave
+    output: Mutex<()>,
+}
+
+impl Client {
+    fn send(&self, mut message: Json) {
+        let _guard = self.output.lock().unwrap();
+        message["seq"] = json!(self.seq.fetch_add(1, Ordering::SeqCst) + 1);
+        let body = message.to_string();
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        // If the client has gone away there is nobody to tell
+        let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let _ = stdout.flush();
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({"type": "event", "event": event, "body": body}))
+    }
+
+    fn respond(&self, request: &Request, body: anyhow::Result<Json>) {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": body.is_ok(),
+        });
+        match body {
+            Ok(Json::Null) => {}
+            Ok(body) => response["body"] = body,
+            Err(e) => response["message"] = json!(format!("{:#}", e)),
+        }
+        self.send(response)
+    }
+}
+
+/// The state shared between the thread talking to the client and the thread
+/// doing the evaluation.
+#[derive(Debug, Default)]
+struct Debugger {
+    client: Client,
+    /// For each file, the (0-based) lines with breakpoints, and their conditions.
+    breakpoints: Mutex<HashMap<PathBuf, HashMap<usize, Option<String>>>>,
+    /// Set by the client to stop at the next statement.
+    pause: AtomicBool,
+    /// Whether evaluation is stopped, waiting for a command.
+    stopped: AtomicBool,
+}
+
+/// The state of the evaluation thread.
+struct Stepper {
+    step: Cell<Step>,
+    /// The last statement we saw, and how deep in the call stack it was.
+    last: RefCell<Option<(SpanLoc, usize)>>,
+    commands: Receiver<Command>,
+}
+
+// Evaluate some code where evaluation is stopped, without stopping inside it
+fn evaluate<'v>(code: String, ctx: &mut EvaluationContext<'v, '_>) -> anyhow::Result<Value<'v>> {
+    let on_stmt = ctx.on_stmt.take();
+    let res = debug::evaluate(code, ctx);
+    ctx.on_stmt = on_stmt;
+    res
+}
+
+fn frame(id: usize, name: String, location: Option<SpanLoc>) -> Json {
+    match location {
+        // Called from Rust, so there is no source to show
+        None => json!({"id": id, "name": name, "line": 0, "column": 0}),
+        Some(x) => json!({
+            "id": id,
+            "name": name,
+            "source": {"path": x.file.name()},
+            "line": x.begin.line + 1,
+            "column": x.begin.column + 1,
+        }),
+    }
+}
+
+// The stack, innermost first. Each frame on the call stack records where its
+// function was called from, which is the location in the frame below it.
+fn stack_trace(span: Span, ctx: &EvaluationContext) -> Json {
+    let frames = ctx.call_stack().to_diagnostic_frames();
+    let mut names = vec!["<module>".to_owned()];
+    let mut locations = Vec::new();
+    for x in frames {
+        names.push(x.name);
+        locations.push(x.location);
+    }
+    locations.push(Some(ctx.look_up_span(span)));
+    let depth = names.len() - 1;
+    let stack = names
+        .into_iter()
+        .zip(locations)
+        .enumerate()
+        .rev()
+        .map(|(i, (name, location))| frame(depth - i, name, location))
+        .collect::<Vec<_>>();
+    json!({"stackFrames": stack, "totalFrames": depth + 1})
+}
+
+// Answer a request about the state of evaluation while it is stopped
+fn inspect(request: &Request, span: Span, ctx: &mut EvaluationContext) -> anyhow::Result<Json> {
+    let args = &request.arguments;
+    let frame_id = args["frameId"].as_i64().unwrap_or(0);
+    // We can only evaluate in the innermost frame
+    let innermost = frame_id == 0;
+    match request.command.as_str() {
+        "stackTrace" => Ok(stack_trace(span, ctx)),
+        "scopes" => {
+            if frame_id < 0 || frame_id as usize > ctx.call_stack().depth() {
+                return Err(anyhow!("Unknown frame {}", frame_id));
+            }
+            Ok(json!({
+                "scopes": [{"name": "Locals", "variablesReference": LOCALS + frame_id, "expensive": false}]
+            }))
+        }
+        "variables" => {
+            // The variables of the frame with the given id, where 0 is the innermost
+            let reference = args["variablesReference"].as_i64().unwrap_or(0);
+            let mut frames = debug::inspect_frame_variables(ctx);
+            let frame = usize::try_from(reference - LOCALS)
+                .ok()
+                .and_then(|x| frames.len().checked_sub(x + 1))
+                .ok_or_else(|| anyhow!("Unknown variables reference {}", reference))?;
+            let variables = frames
+                .swap_remove(frame)
+                .into_iter()
+                .map(|(name, value)| {
+                    json!({
+                        "name": name,
+                        "value": value.to_repr(),
+                        "type": value.get_type(),
+                        "variablesReference": 0,
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(json!({ "variables": variables }))
+        }
+        "evaluate" if innermost => {
+            let expression = args["expression"].as_str().unwrap_or_default().to_owned();
+            let value = evaluate(expression, ctx)?;
+            Ok(json!({
+                "result": value.to_repr(),
+                "type": value.get_type(),
+                "variablesReference": 0,
+            }))
+        }
+        "evaluate" => Err(anyhow!("Can only evaluate in the innermost frame")),
+        _ => Err(anyhow!("Unsupported request `{}`", request.command)),
+    }
+}
+
+// Show what the program prints in the debug console, next to where it came from
+impl PrintHandler for Debugger {
+    fn println(&self, message: &str, location: Option<SpanLoc>) -> anyhow::Result<()> {
+        let mut body = json!({"category": "stdout", "output": format!("{}\n", message)});
+        if let Some(x) = location {
+            body["source"] = json!({"path": x.file.name()});
+            body["line"] = json!(x.begin.line + 1);
+        }
+        self.client.event("output", body);
+        Ok(())
+    }
+}
+
+impl Debugger {
+    fn on_stmt(&self, span: Span, ctx: &mut EvaluationContext, stepper: &Stepper) {
+        let location = ctx.look_up_span(span);
+        let depth = ctx.call_stack().depth();
+        // Only stop at the first statement on a line, unless we come back to it
+        let same_line = match &*stepper.last.borrow() {
+            Some((last, last_depth)) => {
+                Arc::ptr_eq(&last.file, &location.file)
+                    && last.begin.line == location.begin.line
+                    && last.begin.column < location.begin.column
+                    && *last_depth == depth
+            }
+            None => false,
+        };
+        let reason = if self.pause.swap(false, Ordering::SeqCst) {
+            Some("pause")
+        } else if same_line {
+            None
+        } else if stepper.step.get().stops(depth) {
+            Some("step")
+        } else if self.breakpoint(&location, ctx) {
+            Some("breakpoint")
+        } else {
+            None
+        };
+        *stepper.last.borrow_mut() = Some((location, depth));
+        if let Some(reason) = reason {
+            self.stop(reason, span, depth, ctx, stepper)
+        }
+    }
+
+    // Is there a breakpoint at this location whose condition is true
+    fn breakpoint(&self, location: &SpanLoc, ctx: &mut EvaluationContext) -> bool {
+        let condition = match self
+            .breakpoints
+            .lock()
+            .unwrap()
+            .get(Path::new(location.file.name()))
+            .and_then(|x| x.get(&location.begin.line))
+        {
+            None => return false,
+            Some(condition) => condition.clone(),
+        };
+        match condition.map(|x| evaluate(x, ctx)) {
+            None => true,
+            Some(Ok(x)) => x.to_bool(),
+            // Stop, so the user can see what is wrong with the condition
+            Some(Err(e)) => {
+                self.client.event(
+                    "output",
+                    json!({
+                        "category": "stderr",
+                        "output": format!("Breakpoint condition failed: {:#}\n", e),
+                    }),
+                );
+                true
+            }
+        }
+    }
+
+    // Wait for the client to tell us to carry on, answering its requests meanwhile
+    fn stop(
+        &self,
+        reason: &str,
+        span: Span,
+        depth: usize,
+        ctx: &mut EvaluationContext,
+        stepper: &Stepper,
+    ) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.client.event(
+            "stopped",
+            json!({"reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true}),
+        );
+        while let Ok(command) = stepper.commands.recv() {
+            let step = match command {
+                Command::Inspect(request) => {
+                    let body = inspect(&request, span, ctx);
+                    self.client.respond(&request, body);
+                    continue;
+                }
+                Command::Continue => Step::Continue,
+                Command::StepIn => Step::In,
+                Command::StepOver => Step::Over(depth),
+                Command::StepOut => Step::Out(depth),
+            };
+            stepper.step.set(step);
+            return;
+        }
+    }
+}
+
+// The (0-based) lines on which statements start, in order
+fn statement_lines(file: &Path) -> anyhow::Result<Vec<usize>> {
+    let module = parse(
+        &file.to_string_lossy(),
+        fs::read_to_string(file)?,
+        &dialect(),
+    )?;
+    let mut lines = debug::stmt_locations(&module)
+        .into_iter()
+        .map(|x| module.codemap.look_up_span(x).begin.line)
+        .collect::<Vec<_>>();
+    lines.sort_unstable();
+    lines.dedup();
+    Ok(lines)
+}
+
+struct Server {
+    ctx: Arc<Context>,
+    debugger: Arc<Debugger>,
+    commands: Sender<Command>,
+    /// Taken by the evaluation thread when it starts.
+    receiver: Option<Receiver<Command>>,
+    /// The file to evaluate, and whether to stop at its first statement.
+    launch: Option<(PathBuf, bool)>,
+    /// Whether the client has finished setting breakpoints.
+    configured: bool,
+}
+
+impl Server {
+    fn set_breakpoints(&self, args: &Json) -> anyhow::Result<Json> {
+        let path = args["source"]["path"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Expected a `source` with a `path`"))?;
+        let path = canonical(Path::new(path));
+        let lines = statement_lines(&path);
+        let mut breakpoints = HashMap::new();
+        let mut res = Vec::new();
+        for x in args["breakpoints"]
+            .as_array()
+            .map_or(&[][..], |x| x.as_slice())
+        {
+            let line = x["line"].as_u64().unwrap_or(1).max(1) as usize;
+            let condition = x["condition"]
+                .as_str()
+                .filter(|x| !x.is_empty())
+                .map(str::to_owned);
+            // A breakpoint on a line without a statement moves to the next one
+            match &lines {
+                Err(e) => res.push(json!({"verified": false, "message": format!("{:#}", e)})),
+                Ok(lines) => match lines.iter().find(|x| **x >= line - 1) {
+                    None => res.push(json!({"verified": false, "message": "No statement here"})),
+                    Some(x) => {
+                        breakpoints.insert(*x, condition);
+                        res.push(json!({"verified": true, "line": x + 1}));
+                    }
+                },
+            }
+        }
+        self.debugger
+            .breakpoints
+            .lock()
+            .unwrap()
+            .insert(path, breakpoints);
+        Ok(json!({ "breakpoints": res }))
+    }
+
+    // Start evaluation, once we know what to evaluate and where the breakpoints are
+    fn start(&mut self) {
+        if !self.configured || self.launch.is_none() || self.receiver.is_none() {
+            return;
+        }
+        let (program, stop_on_entry) = self.launch.take().unwrap();
+        let stepper = Stepper {
+            step: Cell::new(if stop_on_entry {
+                Step::In
+            } else {
+                Step::Continue
+            }),
+            last: RefCell::new(None),
+            commands: self.receiver.take().unwrap(),
+        };
+        let ctx = self.ctx.clone();
+        let debugger = self.debugger.clone();
+        thread::spawn(move || {
+            let res = ctx.debug(
+                &program,
+                &|span, eval| debugger.on_stmt(span, eval, &stepper),
+                &*debugger,
+            );
+            if let Err(e) = &res {
+                debugger.client.event(
+                    "output",
+                    json!({"category": "stderr", "output": format!("{:#}\n", e)}),
+                );
+            }
+            debugger.client.event(
+                "exited",
+                json!({"exitCode": if res.is_ok() { 0 } else { 1 }}),
+            );
+            debugger.client.event("terminated", json!({}));
+        });
+    }
+
+    // Tell the stopped evaluation what to do. Returns `None` if it will respond itself.
+    fn command(&self, command: Command) -> anyhow::Result<Option<Json>> {
+        if !self.debugger.stopped.load(Ordering::SeqCst) {
+            return Err(anyhow!("Evaluation is not stopped"));
+        }
+        let inspect = matches!(command, Command::Inspect(_));
+        if !inspect {
+            self.debugger.stopped.store(false, Ordering::SeqCst);
+        }
+        self.commands
+            .send(command)
+            .map_err(|_| anyhow!("Evaluation has finished"))?;
+        Ok(if inspect { None } else { Some(Json::Null) })
+    }
+
+    fn handle(&mut self, request: &Request) -> anyhow::Result<Option<Json>> {
+        let args = &request.arguments;
+        match request.command.as_str() {
+            "initialize" => Ok(Some(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsConditionalBreakpoints": true,
+                "supportsEvaluateForHovers": true,
+                "supportsTerminateRequest": true,
+            }))),
+            "launch" => {
+                let program = args["program"]
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Expected a `program` to debug"))?;
+                let stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                self.launch = Some((canonical(Path::new(program)), stop_on_entry));
+                self.start();
+                Ok(Some(Json::Null))
+            }
+            "setBreakpoints" => self.set_breakpoints(args).map(Some),
+            "configurationDone" => {
+                self.configured = true;
+                self.start();
+                Ok(Some(Json::Null))
+            }
+            "threads" => Ok(Some(json!({
+                "threads": [{"id": THREAD_ID, "name": "main"}]
+            }))),
+            "pause" => {
+                self.debugger.pause.store(true, Ordering::SeqCst);
+                Ok(Some(Json::Null))
+            }
+            "continue" => self
+                .command(Command::Continue)
+                .map(|_| Some(json!({"allThreadsContinued": true}))),
+            "next" => self.command(Command::StepOver),
+            "stepIn" => self.command(Command::StepIn),
+            "stepOut" => self.command(Command::StepOut),
+            "stackTrace" | "scopes" | "variables" | "evaluate" => {
+                self.command(Command::Inspect(request.clone()))
+            }
+            "disconnect" | "terminate" => Ok(Some(Json::Null)),
+            _ => Err(anyhow!("Unsupported request `{}`", request.command)),
+        }
+    }
+}
+
+// Read a message, preceded by a `Content-Length` header, or `None` at the end of the input
+fn read_message(input: &mut impl BufRead) -> anyhow::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(x) = line.strip_prefix("Content-Length:") {
+            length = Some(x.trim().parse::<usize>()?);
+        }
+    }
+    let length = length.ok_or_else(|| anyhow!("Expected a `Content-Length` header"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Serve the Debug Adapter Protocol over stdin/stdout, until the client disconnects.
+pub fn server(ctx: Context) -> anyhow::Result<()> {
+    let (commands, receiver) = mpsc::channel();
+    let mut server = Server {
+        ctx: Arc::new(ctx),
+        debugger: Arc::new(Debugger::default()),
+        commands,
+        receiver: Some(receiver),
+        launch: None,
+        configured: false,
+    };
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        // Clients only send requests, anything else we can ignore
+        let request = match serde_json::from_value::<Request>(message) {
+            Ok(request) => request,
+            Err(_) => continue,
+        };
+        match server.handle(&request) {
+            Ok(None) => {}
+            Ok(Some(body)) => server.debugger.client.respond(&request, Ok(body)),
+            Err(e) => server.debugger.client.respond(&request, Err(e)),
+        }
+        match request.command.as_str() {
+            "initialize" => server.debugger.client.event("initialized", json!({})),
+            "disconnect" | "terminate" => return Ok(()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -15,18 +15,30 @@
  * limitations under the License.
  */
 
-use crate::types::Message;
+use crate::{
+    index::{canonical, Entry, Index},
+    types::{CoverageReport, Message},
+};
+use anyhow::anyhow;
+use codemap::Span;
 use itertools::Either;
 use starlark::{
-    analysis,
+    analysis::{
+        self, Completion, CompletionKind, Completions, DefSummary, Definition, FoldingRange, Hover,
+        LineColSpan, Lint, LintConfig, Reference, ReferenceKind, References, Referent, RenameError,
+        Severity, Symbol, Token,
+    },
+    debug::Coverage,
     environment::{FrozenModule, Globals, Module},
-    eval::{eval_module, EvaluationContext, NoLoadFileLoader},
+    eval::{eval_module, EvaluationContext, FileSystemLoader, NoLoadFileLoader, PrintHandler},
     stdlib::{add_typing, extended_environment},
     syntax::{parse, parse_file, AstModule, Dialect},
 };
 use std::{
+    ffi::OsStr,
     fs, iter,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 #[derive(Debug)]
@@ -35,10 +47,26 @@ pub struct Context {
     pub info: bool,
     pub run: bool,
     pub prelude: Vec<FrozenModule>,
+    pub lint_config: LintConfig,
+    /// The directory that `//` paths in a `load` are relative to.
+    pub root: Option<PathBuf>,
+    /// What we know about the files in the workspace.
+    pub index: Index,
+    /// If set, records which statements and branches run.
+    pub coverage: Option<Coverage>,
+    globals: Globals,
 }
 
 impl Context {
-    pub fn new(check: bool, info: bool, run: bool, prelude: &[PathBuf]) -> anyhow::Result<Self> {
+    pub fn new(
+        check: bool,
+        info: bool,
+        run: bool,
+        prelude: &[PathBuf],
+        lint_config: LintConfig,
+        root: Option<PathBuf>,
+        extension: &str,
+    ) -> anyhow::Result<Self> {
         let globals = globals();
         let prelude = prelude
             .iter()
@@ -57,6 +85,11 @@ impl Context {
             info,
             run,
             prelude,
+            lint_config,
+            root,
+            index: Index::new(extension),
+            coverage: None,
+            globals,
         })
     }
 
@@ -115,17 +148,417 @@ impl Context {
         )
     }
 
+    /// Format a file in place, or with `check`, report it if it isn't formatted.
+    pub fn format(&self, file: &Path, check: bool) -> impl Iterator<Item = Message> {
+        let filename = &file.to_string_lossy();
+        Self::err(filename, self.format_file(filename, file, check))
+    }
+
+    fn format_file(
+        &self,
+        filename: &str,
+        file: &Path,
+        check: bool,
+    ) -> anyhow::Result<impl Iterator<Item = Message>> {
+        let content = fs::read_to_string(file)?;
+        let formatted = self.format_with_contents(filename, content.clone())?;
+        if formatted == content {
+            Ok(None.into_iter())
+        } else if check {
+            Ok(Some(Message {
+                path: filename.to_owned(),
+                span: None,
+                severity: Severity::Error,
+                name: "format".to_owned(),
+                description: "File is not formatted".to_owned(),
+                original: None,
+                edits: Vec::new(),
+            })
+            .into_iter())
+        } else {
+            fs::write(file, formatted)?;
+            Ok(None.into_iter())
+        }
+    }
+
+    /// Apply the fixes for the lints in a file in place, then report the lints
+    /// that are left.
+    pub fn fix(&self, file: &Path) -> impl Iterator<Item = Message> {
+        let filename = &file.to_string_lossy();
+        Self::err(filename, self.fix_file(filename, file))
+    }
+
+    fn fix_file(
+        &self,
+        filename: &str,
+        file: &Path,
+    ) -> anyhow::Result<impl Iterator<Item = Message>> {
+        let content = fs::read_to_string(file)?;
+        let module = parse(filename, content.clone(), &dialect())?;
+        let fixed = analysis::apply_fixes(&module, &self.lints(&module));
+        if fixed != content {
+            fs::write(file, &fixed)?;
+        }
+        Ok(self.file_with_contents(filename, fixed))
+    }
+
+    pub fn format_with_contents(&self, filename: &str, content: String) -> anyhow::Result<String> {
+        Ok(parse(filename, content, &dialect())?.format())
+    }
+
+    /// Find the path of a module named in a `load` from `current`, the same
+    /// way as the [`FileSystemLoader`] that runs `current` does.
+    pub fn resolve_load(&self, module: &str, current: &Path) -> anyhow::Result<PathBuf> {
+        FileSystemLoader::new(self.load_root(current), &self.globals, &dialect())
+            .resolve(module, Some(current))
+    }
+
+    /// Find where the identifier at the 0-based `line` and `column` of `file` is
+    /// defined, following a `load` into the file it names.
+    pub fn find_definition(
+        &self,
+        file: &Path,
+        content: String,
+        line: usize,
+        column: usize,
+    ) -> anyhow::Result<Option<(PathBuf, LineColSpan)>> {
+        let module = parse(&file.to_string_lossy(), content, &dialect())?;
+        Ok(match analysis::find_definition(&module, line, column) {
+            None => None,
+            Some(Definition::Location(x)) => {
+                Some((file.to_owned(), LineColSpan::from_span_loc(&x)))
+            }
+            Some(Definition::Load {
+                location,
+                module,
+                name,
+            }) => {
+                let path = self.resolve_load(&module, file)?;
+                match self.entry(&path)?.exports.iter().find(|x| x.1 == name) {
+                    Some((x, _)) => Some((path, *x)),
+                    // Fall back to the `load` itself
+                    None => Some((file.to_owned(), LineColSpan::from_span_loc(&location))),
+                }
+            }
+        })
+    }
+
+    /// Describe the identifier at the 0-based `line` and `column` of `file` as
+    /// Markdown, for a `def` in this or a loaded file, or a global.
+    pub fn hover(
+        &self,
+        file: &Path,
+        content: String,
+        line: usize,
+        column: usize,
+    ) -> anyhow::Result<Option<String>> {
+        let module = parse(&file.to_string_lossy(), content, &dialect())?;
+        Ok(match analysis::find_hover(&module, line, column) {
+            None => None,
+            Some(Hover::Def(x)) => Some(describe_def(&x)),
+            Some(Hover::Load { module, name }) => {
+                let loaded = self.entry(&self.resolve_load(&module, file)?)?;
+                analysis::find_def(&loaded.module, &name).map(|x| describe_def(&x))
+            }
+            Some(Hover::Global(name)) => describe_global(&name),
+        })
+    }
+
+    /// The outline of a file, as its top-level `def`s, assignments and `load`s.
+    pub fn document_symbols(&self, filename: &str, content: String) -> anyhow::Result<Vec<Symbol>> {
+        let module = parse(filename, content, &dialect())?;
+        Ok(analysis::document_symbols(&module))
+    }
+
+    /// The blocks and literals in a file that span multiple lines.
+    pub fn folding_ranges(
+        &self,
+        filename: &str,
+        content: String,
+    ) -> anyhow::Result<Vec<FoldingRange>> {
+        let module = parse(filename, content, &dialect())?;
+        Ok(analysis::folding_ranges(&module))
+    }
+
+    /// Every identifier in a file, classified by what it refers to.
+    pub fn semantic_tokens(&self, filename: &str, content: String) -> anyhow::Result<Vec<Token>> {
+        let module = parse(filename, content, &dialect())?;
+        let globals = globals();
+        let mut names = globals.names();
+        for x in &self.prelude {
+            names.extend(x.names().map(str::to_owned));
+        }
+        let names = names.iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        Ok(analysis::semantic_tokens(&module, Some(&names)))
+    }
+
+    /// What could be written at the 0-based `line` and `column` of `file`, most
+    /// relevant first. The content doesn't have to parse.
+    pub fn completion(
+        &self,
+        file: &Path,
+        content: &str,
+        line: usize,
+        column: usize,
+    ) -> anyhow::Result<Vec<Completion>> {
+        let globals = globals();
+        Ok(
+            match analysis::completions(content, &dialect(), &globals, line, column) {
+                None => Vec::new(),
+                Some(Completions::Names(mut res)) => {
+                    for x in &self.prelude {
+                        let kind = CompletionKind::Variable;
+                        res.extend(x.names().map(|x| Completion {
+                            name: x.to_owned(),
+                            kind,
+                        }));
+                    }
+                    res
+                }
+                Some(Completions::Load(module)) => {
+                    let loaded = self.entry(&self.resolve_load(&module, file)?)?;
+                    loaded
+                        .exports
+                        .iter()
+                        .map(|(_, name)| Completion {
+                            name: name.clone(),
+                            kind: CompletionKind::Variable,
+                        })
+                        .collect()
+                }
+            },
+        )
+    }
+
+    /// Find every occurrence of the variable at the 0-based `line` and `column`
+    /// of `file`. For exported names, including those loaded by `file`, that
+    /// includes the other files in the workspace which `load` them.
+    pub fn references(
+        &self,
+        file: &Path,
+        line: usize,
+        column: usize,
+    ) -> anyhow::Result<Vec<(PathBuf, LineColSpan)>> {
+        let entry = self.entry(file)?;
+        let references = match analysis::find_references(&entry.module, line, column) {
+            None => return Ok(Vec::new()),
+            Some(x) => x,
+        };
+        let mut res = Vec::new();
+        for (path, _, xs) in self.workspace_references(file, entry, references)? {
+            res.extend(
+                xs.iter()
+                    .map(|x| (path.clone(), LineColSpan::from_span_loc(&x.location))),
+            );
+        }
+        Ok(res)
+    }
+
+    /// The edits, by file, to rename the variable at the 0-based `line` and
+    /// `column` of `file` to `new_name`. Fails if the new name would collide
+    /// with, or shadow, another variable in any of the files.
+    pub fn rename(
+        &self,
+        file: &Path,
+        line: usize,
+        column: usize,
+        new_name: &str,
+    ) -> anyhow::Result<Vec<(PathBuf, Vec<(LineColSpan, String)>)>> {
+        let entry = self.entry(file)?;
+        let references = match analysis::find_references(&entry.module, line, column) {
+            None => return Ok(Vec::new()),
+            Some(x) => x,
+        };
+        let files = match references.referent {
+            Referent::Global => return Err(RenameError::Global(references.name).into()),
+            // Renaming the alias given by a `load` only affects this file
+            Referent::Load { .. }
+                if references
+                    .references
+                    .iter()
+                    .all(|x| x.kind != ReferenceKind::LoadSymbol) =>
+            {
+                vec![(file.to_owned(), entry, references.references)]
+            }
+            _ => self.workspace_references(file, entry, references)?,
+        };
+        let mut res = Vec::new();
+        for (path, entry, xs) in files {
+            analysis::check_rename(&entry.module, &xs, new_name)?;
+            let edits = xs
+                .iter()
+                .filter_map(|x| {
+                    let replacement = x.replacement(new_name)?;
+                    Some((LineColSpan::from_span_loc(&x.location), replacement))
+                })
+                .collect();
+            res.push((path, edits));
+        }
+        Ok(res)
+    }
+
+    // Follow exported names, including those loaded into `file`, to every file
+    // in the workspace that refers to them. `entry` is the entry for `file`.
+    fn workspace_references(
+        &self,
+        file: &Path,
+        entry: Arc<Entry>,
+        references: References,
+    ) -> anyhow::Result<Vec<(PathBuf, Arc<Entry>, Vec<Reference>)>> {
+        let (path, name) = match references.referent {
+            Referent::Exported => (file.to_owned(), references.name),
+            Referent::Load { module, name } => (self.resolve_load(&module, file)?, name),
+            Referent::Local | Referent::Global => {
+                return Ok(vec![(file.to_owned(), entry, references.references)]);
+            }
+        };
+        let target = canonical(&path);
+        let defining = self.entry(&path)?;
+        let xs = analysis::find_exported_references(&defining.module, &name);
+        let mut res = vec![(path.clone(), defining, xs)];
+        for x in self.workspace_files(&path).iter() {
+            if x == &target {
+                continue;
+            }
+            // Whatever its name, e.g. a `BUILD` file loading a `.bzl` file, a file
+            // refers to the definition only if one of its loads resolves to it.
+            // A file that doesn't parse can't load anything we can find.
+            let entry = match self.entry(x) {
+                Ok(entry) if entry.loads.contains(&target) => entry,
+                _ => continue,
+            };
+            let is_module = |load: &str| {
+                self.resolve_load(load, x)
+                    .map_or(false, |load| canonical(&load) == target)
+            };
+            let xs = analysis::find_loaded_references(&entry.module, is_module, &name);
+            if !xs.is_empty() {
+                res.push((x.clone(), entry, xs));
+            }
+        }
+        Ok(res)
+    }
+
+    // The Starlark files that might `load` `file`, i.e. those under the root,
+    // or the directory of `file` if there is no root.
+    fn workspace_files(&self, file: &Path) -> Arc<Vec<PathBuf>> {
+        match &self.root {
+            Some(root) => self.index.files(root),
+            None => self
+                .index
+                .files(file.parent().unwrap_or_else(|| Path::new(""))),
+        }
+    }
+
+    // The parsed file at `path`, along with its loads and exports
+    fn entry(&self, path: &Path) -> anyhow::Result<Arc<Entry>> {
+        self.index.get(path, |path, content| {
+            let module = parse(&path.to_string_lossy(), content, &dialect())?;
+            let loads = module
+                .collect_loads()
+                .into_iter()
+                .filter_map(|x| self.resolve_load(x, path).ok())
+                .map(|x| canonical(&x))
+                .collect();
+            let exports = analysis::exported_symbols(&module)
+                .into_iter()
+                .map(|(x, name)| (LineColSpan::from_span_loc(&x), name))
+                .collect();
+            Ok(Entry {
+                module,
+                loads,
+                exports,
+            })
+        })
+    }
+
+    /// Evaluate a file, calling `on_stmt` before each statement, so it can act
+    /// as a debugger, and sending anything printed to `print_handler`.
+    pub fn debug(
+        &self,
+        file: &Path,
+        on_stmt: &dyn Fn(Span, &mut EvaluationContext),
+        print_handler: &dyn PrintHandler,
+    ) -> anyhow::Result<()> {
+        let filename = &file.to_string_lossy();
+        let module = parse(filename, fs::read_to_string(file)?, &dialect())?;
+        let env = Module::new(filename);
+        for p in &self.prelude {
+            env.import_public_symbols(p)
+        }
+        let globals = globals();
+        // Stop in the modules it loads too, which print to the same place
+        let mut loader = FileSystemLoader::new(self.load_root(file), &globals, &dialect());
+        loader.on_stmt = Some(on_stmt);
+        let mut context = EvaluationContext::new(&env, &globals, &loader);
+        context.on_stmt = Some(on_stmt);
+        context.print_handler = print_handler;
+        loader.evaluating(file, || eval_module(module, &mut context))?;
+        Ok(())
+    }
+
+    // The directory `//` paths in a `load` are relative to when evaluating
+    // `file`, which is its own directory if there is no root
+    fn load_root(&self, file: &Path) -> PathBuf {
+        match &self.root {
+            Some(root) => canonical(root),
+            None => canonical(file.parent().unwrap_or_else(|| Path::new(""))),
+        }
+    }
+
     fn run(&self, file: &str, module: AstModule) -> impl Iterator<Item = Message> {
         let env = Module::new(file);
         for p in &self.prelude {
             env.import_public_symbols(p)
         }
         let globals = globals();
-        let mut context = EvaluationContext::new(&env, &globals, &NoLoadFileLoader);
-        Self::err(
-            file,
-            eval_module(module, &mut context).map(|_| iter::empty()),
-        )
+        let on_stmt = |span, context: &mut EvaluationContext| {
+            if let Some(coverage) = &self.coverage {
+                coverage.stmt(span, context)
+            }
+        };
+        let on_branch = |span, taken, context: &mut EvaluationContext| {
+            if let Some(coverage) = &self.coverage {
+                coverage.branch(span, taken, context)
+            }
+        };
+        let on_module = |module: &AstModule| {
+            if let Some(coverage) = &self.coverage {
+                coverage.add_module(module)
+            }
+        };
+        let path = Path::new(file);
+        let mut loader = FileSystemLoader::new(self.load_root(path), &globals, &dialect());
+        if self.coverage.is_some() {
+            // Include the modules it loads
+            loader.on_stmt = Some(&on_stmt);
+            loader.on_branch = Some(&on_branch);
+            loader.on_module = Some(&on_module);
+        }
+        let mut context = EvaluationContext::new(&env, &globals, &loader);
+        if let Some(coverage) = &self.coverage {
+            coverage.add_module(&module);
+            context.on_stmt = Some(&on_stmt);
+            context.on_branch = Some(&on_branch);
+        }
+        let res = loader.evaluating(path, || eval_module(module, &mut context));
+        Self::err(file, res.map(|_| iter::empty()))
+    }
+
+    /// Write the coverage recorded so far, as a JSON summary if `path` ends in
+    /// `.json`, and as LCOV otherwise.
+    pub fn write_coverage(&self, path: &Path) -> anyhow::Result<()> {
+        let coverage = self
+            .coverage
+            .as_ref()
+            .ok_or_else(|| anyhow!("Coverage is not being recorded"))?;
+        let contents = if path.extension() == Some(OsStr::new("json")) {
+            serde_json::to_string_pretty(&CoverageReport::new(coverage.summary()))?
+        } else {
+            coverage.lcov()
+        };
+        fs::write(path, contents)?;
+        Ok(())
     }
 
     fn info(&self, module: &AstModule) {
@@ -136,7 +569,8 @@ impl Context {
         }
     }
 
-    fn check(&self, module: &AstModule) -> impl Iterator<Item = Message> {
+    // The lints for a module, except those that have been turned off
+    fn lints(&self, module: &AstModule) -> Vec<Lint> {
         let mut globals = Vec::new();
         for x in &self.prelude {
             globals.extend(x.names());
@@ -147,10 +581,42 @@ impl Context {
             Some(globals.as_slice())
         };
 
-        analysis::lint(module, globals)
-            .into_iter()
-            .map(Message::from_lint)
+        let mut lints = analysis::lint(module, globals);
+        lints.retain(|x| self.lint_config.enabled(&x.short_name));
+        lints
     }
+
+    fn check(&self, module: &AstModule) -> impl Iterator<Item = Message> {
+        let lint_config = self.lint_config.clone();
+        self.lints(module).into_iter().filter_map(move |x| {
+            let severity = lint_config.severity(&x)?;
+            Some(Message::from_lint(x, severity))
+        })
+    }
+}
+
+fn describe(signature: &str, docstring: Option<&str>) -> String {
+    let mut res = format!("```python\n{}\n```", signature);
+    if let Some(docstring) = docstring {
+        res.push_str("\n\n");
+        res.push_str(docstring);
+    }
+    res
+}
+
+fn describe_def(x: &DefSummary) -> String {
+    describe(&x.signature, x.docstring.as_deref())
+}
+
+fn describe_global(name: &str) -> Option<String> {
+    let globals = globals();
+    let value = globals.get(name)?;
+    let signature = if value.get_type() == "function" {
+        format!("def {}", value.to_repr())
+    } else {
+        format!("{}: {}", name, value.get_type())
+    };
+    Some(describe(&signature, globals.docstring(name)))
 }
 
 pub fn globals() -> Globals {
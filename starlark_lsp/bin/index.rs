@@ -0,0 +1,296 @@
+/*
+ * Copyright 2019 The Starlark in Rust Authors.
+ * Copyright (c) Facebook, Inc. and its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! A cache of the files in the workspace, and what we know about them, so that
+//! cross-file queries don't have to find, read and parse every file each time.
+//! Entries are kept until we are told the file has changed.
+
+use anyhow::anyhow;
+use starlark::{analysis::LineColSpan, syntax::AstModule};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use walkdir::WalkDir;
+
+/// A parsed file.
+#[derive(Debug)]
+pub struct Entry {
+    pub module: AstModule,
+    /// The files named by its `load` statements, as given by [`canonical`].
+    pub loads: Vec<PathBuf>,
+    /// The symbols it exports, and where they are defined.
+    pub exports: Vec<(LineColSpan, String)>,
+}
+
+/// Files that are Starlark whatever the extension we were given.
+const STARLARK_NAMES: &[&str] = &["BUILD", "BUILD.bazel", "WORKSPACE", "WORKSPACE.bazel"];
+
+#[derive(Debug)]
+pub struct Index {
+    /// The extension of Starlark files, without the leading `.`.
+    extension: String,
+    /// Files with unsaved changes, whose contents we use instead of what is on disk.
+    open: Mutex<HashMap<PathBuf, String>>,
+    /// Files we have parsed, or the error we got trying to.
+    entries: Mutex<HashMap<PathBuf, Result<Arc<Entry>, String>>>,
+    /// Every Starlark file under each directory we have searched.
+    files: Mutex<HashMap<PathBuf, Arc<Vec<PathBuf>>>>,
+}
+
+/// The key for a file in the index, so different paths to it are equivalent.
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
+}
+
+impl Index {
+    /// An empty index, for Starlark files with the given extension.
+    pub fn new(extension: &str) -> Self {
+        Self {
+            extension: extension.to_owned(),
+            open: Default::default(),
+            entries: Default::default(),
+            files: Default::default(),
+        }
+    }
+
+    /// Whether `path`, found under `dir`, is a file we should search: a Starlark
+    /// file outside any hidden directory, such as `.git`.
+    fn is_workspace_file(&self, dir: &Path, path: &Path) -> bool {
+        let is_starlark = path.extension() == Some(OsStr::new(&self.extension))
+            || path
+                .file_name()
+                .map_or(false, |x| STARLARK_NAMES.iter().any(|y| x == *y));
+        let in_hidden = match path.strip_prefix(dir) {
+            Ok(relative) => relative
+                .parent()
+                .into_iter()
+                .flat_map(|x| x.components())
+                .any(|x| x.as_os_str().to_string_lossy().starts_with('.')),
+            Err(_) => true,
+        };
+        is_starlark && !in_hidden
+    }
+
+    /// Use `content` for a file with unsaved changes, rather than what is on disk.
+    pub fn open(&self, path: &Path, content: String) {
+        let path = canonical(path);
+        self.entries.lock().unwrap().remove(&path);
+        self.open.lock().unwrap().insert(path, content);
+    }
+
+    /// Go back to what is on disk for a file that is no longer open.
+    pub fn close(&self, path: &Path) {
+        let path = canonical(path);
+        self.entries.lock().unwrap().remove(&path);
+        self.open.lock().unwrap().remove(&path);
+    }
+
+    /// Forget what we know about a file that has changed on disk.
+    pub fn changed(&self, path: &Path) {
+        self.entries.lock().unwrap().remove(&canonical(path));
+    }
+
+    /// Record that a file has been created.
+    pub fn created(&self, path: &Path) {
+        let path = canonical(path);
+        self.entries.lock().unwrap().remove(&path);
+        for (dir, files) in self.files.lock().unwrap().iter_mut() {
+            if self.is_workspace_file(dir, &path) && !files.contains(&path) {
+                Arc::make_mut(files).push(path.clone());
+            }
+        }
+    }
+
+    /// Record that a file has been deleted.
+    pub fn deleted(&self, path: &Path) {
+        // A deleted file can't be canonicalized, but its directory might still exist
+        let path = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => canonical(dir).join(name),
+            _ => path.to_owned(),
+        };
+        self.entries.lock().unwrap().remove(&path);
+        for files in self.files.lock().unwrap().values_mut() {
+            if files.contains(&path) {
+                Arc::make_mut(files).retain(|x| x != &path);
+            }
+        }
+    }
+
+    /// Every Starlark file under `dir`, as given by [`canonical`], skipping
+    /// hidden directories.
+    pub fn files(&self, dir: &Path) -> Arc<Vec<PathBuf>> {
+        let dir = canonical(dir);
+        if let Some(files) = self.files.lock().unwrap().get(&dir) {
+            return files.clone();
+        }
+        let files = Arc::new(
+            WalkDir::new(&dir)
+                .into_iter()
+                // Don't descend into `.git` and the like, which can be huge
+                .filter_entry(|e| {
+                    e.depth() == 0
+                        || !e.file_type().is_dir()
+                        || !e.file_name().to_string_lossy().starts_with('.')
+                })
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file() && self.is_workspace_file(&dir, e.path()))
+                .map(|e| e.into_path())
+                .collect::<Vec<_>>(),
+        );
+        self.files.lock().unwrap().insert(dir, files.clone());
+        files
+    }
+
+    /// The entry for a file, using `parse` to create it from the file's contents
+    /// if we don't already have one.
+    pub fn get(
+        &self,
+        path: &Path,
+        parse: impl FnOnce(&Path, String) -> anyhow::Result<Entry>,
+    ) -> anyhow::Result<Arc<Entry>> {
+        let path = canonical(path);
+        if let Some(entry) = self.entries.lock().unwrap().get(&path) {
+            return entry.clone().map_err(|e| anyhow!(e));
+        }
+        // Don't hold the lock while parsing, which might be slow
+        let content = self.open.lock().unwrap().get(&path).cloned();
+        let entry = match content {
+            Some(content) => Ok(content),
+            None => fs::read_to_string(&path).map_err(anyhow::Error::from),
+        }
+        .and_then(|content| parse(&path, content))
+        .map(Arc::new)
+        .map_err(|e| format!("{:#}", e));
+        self.entries.lock().unwrap().insert(path, entry.clone());
+        entry.map_err(|e| anyhow!(e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use starlark::{
+        analysis,
+        syntax::{parse, Dialect},
+    };
+
+    fn parse_entry(path: &Path, content: String) -> anyhow::Result<Entry> {
+        let module = parse(&path.to_string_lossy(), content, &Dialect::Extended)?;
+        let exports = analysis::exported_symbols(&module)
+            .into_iter()
+            .map(|(x, name)| (LineColSpan::from_span_loc(&x), name))
+            .collect();
+        Ok(Entry {
+            module,
+            loads: Vec::new(),
+            exports,
+        })
+    }
+
+    fn exports(index: &Index, path: &Path) -> Option<Vec<String>> {
+        let entry = index.get(path, parse_entry).ok()?;
+        Some(entry.exports.iter().map(|x| x.1.clone()).collect())
+    }
+
+    #[test]
+    fn test_index_invalidation() {
+        let root = std::env::temp_dir().join("starlark_lsp_index_test");
+        let _ignore = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let a = root.join("a.bzl");
+        let b = root.join("b.bzl");
+        fs::write(&a, "x = 1").unwrap();
+        let index = Index::new("bzl");
+        assert_eq!(*index.files(&root), vec![canonical(&a)]);
+        assert_eq!(exports(&index, &a), Some(vec!["x".to_owned()]));
+
+        // Changes on disk are only seen once we are told about them
+        fs::write(&a, "y = 1").unwrap();
+        assert_eq!(exports(&index, &a), Some(vec!["x".to_owned()]));
+        index.changed(&a);
+        assert_eq!(exports(&index, &a), Some(vec!["y".to_owned()]));
+
+        // Unsaved changes win until the file is closed
+        index.open(&a, "z = 1".to_owned());
+        assert_eq!(exports(&index, &a), Some(vec!["z".to_owned()]));
+        index.close(&a);
+        assert_eq!(exports(&index, &a), Some(vec!["y".to_owned()]));
+
+        // A file we failed to read before it was created
+        assert_eq!(exports(&index, &b), None);
+        fs::write(&b, "w = 1").unwrap();
+        index.created(&b);
+        assert_eq!(exports(&index, &b), Some(vec!["w".to_owned()]));
+        assert_eq!(*index.files(&root), vec![canonical(&a), canonical(&b)]);
+
+        fs::remove_file(&a).unwrap();
+        index.deleted(&a);
+        assert_eq!(exports(&index, &a), None);
+        assert_eq!(*index.files(&root), vec![canonical(&b)]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_index_files() {
+        let root = std::env::temp_dir().join("starlark_lsp_index_files_test");
+        let _ignore = fs::remove_dir_all(&root);
+        for dir in &["pkg", ".git", "pkg/.hidden"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in &[
+            "a.bzl",
+            "BUILD",
+            "WORKSPACE",
+            "main.rs",
+            "pkg/BUILD.bazel",
+            "pkg/b.bzl",
+            "pkg/image.png",
+            ".git/c.bzl",
+            "pkg/.hidden/d.bzl",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let index = Index::new("bzl");
+        let mut files = (*index.files(&root)).clone();
+        files.sort();
+        let mut expected = [
+            "a.bzl",
+            "BUILD",
+            "WORKSPACE",
+            "pkg/BUILD.bazel",
+            "pkg/b.bzl",
+        ]
+        .iter()
+        .map(|x| canonical(&root.join(x)))
+        .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(files, expected);
+
+        // Files we are told about are filtered the same way
+        for file in &["e.bzl", "e.txt", ".git/f.bzl"] {
+            fs::write(root.join(file), "").unwrap();
+            index.created(&root.join(file));
+        }
+        assert!(index.files(&root).contains(&canonical(&root.join("e.bzl"))));
+        assert_eq!(index.files(&root).len(), expected.len() + 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -15,16 +15,23 @@
  * limitations under the License.
  */
 
-use crate::{
-    eval::Context,
-    types::{Message, Severity},
+use crate::{eval::Context, types::Message};
+use starlark::analysis::{
+    self, Completion, CompletionKind, LineColSpan, Severity, Symbol, Token, TokenKind,
+};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use tower_lsp::{
+    jsonrpc::{self, ErrorCode, Result},
+    lsp_types::*,
+    Client, LanguageServer, LspService, Server,
 };
-use tower_lsp::{jsonrpc::Result, lsp_types::*, Client, LanguageServer, LspService, Server};
 
 #[derive(Debug)]
 struct Backend {
     client: Client,
     starlark: Context,
+    /// The latest contents of each open document
+    documents: Mutex<HashMap<Url, String>>,
 }
 
 fn to_severity(x: Severity) -> DiagnosticSeverity {
@@ -36,16 +43,114 @@ fn to_severity(x: Severity) -> DiagnosticSeverity {
     }
 }
 
-fn to_diagnostic(x: Message) -> Diagnostic {
-    let range = match x.span {
-        Some(s) => Range::new(
-            Position::new((s.begin.line - 1) as u64, (s.begin.column - 1) as u64),
-            Position::new((s.end.line - 1) as u64, (s.end.column - 1) as u64),
-        ),
-        _ => Range::default(),
+fn to_range(s: LineColSpan) -> Range {
+    Range::new(
+        Position::new((s.begin.line - 1) as u64, (s.begin.column - 1) as u64),
+        Position::new((s.end.line - 1) as u64, (s.end.column - 1) as u64),
+    )
+}
+
+fn to_document_symbol(x: Symbol) -> DocumentSymbol {
+    // The `deprecated` field is itself deprecated, but still has to be given
+    #[allow(deprecated)]
+    DocumentSymbol {
+        name: x.name,
+        detail: None,
+        kind: match x.kind {
+            analysis::SymbolKind::Function => SymbolKind::Function,
+            analysis::SymbolKind::Variable => SymbolKind::Variable,
+            analysis::SymbolKind::Load => SymbolKind::Module,
+        },
+        tags: None,
+        deprecated: None,
+        range: to_range(LineColSpan::from_span_loc(&x.location)),
+        selection_range: to_range(LineColSpan::from_span_loc(&x.name_location)),
+        children: Some(x.children.into_iter().map(to_document_symbol).collect()),
+    }
+}
+
+fn to_folding_range(x: analysis::FoldingRange) -> FoldingRange {
+    FoldingRange {
+        start_line: x.begin as u64,
+        start_character: None,
+        end_line: x.end as u64,
+        end_character: None,
+        kind: None,
+    }
+}
+
+// The token types and modifiers we use, in the order of their indices
+fn semantic_tokens_legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: vec![SemanticTokenType::VARIABLE, SemanticTokenType::PARAMETER],
+        token_modifiers: vec![
+            SemanticTokenModifier::DEFAULT_LIBRARY,
+            SemanticTokenModifier::new("loaded"),
+        ],
+    }
+}
+
+// Encode tokens relative to the previous one, as LSP requires, with columns
+// in UTF-16 code units
+fn to_semantic_tokens(text: &str, tokens: Vec<Token>) -> Vec<SemanticToken> {
+    let lines = text.lines().collect::<Vec<_>>();
+    let utf16 = |line: &str, begin: usize, end: usize| {
+        line.chars()
+            .skip(begin)
+            .take(end - begin)
+            .map(|c| c.len_utf16() as u32)
+            .sum::<u32>()
+    };
+    let mut res = Vec::new();
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for x in tokens {
+        let (begin, end) = (x.location.begin, x.location.end);
+        let line = lines.get(begin.line).copied().unwrap_or_default();
+        let start = utf16(line, 0, begin.column);
+        let (token_type, token_modifiers_bitset) = match x.kind {
+            TokenKind::Variable => (0, 0),
+            TokenKind::Parameter => (1, 0),
+            TokenKind::Builtin => (0, 1),
+            TokenKind::Loaded => (0, 2),
+        };
+        let delta_line = (begin.line - prev_line) as u32;
+        res.push(SemanticToken {
+            delta_line,
+            delta_start: if delta_line == 0 {
+                start - prev_start
+            } else {
+                start
+            },
+            length: utf16(line, begin.column, end.column.max(begin.column)),
+            token_type,
+            token_modifiers_bitset,
+        });
+        prev_line = begin.line;
+        prev_start = start;
+    }
+    res
+}
+
+fn to_completion_item(x: Completion) -> CompletionItem {
+    let (kind, insert_text) = match x.kind {
+        CompletionKind::Variable => (CompletionItemKind::Variable, None),
+        CompletionKind::Function => (CompletionItemKind::Function, None),
+        CompletionKind::Member => (CompletionItemKind::Field, None),
+        CompletionKind::NamedArgument => {
+            (CompletionItemKind::Variable, Some(format!("{} = ", x.name)))
+        }
     };
+    CompletionItem {
+        label: x.name,
+        kind: Some(kind),
+        insert_text,
+        ..CompletionItem::default()
+    }
+}
+
+fn to_diagnostic(x: Message) -> Diagnostic {
     Diagnostic::new(
-        range,
+        x.span.map(to_range).unwrap_or_default(),
         Some(to_severity(x.severity)),
         Some(NumberOrString::String(x.name)),
         None,
@@ -55,8 +160,189 @@ fn to_diagnostic(x: Message) -> Diagnostic {
     )
 }
 
+// Whether two ranges overlap, counting those which only touch
+fn overlaps(x: Range, y: Range) -> bool {
+    x.start <= y.end && y.start <= x.end
+}
+
+// The position just after the last character of `text`
+fn end_position(text: &str) -> Position {
+    let line = text.matches('\n').count();
+    let last = &text[text.rfind('\n').map_or(0, |i| i + 1)..];
+    Position::new(line as u64, last.encode_utf16().count() as u64)
+}
+
+// LSP columns count UTF-16 code units, but we count characters
+fn char_column(text: &str, position: Position) -> usize {
+    let line = text.lines().nth(position.line as usize).unwrap_or_default();
+    let mut units = 0;
+    line.chars()
+        .take_while(|c| {
+            units += c.len_utf16() as u64;
+            units <= position.character
+        })
+        .count()
+}
+
+// The byte offset of a position in `text`, clamped to the end of its line
+fn byte_offset(text: &str, position: Position) -> usize {
+    let start = text
+        .split('\n')
+        .take(position.line as usize)
+        .map(|x| x.len() + 1)
+        .sum::<usize>()
+        .min(text.len());
+    let line = text[start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+    let column = line.char_indices().find(|(_, c)| {
+        units += c.len_utf16() as u64;
+        units > position.character
+    });
+    start + column.map_or(line.len(), |x| x.0)
+}
+
+// Apply a change sent by the client to our copy of the document
+fn apply_change(text: &mut String, change: TextDocumentContentChangeEvent) {
+    match change.range {
+        None => *text = change.text,
+        Some(range) => {
+            let start = byte_offset(text, range.start);
+            let end = byte_offset(text, range.end).max(start);
+            text.replace_range(start..end, &change.text);
+        }
+    }
+}
+
 impl Backend {
+    fn document(&self, uri: &Url) -> Option<String> {
+        self.documents.lock().unwrap().get(uri).cloned()
+    }
+
+    // The contents of an open document that is a file on disk
+    fn document_with_path(&self, uri: &Url) -> Option<(String, PathBuf)> {
+        Some((self.document(uri)?, uri.to_file_path().ok()?))
+    }
+
+    fn definition(&self, uri: Url, position: Position) -> anyhow::Result<Option<Location>> {
+        let (text, path) = match self.document_with_path(&uri) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let column = char_column(&text, position);
+        Ok(self
+            .starlark
+            .find_definition(&path, text, position.line as usize, column)?
+            .and_then(|(path, span)| {
+                Some(Location::new(
+                    Url::from_file_path(path).ok()?,
+                    to_range(span),
+                ))
+            }))
+    }
+
+    fn hover_text(&self, uri: Url, position: Position) -> anyhow::Result<Option<String>> {
+        let (text, path) = match self.document_with_path(&uri) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let column = char_column(&text, position);
+        self.starlark
+            .hover(&path, text, position.line as usize, column)
+    }
+
+    fn completion_items(&self, uri: Url, position: Position) -> anyhow::Result<Vec<Completion>> {
+        let (text, path) = match self.document_with_path(&uri) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+        let column = char_column(&text, position);
+        self.starlark
+            .completion(&path, &text, position.line as usize, column)
+    }
+
+    fn find_references(&self, uri: Url, position: Position) -> anyhow::Result<Vec<Location>> {
+        let (text, path) = match self.document_with_path(&uri) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+        let column = char_column(&text, position);
+        Ok(self
+            .starlark
+            .references(&path, position.line as usize, column)?
+            .into_iter()
+            .filter_map(|(path, span)| {
+                Some(Location::new(
+                    Url::from_file_path(path).ok()?,
+                    to_range(span),
+                ))
+            })
+            .collect())
+    }
+
+    fn rename_edit(
+        &self,
+        uri: Url,
+        position: Position,
+        new_name: &str,
+    ) -> anyhow::Result<Option<WorkspaceEdit>> {
+        let (text, path) = match self.document_with_path(&uri) {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let column = char_column(&text, position);
+        let mut changes = HashMap::new();
+        for (path, edits) in
+            self.starlark
+                .rename(&path, position.line as usize, column, new_name)?
+        {
+            if let Ok(uri) = Url::from_file_path(path) {
+                let edits = edits
+                    .into_iter()
+                    .map(|(span, text)| TextEdit::new(to_range(span), text));
+                changes.insert(uri, edits.collect());
+            }
+        }
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    // Quick fixes for the problems in part of a document
+    fn code_actions(&self, uri: Url, range: Range) -> Vec<CodeActionOrCommand> {
+        let text = match self.document(&uri) {
+            Some(text) => text,
+            None => return Vec::new(),
+        };
+        self.starlark
+            .file_with_contents(&uri.to_string(), text)
+            .filter(|x| {
+                !x.edits.is_empty() && x.span.map_or(false, |s| overlaps(to_range(s), range))
+            })
+            .map(|x| {
+                let edits = x
+                    .edits
+                    .iter()
+                    .map(|(span, text)| TextEdit::new(to_range(*span), text.clone()))
+                    .collect();
+                let mut changes = HashMap::new();
+                changes.insert(uri.clone(), edits);
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Fix: {}", x.description),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit::new(changes)),
+                    diagnostics: Some(vec![to_diagnostic(x)]),
+                    ..CodeAction::default()
+                })
+            })
+            .collect()
+    }
+
     async fn validate(&self, uri: Url, version: Option<i64>, text: String) {
+        self.documents
+            .lock()
+            .unwrap()
+            .insert(uri.clone(), text.clone());
+        if let Ok(path) = uri.to_file_path() {
+            self.starlark.index.open(&path, text.clone());
+        }
         let diags = self
             .starlark
             .file_with_contents(&uri.to_string(), text)
@@ -70,12 +356,54 @@ impl Backend {
 impl LanguageServer for Backend {
     async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
         let mut r = InitializeResult::default();
-        r.capabilities.text_document_sync =
-            Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::Full));
+        r.capabilities.text_document_sync = Some(TextDocumentSyncCapability::Kind(
+            TextDocumentSyncKind::Incremental,
+        ));
+        r.capabilities.document_formatting_provider = Some(true);
+        r.capabilities.definition_provider = Some(true);
+        r.capabilities.hover_provider = Some(HoverProviderCapability::Simple(true));
+        r.capabilities.code_action_provider = Some(CodeActionProviderCapability::Simple(true));
+        r.capabilities.references_provider = Some(true);
+        r.capabilities.rename_provider = Some(RenameProviderCapability::Simple(true));
+        r.capabilities.document_symbol_provider = Some(true);
+        r.capabilities.folding_range_provider = Some(FoldingRangeProviderCapability::Simple(true));
+        r.capabilities.semantic_tokens_provider = Some(
+            SemanticTokensServerCapabilities::SemanticTokensOptions(SemanticTokensOptions {
+                legend: semantic_tokens_legend(),
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            }),
+        );
+        r.capabilities.completion_provider = Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_owned()]),
+            ..CompletionOptions::default()
+        });
         Ok(r)
     }
 
     async fn initialized(&self, _: InitializedParams) {
+        // Find out when files change on disk, so we can update the index
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: "**/*".to_owned(),
+                kind: None,
+            }],
+        };
+        let registration = Registration {
+            id: "watch".to_owned(),
+            method: "workspace/didChangeWatchedFiles".to_owned(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        if self
+            .client
+            .register_capability(vec![registration])
+            .await
+            .is_err()
+        {
+            self.client
+                .log_message(MessageType::Warning, "Can't watch files for changes")
+                .await;
+        }
         self.client
             .log_message(MessageType::Info, "Starlark server initialised")
             .await;
@@ -91,22 +419,172 @@ impl LanguageServer for Backend {
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        // We asked for Sync full, so can just grab all the text from params
-        let change = params.content_changes.into_iter().next().unwrap();
-        self.validate(
-            params.text_document.uri,
-            params.text_document.version,
-            change.text,
-        )
-        .await;
+        // We asked for incremental sync, so apply each change to what we have
+        let mut text = self.document(&params.text_document.uri).unwrap_or_default();
+        for change in params.content_changes {
+            apply_change(&mut text, change);
+        }
+        self.validate(params.text_document.uri, params.text_document.version, text)
+            .await;
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        self.documents
+            .lock()
+            .unwrap()
+            .remove(&params.text_document.uri);
+        if let Ok(path) = params.text_document.uri.to_file_path() {
+            self.starlark.index.close(&path);
+        }
         self.client
             .publish_diagnostics(params.text_document.uri, vec![], None)
             .await;
     }
 
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        for change in params.changes {
+            if let Ok(path) = change.uri.to_file_path() {
+                let index = &self.starlark.index;
+                match change.typ {
+                    FileChangeType::Created => index.created(&path),
+                    FileChangeType::Changed => index.changed(&path),
+                    FileChangeType::Deleted => index.deleted(&path),
+                }
+            }
+        }
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let text = match self.document(&uri) {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        // If the document doesn't parse there is nothing we can do, and the
+        // diagnostics will already be telling the user why
+        Ok(
+            match self
+                .starlark
+                .format_with_contents(&uri.to_string(), text.clone())
+            {
+                Err(_) => None,
+                Ok(formatted) if formatted == text => Some(Vec::new()),
+                Ok(formatted) => Some(vec![TextEdit::new(
+                    Range::new(Position::new(0, 0), end_position(&text)),
+                    formatted,
+                )]),
+            },
+        )
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> Result<Option<DocumentSymbolResponse>> {
+        let uri = params.text_document.uri;
+        Ok(self
+            .document(&uri)
+            .and_then(|text| self.starlark.document_symbols(&uri.to_string(), text).ok())
+            .map(|xs| {
+                DocumentSymbolResponse::Nested(xs.into_iter().map(to_document_symbol).collect())
+            }))
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri;
+        Ok(self
+            .document(&uri)
+            .and_then(|text| self.starlark.folding_ranges(&uri.to_string(), text).ok())
+            .map(|xs| xs.into_iter().map(to_folding_range).collect()))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri;
+        let text = match self.document(&uri) {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        Ok(self
+            .starlark
+            .semantic_tokens(&uri.to_string(), text.clone())
+            .ok()
+            .map(|xs| {
+                SemanticTokensResult::Tokens(SemanticTokens {
+                    result_id: None,
+                    data: to_semantic_tokens(&text, xs),
+                })
+            }))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let params = params.text_document_position_params;
+        // Failing to find a definition, e.g. because the file doesn't parse,
+        // isn't worth reporting as an error
+        Ok(self
+            .definition(params.text_document.uri, params.position)
+            .ok()
+            .flatten()
+            .map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        let params = params.text_document_position_params;
+        Ok(self
+            .hover_text(params.text_document.uri, params.position)
+            .ok()
+            .flatten()
+            .map(|value| Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            }))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        let params = params.text_document_position;
+        Ok(self
+            .completion_items(params.text_document.uri, params.position)
+            .ok()
+            .map(|xs| CompletionResponse::Array(xs.into_iter().map(to_completion_item).collect())))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        Ok(Some(
+            self.code_actions(params.text_document.uri, params.range),
+        ))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let params = params.text_document_position;
+        Ok(self
+            .find_references(params.text_document.uri, params.position)
+            .ok())
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let position = params.text_document_position;
+        // Unlike the queries above, a rename that can't be done should be
+        // reported, so the user knows why nothing changed
+        self.rename_edit(
+            position.text_document.uri,
+            position.position,
+            &params.new_name,
+        )
+        .map_err(|e| jsonrpc::Error {
+            code: ErrorCode::InvalidParams,
+            message: e.to_string(),
+            data: None,
+        })
+    }
+
     async fn shutdown(&self) -> Result<()> {
         Ok(())
     }
@@ -116,9 +594,88 @@ pub async fn server(starlark: Context) {
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, messages) = LspService::new(|client| Backend { client, starlark });
+    let (service, messages) = LspService::new(|client| Backend {
+        client,
+        starlark,
+        documents: Mutex::new(HashMap::new()),
+    });
     Server::new(stdin, stdout)
         .interleave(messages)
         .serve(service)
         .await;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn change(
+        range: Option<((u64, u64), (u64, u64))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|(start, end)| {
+                Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1))
+            }),
+            range_length: None,
+            text: text.to_owned(),
+        }
+    }
+
+    fn apply(text: &str, changes: Vec<TextDocumentContentChangeEvent>) -> String {
+        let mut text = text.to_owned();
+        for x in changes {
+            apply_change(&mut text, x);
+        }
+        text
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let text = "aé😿b\nxyz";
+        let offset = |line, character| byte_offset(text, Position::new(line, character));
+        assert_eq!(offset(0, 0), 0);
+        // `é` is one UTF-16 code unit but two bytes
+        assert_eq!(offset(0, 2), 3);
+        // `😿` is a surrogate pair, so two UTF-16 code units and four bytes
+        assert_eq!(offset(0, 4), 7);
+        // The middle of a surrogate pair is the start of the character
+        assert_eq!(offset(0, 3), 3);
+        assert_eq!(offset(1, 1), 10);
+        // Past the end of a line or the document
+        assert_eq!(offset(0, 100), 8);
+        assert_eq!(offset(1, 100), text.len());
+        assert_eq!(offset(5, 0), text.len());
+    }
+
+    #[test]
+    fn test_apply_change() {
+        assert_eq!(apply("x = 1", vec![change(None, "y = 2")]), "y = 2");
+        assert_eq!(
+            apply("s = '😿'\nt = 1", vec![change(Some(((0, 7), (0, 7))), "!")]),
+            "s = '😿!'\nt = 1"
+        );
+        // A range past the end of a line stops at the end of the line
+        assert_eq!(
+            apply("ab\ncd", vec![change(Some(((0, 1), (0, 10))), "x")]),
+            "ax\ncd"
+        );
+        // A range past the end of the document appends
+        assert_eq!(
+            apply("ab", vec![change(Some(((3, 0), (4, 0))), "\nc")]),
+            "ab\nc"
+        );
+        // Each change applies to the text as left by the one before
+        assert_eq!(
+            apply(
+                "a = 1\nb = 2",
+                vec![
+                    change(Some(((0, 4), (0, 5))), "10"),
+                    change(Some(((1, 0), (1, 0))), "# 😿\n"),
+                    change(Some(((1, 4), (1, 4))), "!"),
+                ]
+            ),
+            "a = 10\n# 😿!\nb = 2"
+        );
+    }
+}
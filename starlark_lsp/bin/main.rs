@@ -23,17 +23,23 @@
 #![cfg_attr(feature = "custom_linter", allow(deprecated))] // :(
 #![cfg_attr(feature = "custom_linter", plugin(linter))]
 
-use crate::types::{LintMessage, Message, Severity};
+use crate::types::{LintMessage, Message};
 use anyhow::anyhow;
 use eval::Context;
 use gazebo::prelude::*;
 use itertools::Either;
 use rustyline::{error::ReadlineError, Editor};
+use starlark::{
+    analysis::{LintConfig, Severity},
+    debug::Coverage,
+};
 use std::{ffi::OsStr, fmt, fmt::Display, fs, path::PathBuf, sync::Arc};
 use structopt::{clap::AppSettings, StructOpt};
 use walkdir::WalkDir;
 
+mod dap;
 mod eval;
+mod index;
 mod lsp;
 mod types;
 
@@ -55,9 +61,39 @@ pub struct Args {
     #[structopt(long = "lsp", help = "Start an LSP server.")]
     lsp: bool,
 
+    #[structopt(long = "dap", help = "Start a DAP server, to debug a file.")]
+    dap: bool,
+
     #[structopt(long = "check", help = "Run checks and lints.")]
     check: bool,
 
+    #[structopt(
+        long = "fix",
+        help = "Fix the lints that have an obvious fix in place, implies `--check`."
+    )]
+    fix: bool,
+
+    #[structopt(long = "format", help = "Format the files in place.")]
+    format: bool,
+
+    #[structopt(
+        long = "format-check",
+        help = "Check the files are formatted, without changing them."
+    )]
+    format_check: bool,
+
+    #[structopt(
+        long = "lint",
+        help = "Change the severity of a lint, e.g. `unused-argument=off`."
+    )]
+    lint: Vec<String>,
+
+    #[structopt(
+        long = "coverage",
+        help = "Write which statements and branches ran, including in loaded files, to a file, as JSON if it ends in `.json`, otherwise as LCOV."
+    )]
+    coverage: Vec<PathBuf>,
+
     #[structopt(long = "info", help = "Show information about the code.")]
     info: bool,
 
@@ -73,6 +109,12 @@ pub struct Args {
     #[structopt(long = "prelude", help = "Files to load in advance.")]
     prelude: Vec<PathBuf>,
 
+    #[structopt(
+        long = "root",
+        help = "Directory that `//` paths in a `load` are relative to."
+    )]
+    root: Option<PathBuf>,
+
     #[structopt(
         long = "expression",
         short = "e",
@@ -196,12 +238,24 @@ fn main() -> anyhow::Result<()> {
         .as_ref()
         .map_or("bzl", |x| x.as_str())
         .trim_start_match('.');
+    let format = args.format || args.format_check;
+    let check = args.check || args.fix;
+    let mut lint_config = LintConfig::default();
+    for x in &args.lint {
+        lint_config.add(x)?;
+    }
     let mut ctx = Context::new(
-        args.check,
+        check,
         args.info,
-        !args.check && !args.info,
+        !check && !args.info && !format,
         &expand_dirs(ext, args.prelude).collect::<Vec<_>>(),
+        lint_config,
+        args.root,
+        ext,
     )?;
+    if !args.coverage.is_empty() {
+        ctx.coverage = Some(Coverage::new());
+    }
 
     let mut stats = Stats::default();
     for e in args.evaluate {
@@ -211,13 +265,23 @@ fn main() -> anyhow::Result<()> {
 
     for file in expand_dirs(ext, expand_args(args.files)?) {
         stats.increment_file();
-        drain(ctx.file(&file), args.json, &mut stats);
+        if format {
+            drain(ctx.format(&file, args.format_check), args.json, &mut stats);
+        } else if args.fix {
+            drain(ctx.fix(&file), args.json, &mut stats);
+        } else {
+            drain(ctx.file(&file), args.json, &mut stats);
+        }
     }
 
     if args.interactive {
         interactive(&ctx)?;
     }
 
+    for x in &args.coverage {
+        ctx.write_coverage(x)?;
+    }
+
     if args.lsp {
         ctx.check = true;
         ctx.info = false;
@@ -225,6 +289,10 @@ fn main() -> anyhow::Result<()> {
         tokio::runtime::Runtime::new()?.block_on(async {
             lsp::server(ctx).await;
         })
+    } else if args.dap {
+        dap::server(ctx)?;
+        // Anything else we print would be mistaken for part of the protocol
+        return Ok(());
     }
 
     if !args.json {
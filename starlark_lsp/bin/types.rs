@@ -16,36 +16,14 @@
  */
 
 use gazebo::prelude::*;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use starlark::{
-    analysis::{LineColSpan, Lint},
+    analysis::{LineColSpan, Lint, Severity},
+    debug::FileCoverage,
     errors::Diagnostic,
 };
 use std::fmt::{self, Display};
 
-/// A standardised set of severities.
-#[derive(Debug, Serialize, Dupe, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum Severity {
-    Error,
-    Warning,
-    // Not all severities are used right now
-    #[allow(dead_code)]
-    Advice,
-    Disabled,
-}
-
-impl Display for Severity {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(match self {
-            Severity::Error => "Error",
-            Severity::Warning => "Warning",
-            Severity::Advice => "Advice",
-            Severity::Disabled => "Disabled",
-        })
-    }
-}
-
 #[derive(Debug, Clone)]
 pub struct Message {
     pub path: String,
@@ -55,6 +33,8 @@ pub struct Message {
     pub description: String,
     /// The text referred to by span
     pub original: Option<String>,
+    /// Replacements of the text at each span which would fix the problem.
+    pub edits: Vec<(LineColSpan, String)>,
 }
 
 impl Display for Message {
@@ -88,6 +68,7 @@ impl Message {
                     name: "error".to_owned(),
                     description: format!("{:#}", message),
                     original: Some(original),
+                    edits: Vec::new(),
                 }
             }
             _ => Self {
@@ -97,23 +78,24 @@ impl Message {
                 name: "error".to_owned(),
                 description: format!("{:#}", x),
                 original: None,
+                edits: Vec::new(),
             },
         }
     }
 
-    pub fn from_lint(x: Lint) -> Self {
+    pub fn from_lint(x: Lint, severity: Severity) -> Self {
         Self {
             path: x.location.file.name().to_owned(),
             span: Some(LineColSpan::from_span_loc(&x.location)),
-            severity: if x.serious {
-                Severity::Warning
-            } else {
-                // Start with all non-serious errors disabled, and ramp up from there
-                Severity::Disabled
-            },
+            severity,
             name: x.short_name,
             description: x.problem,
             original: Some(x.original),
+            edits: x
+                .edits
+                .into_iter()
+                .map(|x| (LineColSpan::from_span_loc(&x.location), x.replacement))
+                .collect(),
         }
     }
 }
@@ -126,6 +108,7 @@ pub struct LintMessage {
     line: Option<usize>,
     char: Option<usize>,
     code: String,
+    #[serde(serialize_with = "serialize_severity")]
     severity: Severity,
     name: String,
     description: Option<String>,
@@ -133,6 +116,11 @@ pub struct LintMessage {
     original: Option<String>,
 }
 
+// Severities are written in lowercase, as they are given to `--lint`
+fn serialize_severity<S: Serializer>(x: &Severity, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&x.to_string().to_lowercase())
+}
+
 impl LintMessage {
     pub fn new(x: Message) -> Self {
         Self {
@@ -147,3 +135,60 @@ impl LintMessage {
         }
     }
 }
+
+/// How many statements or branches there are, and how many of them ran.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct CoverageCount {
+    total: usize,
+    covered: usize,
+}
+
+impl CoverageCount {
+    fn new(total: usize, covered: usize) -> Self {
+        Self { total, covered }
+    }
+
+    fn add(&mut self, x: Self) {
+        self.total += x.total;
+        self.covered += x.covered;
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCoverageReport {
+    path: String,
+    statements: CoverageCount,
+    branches: CoverageCount,
+    /// Lines are 1-based, as they are in LCOV.
+    missed_lines: Vec<usize>,
+}
+
+/// A JSON-deriving summary of coverage, with the totals over all files.
+#[derive(Debug, Clone, Serialize)]
+pub struct CoverageReport {
+    statements: CoverageCount,
+    branches: CoverageCount,
+    files: Vec<FileCoverageReport>,
+}
+
+impl CoverageReport {
+    pub fn new(xs: Vec<FileCoverage>) -> Self {
+        let files = xs.into_map(|x| FileCoverageReport {
+            path: x.path,
+            statements: CoverageCount::new(x.statements, x.statements_hit),
+            branches: CoverageCount::new(x.branches, x.branches_hit),
+            missed_lines: x.missed_lines.into_map(|x| x + 1),
+        });
+        let mut statements = CoverageCount::default();
+        let mut branches = CoverageCount::default();
+        for x in &files {
+            statements.add(x.statements);
+            branches.add(x.branches);
+        }
+        Self {
+            statements,
+            branches,
+            files,
+        }
+    }
+}
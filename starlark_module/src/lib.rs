@@ -116,6 +116,9 @@ use syn::*;
 ///
 /// All these functions interoperate properly with `dir()`, `getattr()` and `hasattr()`.
 ///
+/// Doc comments on functions and constants are recorded, and can be
+/// retrieved with `Globals::docstring`.
+///
 /// If a desired function name is also a Rust keyword, use the `r#` prefix, e.g. `r#type`.
 #[proc_macro_attribute]
 pub fn starlark_module(attr: TokenStream, input: TokenStream) -> TokenStream {
@@ -170,8 +173,50 @@ fn add_const(x: &ItemConst) -> proc_macro2::TokenStream {
     let name_str = x.ident.to_string();
     let ty = &x.ty;
     let value = &x.expr;
+    let docstring = set_docstring(&name_str, &x.attrs);
     quote! {
         globals_builder.set::<#ty>(#name_str, #value);
+        #docstring
+    }
+}
+
+// The text of the doc comments, without the lines rustdoc hides in code blocks
+fn get_docstring(attrs: &[Attribute]) -> Option<String> {
+    let mut res = Vec::new();
+    let mut in_code = false;
+    for x in attrs {
+        if let Ok(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Str(s),
+            ..
+        })) = x.parse_meta()
+        {
+            if !path.is_ident("doc") {
+                continue;
+            }
+            let line = s.value();
+            let line = line.strip_prefix(' ').unwrap_or(&line);
+            if line.starts_with("```") {
+                in_code = !in_code;
+            } else if in_code && (line == "#" || line.starts_with("# ")) {
+                continue;
+            }
+            res.push(line.to_owned());
+        }
+    }
+    if res.is_empty() {
+        None
+    } else {
+        Some(res.join("\n"))
+    }
+}
+
+fn set_docstring(name_str: &str, attrs: &[Attribute]) -> proc_macro2::TokenStream {
+    match get_docstring(attrs) {
+        None => quote! {},
+        Some(docstring) => quote! {
+            globals_builder.set_docstring(#name_str, #docstring);
+        },
     }
 }
 
@@ -196,6 +241,7 @@ fn add_function(func: &ItemFn) -> proc_macro2::TokenStream {
         ReturnType::Default => panic!("Function named '{}' must have a return type", name),
         ReturnType::Type(_, x) => quote! {#x},
     };
+    let docstring = set_docstring(name_str, &func.attrs);
     let body = &func.block;
     let args = func.sig.inputs.iter().map(Arg::new).collect::<Vec<_>>();
     let bind_args = args.map(bind_argument);
@@ -247,6 +293,7 @@ fn add_function(func: &ItemFn) -> proc_macro2::TokenStream {
             let mut signature = starlark::eval::Parameters::new(#name_str.to_owned());
             #( #signature )*
             #setter
+            #docstring
         }
     }
 }
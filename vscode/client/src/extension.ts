@@ -15,7 +15,7 @@
  * limitations under the License.
  */
 
-import { ExtensionContext } from 'vscode';
+import { DebugAdapterExecutable, ExtensionContext, debug } from 'vscode';
 import {
     LanguageClient,
     LanguageClientOptions,
@@ -42,8 +42,18 @@ export function activate(context: ExtensionContext) {
         clientOptions
     );
 
+    // Semantic tokens are still a proposed feature in this version of the client
+    client.registerProposedFeatures();
+
     // Start the client. This will also launch the server
     client.start();
+
+    // The same binary is also the debugger, started afresh for each session
+    context.subscriptions.push(
+        debug.registerDebugAdapterDescriptorFactory('starlark', {
+            createDebugAdapterDescriptor: () => new DebugAdapterExecutable('starlark', ['--dap']),
+        })
+    );
 }
 
 export function deactivate(): Thenable<void> | undefined {